@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::{Lit, Solver};
+
+// =========================================================================
+// And-Inverter Graphs and their Tseitin Encoding
+// =========================================================================
+
+const NONE: usize = usize::MAX;
+
+// Polarities of a node whose definition is encoded: clauses for "node true" and "node false"
+const POSITIVE: u8 = 1;
+const NEGATIVE: u8 = 2;
+const BOTH: u8 = POSITIVE | NEGATIVE;
+
+/// An edge of an `Aig`: a node, possibly complemented. Node 0 is the constant false.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AigLit(u32);
+
+impl AigLit {
+    pub const FALSE: AigLit = AigLit(0);
+    pub const TRUE: AigLit = AigLit(1);
+
+    fn new(node: usize, is_negated: bool) -> Self {
+        AigLit(((node as u32) << 1) | (is_negated as u32))
+    }
+
+    /// Index of the node in its `Aig`.
+    pub fn node(&self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_neg(&self) -> bool {
+        (self.0 & 1) != 0
+    }
+
+    pub fn not(&self) -> Self {
+        AigLit(self.0 ^ 1)
+    }
+
+    /// The value of the edge given the values of all nodes (see `Aig::simulate`).
+    pub fn value(&self, values: &[bool]) -> bool {
+        values[self.node()] != self.is_neg()
+    }
+}
+
+impl fmt::Display for AigLit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}n{}", if self.is_neg() { "-" } else { "" }, self.node())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Node {
+    False,
+    Input,
+    And(AigLit, AigLit),
+}
+
+/// An and-inverter graph for building problems in Rust instead of writing DIMACS text.
+/// Every helper reduces to two-input ANDs of edges, which are hash-consed (an AND of the same two
+/// edges is created once) and simplified with constants and trivial cases ('a & a', 'a & -a'),
+/// so nodes are always in topological order.
+#[derive(Debug, Clone)]
+pub struct Aig {
+    nodes: Vec<Node>,
+    inputs: Vec<usize>,
+    strash: HashMap<(AigLit, AigLit), usize>, // Ordered inputs of an AND -> its node
+}
+
+impl Default for Aig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Aig {
+    pub fn new() -> Self {
+        Aig { nodes: vec![Node::False], inputs: Vec::new(), strash: HashMap::new() }
+    }
+
+    /// Adds a fresh input.
+    pub fn input(&mut self) -> AigLit {
+        self.nodes.push(Node::Input);
+        self.inputs.push(self.nodes.len() - 1);
+        AigLit::new(self.nodes.len() - 1, false)
+    }
+
+    /// The input nodes in the order they were created.
+    pub fn inputs(&self) -> &[usize] {
+        &self.inputs
+    }
+
+    /// Number of nodes, the constant and the inputs included.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of AND nodes.
+    pub fn num_ands(&self) -> usize {
+        self.nodes.len() - self.inputs.len() - 1
+    }
+
+    /// The inputs of 'node' if it is an AND.
+    pub fn and_inputs(&self, node: usize) -> Option<(AigLit, AigLit)> {
+        match self.nodes[node] {
+            Node::And(a, b) => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    pub fn and(&mut self, a: AigLit, b: AigLit) -> AigLit {
+        if a == AigLit::FALSE || b == AigLit::FALSE || a == b.not() { return AigLit::FALSE; }
+        if a == AigLit::TRUE || a == b { return b; }
+        if b == AigLit::TRUE { return a; }
+        let key = (a.min(b), a.max(b));
+        if let Some(&node) = self.strash.get(&key) { return AigLit::new(node, false); }
+        self.nodes.push(Node::And(key.0, key.1));
+        self.strash.insert(key, self.nodes.len() - 1);
+        AigLit::new(self.nodes.len() - 1, false)
+    }
+
+    pub fn or(&mut self, a: AigLit, b: AigLit) -> AigLit {
+        self.and(a.not(), b.not()).not()
+    }
+
+    pub fn xor(&mut self, a: AigLit, b: AigLit) -> AigLit {
+        let only_a = self.and(a, b.not());
+        let only_b = self.and(a.not(), b);
+        self.or(only_a, only_b)
+    }
+
+    /// 'then_lit' if 'condition' is true, else 'else_lit'.
+    pub fn ite(&mut self, condition: AigLit, then_lit: AigLit, else_lit: AigLit) -> AigLit {
+        if then_lit == else_lit { return then_lit; }
+        let then_part = self.and(condition, then_lit);
+        let else_part = self.and(condition.not(), else_lit);
+        self.or(then_part, else_part)
+    }
+
+    /// True iff at least two of 'a', 'b' and 'c' are true.
+    pub fn majority(&mut self, a: AigLit, b: AigLit, c: AigLit) -> AigLit {
+        let b_or_c = self.or(b, c);
+        let b_and_c = self.and(b, c);
+        let with_a = self.and(a, b_or_c);
+        self.or(with_a, b_and_c)
+    }
+
+    /// The conjunction of 'lits' (true if there are none), as a balanced tree.
+    pub fn and_all(&mut self, lits: &[AigLit]) -> AigLit {
+        match lits.len() {
+            0 => AigLit::TRUE,
+            1 => lits[0],
+            n => {
+                let left = self.and_all(&lits[..n / 2]);
+                let right = self.and_all(&lits[n / 2..]);
+                self.and(left, right)
+            }
+        }
+    }
+
+    /// The disjunction of 'lits' (false if there are none), as a balanced tree.
+    pub fn or_all(&mut self, lits: &[AigLit]) -> AigLit {
+        let negated: Vec<AigLit> = lits.iter().map(|l| l.not()).collect();
+        self.and_all(&negated).not()
+    }
+
+    /// The value of every node when input number i (see `inputs`) has value 'inputs[i]'.
+    pub fn simulate(&self, inputs: &[bool]) -> Vec<bool> {
+        let mut values = vec![false; self.nodes.len()];
+        for (&node, &value) in self.inputs.iter().zip(inputs) { values[node] = value; }
+        for node in 0..self.nodes.len() {
+            if let Node::And(a, b) = self.nodes[node] { values[node] = a.value(&values) && b.value(&values); }
+        }
+        values
+    }
+}
+
+/// How `AigEncoder` turns AND nodes into clauses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AigEncoding {
+    /// Both directions of every definition: the variable of a node always equals its value.
+    #[default]
+    Tseitin,
+    /// Only the directions needed by the polarities in which a node is used. Models may give the
+    /// variables of nodes other values than the circuit does, so read them with `evaluate`.
+    PlaistedGreenbaum,
+}
+
+/// Encodes the cones of edges of an `Aig` into a `Solver`, giving every node one variable.
+/// It remembers what it encoded, so it can be used again as the graph grows (nodes shared with
+/// earlier edges are not encoded twice). Like `Solver::add_clause`, it must be called at
+/// decision level 0. Between `solve` calls, the solver brings back the definitions that
+/// simplifications removed once new clauses or assumptions use their variables.
+#[derive(Debug, Clone, Default)]
+pub struct AigEncoder {
+    pub encoding: AigEncoding,
+    vars: Vec<usize>,  // Node -> solver variable (NONE if not encoded yet)
+    encoded: Vec<u8>,  // Node -> polarities whose clauses were added
+}
+
+impl AigEncoder {
+    pub fn new(encoding: AigEncoding) -> Self {
+        AigEncoder { encoding, vars: Vec::new(), encoded: Vec::new() }
+    }
+
+    /// The solver variable of 'node', if it was encoded.
+    pub fn var(&self, node: usize) -> Option<usize> {
+        self.vars.get(node).copied().filter(|&v| v != NONE)
+    }
+
+    /// Encodes the cone of 'edge' for both of its values and returns its solver literal,
+    /// e.g. for use as an assumption.
+    pub fn lit(&mut self, aig: &Aig, solver: &mut Solver, edge: AigLit) -> Lit {
+        self.encode(aig, solver, edge, BOTH)
+    }
+
+    /// Adds the constraint "'edge' is true" to the solver. Returns false if the formula became UNSAT.
+    pub fn assert(&mut self, aig: &Aig, solver: &mut Solver, edge: AigLit) -> bool {
+        let lit = self.encode(aig, solver, edge, POSITIVE);
+        solver.add_clause(vec![lit])
+    }
+
+    /// The value of every node of 'aig' under the inputs of a solver model (inputs that were never
+    /// encoded are false). See `Aig::simulate` and `AigLit::value`.
+    pub fn evaluate(&self, aig: &Aig, model: &[bool]) -> Vec<bool> {
+        let inputs: Vec<bool> = aig.inputs().iter().map(|&node| self.var(node).is_some_and(|v| model[v])).collect();
+        aig.simulate(&inputs)
+    }
+
+    /// The solver variable of 'node', created on first use (the constant node is fixed to false).
+    fn var_of(&mut self, solver: &mut Solver, node: usize) -> usize {
+        if self.vars[node] == NONE {
+            self.vars[node] = solver.new_var();
+            if node == 0 { solver.add_clause(vec![Lit::new(self.vars[node], true)]); }
+        }
+        self.vars[node]
+    }
+
+    /// Adds the clauses of the cone of 'root' needed for its values in 'need' (relative to the
+    /// edge), walking the nodes with a stack. Tseitin encoding always needs both values.
+    fn encode(&mut self, aig: &Aig, solver: &mut Solver, root: AigLit, need: u8) -> Lit {
+        self.vars.resize(aig.num_nodes(), NONE);
+        self.encoded.resize(aig.num_nodes(), 0);
+        let need_of = |edge: AigLit, need: u8| if edge.is_neg() { ((need & POSITIVE) << 1) | ((need & NEGATIVE) >> 1) } else { need };
+        let full = self.encoding == AigEncoding::Tseitin;
+
+        let mut stack = vec![(root.node(), if full { BOTH } else { need_of(root, need) })];
+        while let Some((node, need)) = stack.pop() {
+            let missing = need & !self.encoded[node];
+            if missing == 0 { continue; }
+            self.encoded[node] |= missing;
+            let output = Lit::new(self.var_of(solver, node), false);
+            let Some((a, b)) = aig.and_inputs(node) else { continue };
+            let la = Lit::new(self.var_of(solver, a.node()), a.is_neg());
+            let lb = Lit::new(self.var_of(solver, b.node()), b.is_neg());
+            if missing & POSITIVE != 0 {
+                // node -> a & b
+                solver.add_clause(vec![output.not(), la]);
+                solver.add_clause(vec![output.not(), lb]);
+            }
+            if missing & NEGATIVE != 0 {
+                // a & b -> node
+                solver.add_clause(vec![output, la.not(), lb.not()]);
+            }
+            for input in [a, b] {
+                stack.push((input.node(), if full { BOTH } else { need_of(input, missing) }));
+            }
+        }
+        Lit::new(self.var_of(solver, root.node()), root.is_neg())
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::bignum::BigUint;
+use crate::preprocessing::{BitRow, XorConstraint};
+use crate::{Formula, RandomStrategy, Rng, Solver};
+
+// =========================================================================
+// Approximate Model Counting: ApproxMC with Random XOR Hashing
+// =========================================================================
+
+/// Tolerance and confidence of `approx_count`.
+#[derive(Debug, Clone)]
+pub struct ApproxMcOptions {
+    /// The estimate is within a factor 1 + epsilon of the count ...
+    pub epsilon: f64,
+    /// ... with probability at least 1 - delta.
+    pub delta: f64,
+    /// Seed of the random hash functions.
+    pub seed: u64,
+}
+
+impl Default for ApproxMcOptions {
+    fn default() -> Self {
+        ApproxMcOptions { epsilon: 0.8, delta: 0.2, seed: 1 }
+    }
+}
+
+/// Estimates the number of projections of the models of 'formula' (clauses and XORs) onto its
+/// projection variables, the sampling set (all variables if it has none).
+///
+/// Random XORs over the sampling set cut the solution space into cells. Per round, a prefix of
+/// m XORs of a fresh random hash is chosen so that the solutions in the cell are fewer than a
+/// threshold, counted by enumeration; the round estimates the count by cell size · 2^m, and the
+/// median of the rounds is returned. Counts below the threshold are exact.
+pub fn approx_count(formula: &Formula, options: &ApproxMcOptions) -> BigUint {
+    let sampling: Vec<usize> = formula.projection.clone().unwrap_or_else(|| (0..formula.num_vars).collect());
+    let epsilon = options.epsilon;
+    let threshold = (1.0 + 9.84 * (1.0 + epsilon / (1.0 + epsilon)) * (1.0 + 1.0 / epsilon).powi(2)).ceil() as usize;
+    let rounds = (17.0 * (3.0 / options.delta).log2()).ceil() as usize;
+
+    let count = cell_count(formula, &sampling, &[], threshold);
+    if count < threshold { return BigUint::from(count as u64); }
+
+    let mut rng = Rng::new(options.seed);
+    let mut estimates = Vec::with_capacity(rounds);
+    let mut m = 1;
+    for _ in 0..rounds {
+        let hash = random_hash(&sampling, &mut rng);
+        // Cells shrink as XORs are added: search the first m whose cell is below the threshold,
+        // starting from the one of the previous round
+        let mut counts: HashMap<usize, usize> = HashMap::new();
+        let mut count = |m: usize| *counts.entry(m).or_insert_with(|| cell_count(formula, &sampling, &hash[..m], threshold));
+        if count(m) < threshold {
+            while m > 1 && count(m - 1) < threshold { m -= 1; }
+        } else {
+            while m < hash.len() && count(m) >= threshold { m += 1; }
+        }
+        estimates.push(&BigUint::from(count(m) as u64) << m);
+    }
+    estimates.sort();
+    estimates.swap_remove(estimates.len() / 2)
+}
+
+/// One row per sampling variable: each variable joins a row with probability 1/2,
+/// and the right-hand sides are random.
+pub(crate) fn random_hash(sampling: &[usize], rng: &mut Rng) -> Vec<XorConstraint> {
+    (0..sampling.len()).map(|_| {
+        let mut row = BitRow::new(sampling.len(), rng.coin());
+        for column in 0..sampling.len() {
+            if rng.coin() { row.set_bit(column); }
+        }
+        XorConstraint { vars: row.ones().map(|column| sampling[column]).collect(), rhs: row.rhs }
+    }).collect()
+}
+
+/// Number of distinct projections onto 'sampling' of the models satisfying the 'hash' XORs too,
+/// enumerated up to 'threshold'.
+fn cell_count(formula: &Formula, sampling: &[usize], hash: &[XorConstraint], threshold: usize) -> usize {
+    cell_models(formula, sampling, hash, threshold).len()
+}
+
+/// Models of 'formula' satisfying the 'hash' XORs, one per projection onto 'sampling', up to 'limit'.
+pub(crate) fn cell_models(formula: &Formula, sampling: &[usize], hash: &[XorConstraint], limit: usize) -> Vec<Vec<bool>> {
+    let mut solver = Solver::new(formula.num_vars);
+    for clause in &formula.clauses {
+        solver.add_clause(clause.clone());
+    }
+    for xor in formula.xors.iter().chain(hash) {
+        solver.add_xor(xor.clone());
+    }
+    let mut strategy = RandomStrategy::new(formula.num_vars);
+    solver.models(Some(sampling), &mut strategy).decision_blocking(true).take(limit).collect()
+}
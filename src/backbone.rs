@@ -0,0 +1,102 @@
+use crate::preprocessing::apply_replacements;
+use crate::{Formula, Lit, RandomStrategy, Solver, VarValue};
+
+// =========================================================================
+// Backbones: Literals True in Every Model
+// =========================================================================
+
+/// How `backbone` tests the candidate literals left after the first model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackboneAlgorithm {
+    /// One candidate at a time: solving under its negation either proves it or gives a model
+    /// that rules out every candidate it falsifies.
+    #[default]
+    Iterative,
+    /// Up to k candidates at a time, under a clause (enabled by an activation literal) demanding
+    /// that one of them is false: unsatisfiable proves them all.
+    Chunked(usize),
+    /// All candidates at once, assuming their negations: a core of a single literal proves it,
+    /// a larger core is halved and tried again.
+    CoreBased,
+}
+
+/// Computes the backbone of 'formula' (clauses and XORs): the literals true in every model,
+/// sorted by variable. Returns None if it is unsatisfiable.
+///
+/// The candidates are the literals of a first model; every later model filters them, and
+/// literals the solver fixes at the root (by propagation or the proven backbone
+/// literals, which join the formula as units) are accepted without a search.
+pub fn backbone(formula: &Formula, algorithm: BackboneAlgorithm) -> Option<Vec<Lit>> {
+    let mut solver = Solver::new(formula.num_vars);
+    for clause in &formula.clauses {
+        solver.add_clause(clause.clone());
+    }
+    for xor in &formula.xors {
+        solver.add_xor(xor.clone());
+    }
+    let mut strategy = RandomStrategy::new(formula.num_vars);
+    let mut solve = |solver: &mut Solver, assumptions: &[Lit]| solver.solve_with_assumptions(assumptions, &mut strategy, false);
+
+    if !solve(&mut solver, &[]) { return None; }
+    let model = solver.model();
+    let mut candidates: Vec<Lit> = (0..formula.num_vars).map(|var| Lit::new(var, !model[var])).collect();
+    let mut backbone = Vec::new();
+    loop {
+        candidates.retain(|&lit| {
+            let fixed = root_value(&solver, lit) == VarValue::True;
+            if fixed { backbone.push(lit); }
+            !fixed
+        });
+        let Some(&last) = candidates.last() else { break };
+
+        // Each step proves candidates, which become units, or finds a model
+        let sat = match algorithm {
+            BackboneAlgorithm::Iterative => {
+                let sat = solve(&mut solver, &[last.not()]);
+                if !sat { solver.add_clause(vec![last]); }
+                sat
+            }
+            BackboneAlgorithm::Chunked(size) => {
+                let chunk = candidates[candidates.len().saturating_sub(size.max(1))..].to_vec();
+                let activation = Lit::new(solver.new_var(), false);
+                let mut clause: Vec<Lit> = chunk.iter().map(|l| l.not()).collect();
+                clause.push(activation.not());
+                solver.add_clause(clause);
+                let sat = solve(&mut solver, &[activation]);
+                solver.add_clause(vec![activation.not()]);
+                if !sat {
+                    for lit in chunk { solver.add_clause(vec![lit]); }
+                }
+                sat
+            }
+            BackboneAlgorithm::CoreBased => {
+                let mut tested = candidates.clone();
+                loop {
+                    let assumptions: Vec<Lit> = tested.iter().map(|l| l.not()).collect();
+                    if solve(&mut solver, &assumptions) { break true; }
+                    let core: Vec<Lit> = tested.iter().copied().filter(|l| solver.core().contains(&l.not())).collect();
+                    match core.len() {
+                        0 => tested.truncate(1), // Not expected: the formula is satisfiable
+                        1 => {
+                            solver.add_clause(core);
+                            break false;
+                        }
+                        n => tested = core[..n / 2].to_vec(),
+                    }
+                }
+            }
+        };
+        if sat {
+            let model = solver.model();
+            candidates.retain(|l| model[l.var()] != l.is_neg());
+        }
+    }
+    backbone.sort_by_key(|l| l.var());
+    Some(backbone)
+}
+
+/// The value of 'lit' at the root of the solver, through the substitutions of preprocessing.
+fn root_value(solver: &Solver, lit: Lit) -> VarValue {
+    let lit = apply_replacements(&[lit], &solver.replacements)[0];
+    Solver::value_lit(&solver.assignments, lit)
+}
@@ -0,0 +1,281 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Shl};
+use std::str::FromStr;
+
+// =========================================================================
+// Arbitrary-Precision Numbers for Model Counts
+// =========================================================================
+
+/// An unsigned integer of any size (little-endian base 2^32 limbs, no leading zero limbs).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        BigUint { limbs: Vec::new() }
+    }
+
+    pub fn one() -> Self {
+        BigUint::from(1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn normalize(mut self) -> Self {
+        while self.limbs.last() == Some(&0) { self.limbs.pop(); }
+        self
+    }
+
+    /// self · 'factor' + 'addend'.
+    fn mul_add_small(&self, factor: u32, addend: u32) -> Self {
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        let mut carry = addend as u64;
+        for &limb in &self.limbs {
+            let x = limb as u64 * factor as u64 + carry;
+            limbs.push(x as u32);
+            carry = x >> 32;
+        }
+        limbs.push(carry as u32);
+        BigUint { limbs }.normalize()
+    }
+
+    /// Quotient and remainder of the division by 'divisor' (not zero).
+    fn div_rem_small(&self, divisor: u32) -> (Self, u32) {
+        let mut limbs = vec![0; self.limbs.len()];
+        let mut rem = 0u64;
+        for (i, &limb) in self.limbs.iter().enumerate().rev() {
+            let x = (rem << 32) | limb as u64;
+            limbs[i] = (x / divisor as u64) as u32;
+            rem = x % divisor as u64;
+        }
+        (BigUint { limbs }.normalize(), rem as u32)
+    }
+
+    /// self · 10^'exponent'.
+    pub fn mul_pow10(&self, exponent: u32) -> Self {
+        let mut result = self.clone();
+        for _ in 0..exponent / 9 { result = result.mul_add_small(1_000_000_000, 0); }
+        result.mul_add_small(10u32.pow(exponent % 9), 0)
+    }
+
+    /// Binary logarithm, from the leading 64 bits (negative infinity for zero).
+    pub fn log2(&self) -> f64 {
+        let top = self.limbs.iter().rev().take(2).fold(0f64, |x, &limb| x * 4294967296.0 + limb as f64);
+        let shifted = self.limbs.len().saturating_sub(2) * 32;
+        top.log2() + shifted as f64
+    }
+
+    /// self - 'other', or None if 'other' is larger.
+    pub fn checked_sub(&self, other: &BigUint) -> Option<BigUint> {
+        if *self < *other { return None; }
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for (i, &limb) in self.limbs.iter().enumerate() {
+            let mut x = limb as i64 - other.limbs.get(i).copied().unwrap_or(0) as i64 - borrow;
+            borrow = (x < 0) as i64;
+            if x < 0 { x += 1 << 32; }
+            limbs.push(x as u32);
+        }
+        Some(BigUint { limbs }.normalize())
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> Self {
+        BigUint { limbs: vec![value as u32, (value >> 32) as u32] }.normalize()
+    }
+}
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let (long, short) = if self.limbs.len() >= other.limbs.len() { (self, other) } else { (other, self) };
+        let mut limbs = Vec::with_capacity(long.limbs.len() + 1);
+        let mut carry = 0u64;
+        for (i, &limb) in long.limbs.iter().enumerate() {
+            let x = limb as u64 + short.limbs.get(i).copied().unwrap_or(0) as u64 + carry;
+            limbs.push(x as u32);
+            carry = x >> 32;
+        }
+        limbs.push(carry as u32);
+        BigUint { limbs }.normalize()
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() { return BigUint::zero(); }
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let x = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = x as u32;
+                carry = x >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigUint { limbs }.normalize()
+    }
+}
+
+impl Shl<usize> for &BigUint {
+    type Output = BigUint;
+
+    /// self · 2^'bits'.
+    fn shl(self, bits: usize) -> BigUint {
+        if self.is_zero() { return BigUint::zero(); }
+        let mut limbs = vec![0; bits / 32];
+        let offset = bits % 32;
+        let mut carry = 0u32;
+        for &limb in &self.limbs {
+            limbs.push((limb << offset) | carry);
+            carry = if offset == 0 { 0 } else { limb >> (32 - offset) };
+        }
+        limbs.push(carry);
+        BigUint { limbs }.normalize()
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Base 10^9 chunks, least significant first
+        let mut chunks = Vec::new();
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (quotient, chunk) = rest.div_rem_small(1_000_000_000);
+            chunks.push(chunk);
+            rest = quotient;
+        }
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, others)) => {
+                write!(f, "{}", first)?;
+                others.iter().rev().try_for_each(|chunk| write!(f, "{:09}", chunk))
+            }
+        }
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) { return Err(format!("invalid number '{}'", s)); }
+        Ok(s.bytes().fold(BigUint::zero(), |n, b| n.mul_add_small(10, (b - b'0') as u32)))
+    }
+}
+
+/// A non-negative decimal fraction: 'digits' / 10^'scale'. Used for exact weighted model counts.
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    digits: BigUint,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn zero() -> Self {
+        Decimal::from(BigUint::zero())
+    }
+
+    pub fn one() -> Self {
+        Decimal::from(BigUint::one())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_zero()
+    }
+
+    /// The digits for 'scale' decimal places (at least the own ones).
+    fn digits_at(&self, scale: u32) -> BigUint {
+        self.digits.mul_pow10(scale - self.scale)
+    }
+
+    /// self - 'other', or None if 'other' is larger.
+    pub fn checked_sub(&self, other: &Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        let digits = self.digits_at(scale).checked_sub(&other.digits_at(scale))?;
+        Some(Decimal { digits, scale })
+    }
+
+    /// The integer part.
+    pub fn trunc(&self) -> BigUint {
+        let mut digits = self.digits.clone();
+        for _ in 0..self.scale { digits = digits.div_rem_small(10).0; }
+        digits
+    }
+}
+
+impl From<BigUint> for Decimal {
+    fn from(digits: BigUint) -> Self {
+        Decimal { digits, scale: 0 }
+    }
+}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal { digits: &self.digits_at(scale) + &other.digits_at(scale), scale }
+    }
+}
+
+impl Mul for &Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: &Decimal) -> Decimal {
+        Decimal { digits: &self.digits * &other.digits, scale: self.scale + other.scale }
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        let scale = self.scale.max(other.scale);
+        self.digits_at(scale) == other.digits_at(scale)
+    }
+}
+
+impl Eq for Decimal {}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = format!("{:0>width$}", self.digits.to_string(), width = self.scale as usize + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - self.scale as usize);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() { write!(f, "{}", integer) } else { write!(f, "{}.{}", integer, fraction) }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    /// Parses "12", "0.25" or ".5".
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let digits = format!("{}{}", integer, fraction);
+        let digits = digits.parse::<BigUint>().map_err(|_| format!("invalid decimal '{}'", s))?;
+        Ok(Decimal { digits, scale: fraction.len() as u32 })
+    }
+}
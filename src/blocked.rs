@@ -0,0 +1,210 @@
+use std::collections::{HashSet, VecDeque};
+use crate::occurrences::ClauseDatabase;
+use crate::{Lit, Solver, VarValue};
+
+// =========================================================================
+// Blocked and Covered Clause Elimination
+// =========================================================================
+
+/// Clauses are only checked against occurrence lists up to this length.
+const MAX_OCCURRENCES: usize = 64;
+/// Covered literal addition stops once a clause has grown to this many literals.
+const MAX_COVERED_SIZE: usize = 64;
+/// Number of resolution partners visited during covered literal addition before we fall back
+/// to plain blocked clause elimination (most candidates do not end up covered).
+const COVERED_BUDGET: usize = 2_000_000;
+
+/// Counters of a single elimination round (reported in verbose mode).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlockedStats {
+    pub blocked: usize, // Removed because all resolvents on a literal are tautologies
+    pub covered: usize, // Removed after covered literal addition made them blocked (or tautological)
+}
+
+impl Solver {
+    /// Blocked clause elimination, optionally extended by covered literal addition.
+    ///
+    /// A clause C is blocked on l in C if every resolvent with a clause containing -l is a tautology.
+    /// Covered literal addition (CLA) extends C by the literals shared by all non-tautological
+    /// resolution partners on l. This preserves satisfiability, and on Tseitin encoded circuits
+    /// it frequently turns a clause into a blocked one, because gate definitions are "covered"
+    /// by the clauses of the gates they feed into.
+    ///
+    /// Every removed clause is pushed onto the reconstruction stack (together with the
+    /// intermediate CLA steps), so models can be repaired afterwards. Clauses, constraints and
+    /// assumptions added later bring back the removed clauses they could conflict with.
+    /// Literals on variables of native constraints are never used as witnesses.
+    /// Must be called at decision level 0.
+    pub fn eliminate_blocked_clauses(&mut self, covered: bool) -> BlockedStats {
+        let mut stats = BlockedStats::default();
+        self.with_clause_database(|solver, db| {
+            stats = solver.eliminate_blocked_clauses_in(db, covered);
+            stats.blocked + stats.covered > 0
+        });
+        stats
+    }
+
+    /// `eliminate_blocked_clauses` on a clause database shared with other passes
+    /// (see `PassContext`).
+    pub(crate) fn eliminate_blocked_clauses_in(&mut self, db: &mut ClauseDatabase, covered: bool) -> BlockedStats {
+        let mut stats = BlockedStats::default();
+
+        // Worklist of clauses to check. Removing a clause D can only make clauses
+        // containing the negation of one of D's literals blocked, so only those are re-queued.
+        let mut queue: VecDeque<usize> = (0..db.clauses.len()).filter(|&i| db.clauses[i].is_some()).collect();
+        let mut queued = vec![true; db.clauses.len()];
+        let mut marks = vec![false; self.num_vars * 2];
+        let mut budget = if covered { COVERED_BUDGET } else { 0 };
+        let frozen = self.frozen_variables();
+
+        while let Some(idx) = queue.pop_front() {
+            queued[idx] = false;
+            let Some(steps) = db.find_covering_steps(idx, &frozen, &mut marks, &mut budget) else { continue };
+            if steps.len() == 1 { stats.blocked += 1; } else { stats.covered += 1; }
+            for (i, (witness, clause)) in steps.into_iter().enumerate() {
+                if i == 0 { self.reconstruction.push(witness, clause); } else { self.reconstruction.push_step(witness, clause); }
+            }
+
+            let removed = db.remove(idx);
+            for &l in &removed {
+                if db.live[l.to_usize()] > MAX_OCCURRENCES { continue; } // Still too many partners to check
+                for &other in &db.occurs[l.not().to_usize()] {
+                    if !queued[other] && db.clauses[other].is_some() {
+                        queued[other] = true;
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+        stats
+    }
+
+    /// Blocked clause addition for binary clauses.
+    ///
+    /// Plaisted-Greenbaum style encodings often contain only one direction 'p -> q' of an
+    /// equivalence, i.e. the clause (-p v q). The converse (p v -q) is blocked on -q whenever every
+    /// clause containing q also contains p, and adding a blocked clause preserves satisfiability
+    /// (every model of the extended formula is a model of the original one, so no reconstruction
+    /// is needed). The new binaries close cycles in the implication graph for equivalent literal
+    /// substitution. Returns the number of added clauses. Must be called at decision level 0.
+    ///
+    /// The added clauses are not implied by the formula, and neither are the equivalences they
+    /// close, so clauses added or literals assumed afterwards could make a satisfiable formula
+    /// unsatisfiable. Only the "bca" pass calls this, and only before the first `solve`.
+    pub(crate) fn add_blocked_binaries(&mut self) -> usize {
+        if !self.ok || self.decision_level() != 0 { return 0; }
+
+        // Occurrence lists of clauses that are not satisfied at the root
+        let mut occurs: Vec<Vec<usize>> = vec![Vec::new(); self.num_vars * 2];
+        for (i, c) in self.clauses.iter().enumerate() {
+            if c.lits.iter().any(|&l| Self::value_lit(&self.assignments, l) == VarValue::True) { continue; }
+            for &l in &c.lits {
+                if Self::value_lit(&self.assignments, l) == VarValue::Unassigned { occurs[l.to_usize()].push(i); }
+            }
+        }
+
+        let frozen = self.frozen_variables();
+        let mut existing: HashSet<[Lit; 2]> = HashSet::new();
+        let binaries = self.root_binary_clauses();
+        for &[p, q] in &binaries { existing.insert(sorted_pair(p, q)); }
+
+        let mut added = 0;
+        for [p, q] in binaries {
+            // Candidate: the converse (-p v -q) of the binary clause (p v q)
+            let candidate = sorted_pair(p.not(), q.not());
+            if existing.contains(&candidate) || frozen[p.var()] || frozen[q.var()] { continue; }
+
+            // Blocked on -p iff every clause containing p also contains q (and symmetrically).
+            // The occurrence lists include the clauses added so far, so every addition
+            // is checked against the formula it is actually added to.
+            let contained_in_all = |lit: Lit, other: Lit| -> bool {
+                occurs[lit.to_usize()].len() <= MAX_OCCURRENCES
+                    && occurs[lit.to_usize()].iter().all(|&i| self.clauses[i].lits.contains(&other))
+            };
+            let blocked_on = if contained_in_all(p, q) {
+                p.not()
+            } else if contained_in_all(q, p) {
+                q.not()
+            } else {
+                continue;
+            };
+
+            // A proof checks the clause as a RAT on its first literal
+            let idx = self.clauses.len();
+            self.log_add(&[blocked_on, if blocked_on == p.not() { q.not() } else { p.not() }]);
+            if !self.add_clause_internal(candidate.to_vec(), false) { break; }
+            existing.insert(candidate);
+            added += 1;
+            if self.clauses.len() > idx {
+                occurs[p.not().to_usize()].push(idx);
+                occurs[q.not().to_usize()].push(idx);
+            }
+        }
+        added
+    }
+}
+
+impl ClauseDatabase {
+    /// Tries to show that clause 'idx' is blocked, extending it by covered literals while 'budget' lasts.
+    /// Returns the reconstruction entries (witness, clause) in the order they must be pushed:
+    /// first every CLA step, then the final blocked clause.
+    fn find_covering_steps(&self, idx: usize, frozen: &[bool], marks: &mut [bool], budget: &mut usize) -> Option<Vec<(Lit, Vec<Lit>)>> {
+        let mut extended = self.clauses[idx].clone()?;
+        if extended.len() < 2 { return None; }
+        let covered = *budget > 0;
+        for &l in &extended { marks[l.to_usize()] = true; }
+
+        let mut steps = Vec::new();
+        let mut result = None;
+        let mut i = 0;
+        'extend: while i < extended.len() {
+            let l = extended[i];
+            i += 1;
+            if frozen[l.var()] || self.live[l.not().to_usize()] > MAX_OCCURRENCES { continue; }
+            let partners = &self.occurs[l.not().to_usize()];
+            if covered { *budget = budget.saturating_sub(partners.len()); }
+
+            // Intersection of all non-tautological resolution partners (without -l)
+            let mut shared: Option<Vec<Lit>> = None;
+            for &p in partners {
+                if p == idx { continue; }
+                let Some(other) = &self.clauses[p] else { continue };
+                if other.iter().any(|&m| m != l.not() && marks[m.not().to_usize()]) { continue; } // Tautology
+                let rest = other.iter().copied().filter(|&m| m != l.not() && !marks[m.to_usize()]);
+                shared = Some(match shared {
+                    None => rest.collect(),
+                    Some(prev) => {
+                        let rest: Vec<Lit> = rest.collect();
+                        prev.into_iter().filter(|m| rest.contains(m)).collect()
+                    }
+                });
+                if !covered && shared.is_some() { continue 'extend; }
+            }
+
+            match shared {
+                None => {
+                    // Blocked on 'l'
+                    steps.push((l, extended.clone()));
+                    result = Some(steps);
+                    break;
+                }
+                Some(new_lits) if covered && !new_lits.is_empty() && extended.len() < MAX_COVERED_SIZE => {
+                    steps.push((l, extended.clone()));
+                    for m in new_lits {
+                        marks[m.to_usize()] = true;
+                        extended.push(m);
+                    }
+                    // The new literals are appended, so they get their own turn below
+                }
+                Some(_) => {}
+            }
+        }
+
+        for &l in &extended { marks[l.to_usize()] = false; }
+        result
+    }
+}
+
+fn sorted_pair(a: Lit, b: Lit) -> [Lit; 2] {
+    if a.to_usize() <= b.to_usize() { [a, b] } else { [b, a] }
+}
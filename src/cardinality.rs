@@ -0,0 +1,476 @@
+use crate::occurrences::ClauseDatabase;
+use crate::preprocessing::apply_replacements;
+use crate::{BranchingStrategy, Lit, Reason, Solver, VarValue};
+
+// =========================================================================
+// Native Cardinality Constraints: Detection and Counter Propagation
+// =========================================================================
+
+/// Number of literals propagated while looking for cardinality constraints before the pass stops.
+const CARDINALITY_BUDGET: usize = 500_000;
+/// Smallest detected at-most-one constraints.
+const MIN_CLIQUE_SIZE: usize = 3;
+/// Largest bound of the detected at-most-k constraints.
+const MAX_DETECTED_BOUND: usize = 3;
+/// Largest number of literals of a detected at-most-k constraint with k > 1
+/// (a new literal is checked with every (k - 1)-subset of the others).
+const MAX_DETECTED_SIZE: usize = 32;
+
+/// At most 'bound' of 'lits' are true.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardinalityConstraint {
+    pub lits: Vec<Lit>,
+    pub bound: usize,
+}
+
+/// Counters of a detection round (reported in verbose mode).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CardinalityStats {
+    pub at_most_one: usize, // Detected at-most-one constraints
+    pub at_most_k: usize,   // Detected at-most-k constraints with k > 1
+    pub removed: usize,     // Clauses of their direct encodings removed
+}
+
+/// The native cardinality constraints during search (over representatives, without root literals).
+///
+/// Every constraint lists its true literals in the order propagation counted them.
+/// When the count reaches the bound, the other literals are implied false; one more is a conflict.
+/// Reasons are only generated when conflict analysis asks for them: the first 'bound' counted
+/// literals explain every implied literal, as they were counted before it was assigned and stay
+/// counted until it is unassigned again (backtracking uncounts in reverse order).
+#[derive(Debug, Default)]
+pub(crate) struct CardinalityPropagator {
+    constraints: Vec<CardinalityConstraint>,
+    counted: Vec<Vec<Lit>>,  // Constraint -> its true literals in counting order
+    occurs: Vec<Vec<usize>>, // Literal -> constraints containing it
+}
+
+/// Literal-indexed marks of the detection, cleared after every use.
+struct Scratch {
+    degree: Vec<usize>, // Number of literals a candidate excludes
+    taken: Vec<bool>,   // In a clique of the current search
+    open: Vec<bool>,    // Can still extend the current clique
+    keep: Vec<bool>,
+    seen: Vec<bool>,
+}
+
+impl Scratch {
+    fn new(num_vars: usize) -> Self {
+        let marks = vec![false; num_vars * 2];
+        Scratch { degree: vec![0; num_vars * 2], taken: marks.clone(), open: marks.clone(), keep: marks.clone(), seen: marks }
+    }
+}
+
+impl CardinalityPropagator {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Uncounts 'lit', which was counted by `Solver::propagate_cardinalities` and is being unassigned.
+    #[inline]
+    pub(crate) fn unassigned(&mut self, lit: Lit) {
+        let Some(list) = self.occurs.get(lit.to_usize()) else { return };
+        for &c in list {
+            self.counted[c].pop();
+        }
+    }
+}
+
+impl Solver {
+    /// Adds the cardinality constraint "at most 'bound' of 'lits' are true" to the formula.
+    /// It is kept as a native constraint propagated with a counter, or encoded into one clause
+    /// per (bound + 1)-subset of 'lits' while a proof is written.
+    /// Must be called at decision level 0. Returns false if the formula became UNSAT.
+    pub fn add_at_most(&mut self, lits: Vec<Lit>, bound: usize) -> bool {
+        if !self.restore_clauses(&lits) { return false; }
+        if self.proof.is_some() {
+            let mut ok = true;
+            for_each_subset(&lits, bound + 1, &mut |subset| {
+                if ok { ok = self.add_clause_internal(subset.iter().map(|l| l.not()).collect(), false); }
+            });
+            return ok;
+        }
+        let constraint = CardinalityConstraint { lits, bound };
+        self.cardinalities.push(constraint.clone());
+        self.attach_cardinality(&constraint)
+    }
+
+    /// Rebuilds the propagator from the cardinality constraints of the formula for the current
+    /// substitution and root assignment. Must be called at decision level 0.
+    pub(crate) fn attach_cardinalities(&mut self) {
+        self.cardinality = CardinalityPropagator::default();
+        for constraint in self.cardinalities.clone() {
+            if !self.attach_cardinality(&constraint) { return; }
+        }
+    }
+
+    /// Maps 'constraint' onto representatives, folds root assignments into its bound and
+    /// attaches what is left. Sets `ok = false` if it is already violated.
+    fn attach_cardinality(&mut self, constraint: &CardinalityConstraint) -> bool {
+        let mut bound = constraint.bound;
+        let mut lits = Vec::with_capacity(constraint.lits.len());
+        for lit in apply_replacements(&constraint.lits, &self.replacements) {
+            match Self::value_lit(&self.assignments, lit) {
+                VarValue::Unassigned => lits.push(lit),
+                VarValue::True if bound == 0 => {
+                    self.ok = false;
+                    return false;
+                }
+                VarValue::True => bound -= 1,
+                VarValue::False => {}
+            }
+        }
+        if lits.len() <= bound { return true; }
+        if bound == 0 {
+            for lit in lits {
+                if !self.add_clause_internal(vec![lit.not()], false) { return false; }
+            }
+            return true;
+        }
+
+        // A literal occurring twice (after substitution) simply counts twice
+        lits.sort_by_key(|l| l.to_usize());
+        let propagator = &mut self.cardinality;
+        let c = propagator.constraints.len();
+        if propagator.occurs.len() < self.num_vars * 2 { propagator.occurs.resize(self.num_vars * 2, Vec::new()); }
+        for &l in &lits { propagator.occurs[l.to_usize()].push(c); }
+        propagator.counted.push(Vec::new());
+        propagator.constraints.push(CardinalityConstraint { lits, bound });
+        true
+    }
+
+    /// Counts the true literal 'p' in all cardinality constraints containing it and implies
+    /// the other literals of those that reach their bound. Returns a violated constraint.
+    #[inline]
+    pub(crate) fn propagate_cardinalities(&mut self, p: Lit) -> Option<Reason> {
+        let list = match self.cardinality.occurs.get_mut(p.to_usize()) {
+            Some(list) if !list.is_empty() => std::mem::take(list),
+            _ => return None,
+        };
+
+        // Every constraint counts 'p' even after a conflict, so that backtracking can uncount it
+        let mut conflict = None;
+        for &c in &list {
+            let counted = &mut self.cardinality.counted[c];
+            counted.push(p);
+            let count = counted.len();
+            let bound = self.cardinality.constraints[c].bound;
+            if count == bound {
+                for i in 0..self.cardinality.constraints[c].lits.len() {
+                    let lit = self.cardinality.constraints[c].lits[i];
+                    if self.assignments[lit.var()] == VarValue::Unassigned {
+                        self.unchecked_enqueue(lit.not(), Some(Reason::Cardinality(c)));
+                    }
+                }
+            } else if count == bound + 1 && conflict.is_none() {
+                conflict = Some(Reason::Cardinality(c));
+            }
+        }
+        self.cardinality.occurs[p.to_usize()] = list;
+        conflict
+    }
+
+    /// The reason clause of constraint 'c' for 'implied' (first), or its conflict clause.
+    pub(crate) fn cardinality_reason(&self, c: usize, implied: Option<Lit>, out: &mut Vec<Lit>) {
+        let bound = self.cardinality.constraints[c].bound;
+        let counted = &self.cardinality.counted[c];
+        out.clear();
+        match implied {
+            Some(lit) => {
+                out.push(lit);
+                out.extend(counted[..bound].iter().map(|l| l.not()));
+            }
+            None => out.extend(counted[..=bound].iter().map(|l| l.not())),
+        }
+    }
+
+    /// Variables of the native cardinality constraints (after substitution).
+    pub(crate) fn cardinality_variables(&self) -> impl Iterator<Item = usize> + '_ {
+        self.cardinality.constraints.iter().flat_map(|c| c.lits.iter().map(|l| l.var()))
+    }
+
+    /// Detects at-most-one and at-most-k constraints and replaces their direct encodings
+    /// by native constraints.
+    ///
+    /// Detection is semantic: 'x' excludes 'y' if propagating 'x' falsifies 'y', which covers
+    /// pairwise encodings as well as sequential counters and other propagation-complete ones.
+    /// - At-most-one: cliques of mutually excluding literals, grown greedily from the literals
+    ///   excluding the most others.
+    /// - At-most-k: grown from k seed literals, checking every k-subset by propagation.
+    ///
+    /// Only the clauses over the literals of a constraint (k + 1 negated ones) are removed;
+    /// auxiliary variables of counter encodings keep their clauses.
+    /// The number of propagated literals is bounded by `CARDINALITY_BUDGET`.
+    /// Skipped while a proof is written. Must be called at decision level 0.
+    pub fn detect_cardinality_constraints(&mut self, strategy: &mut dyn BranchingStrategy) -> CardinalityStats {
+        let mut stats = CardinalityStats::default();
+        if !self.ok || self.decision_level() != 0 || self.proof.is_some() { return stats; }
+        if self.propagate().is_some() {
+            self.ok = false;
+            return stats;
+        }
+
+        let mut db = self.clause_database();
+        let mut budget = CARDINALITY_BUDGET;
+        let mut used = vec![false; self.num_vars * 2];
+        let mut scratch = Scratch::new(self.num_vars);
+        // Native constraints propagate like their encodings, they must not be found again
+        for var in self.cardinality_variables().collect::<Vec<_>>() {
+            used[2 * var] = true;
+            used[2 * var + 1] = true;
+        }
+        let mut found: Vec<CardinalityConstraint> = Vec::new();
+
+        // Literals whose assignment shortens a clause
+        let candidates: Vec<Lit> = (0..self.num_vars * 2)
+            .map(|code| Lit::new(code >> 1, code & 1 == 1))
+            .filter(|&l| self.assignments[l.var()] == VarValue::Unassigned && db.live[l.not().to_usize()] > 0)
+            .collect();
+
+        // == AT-MOST-ONE ==
+        // At the root, only binary clauses propagate a single literal
+        let binary: Vec<Lit> = candidates.iter().copied()
+            .filter(|&l| db.occurrences(l.not()).any(|i| db.clauses[i].as_ref().unwrap().len() == 2))
+            .collect();
+        for clique in self.exclusion_cliques(&binary, &used, &mut scratch, &mut budget, strategy) {
+            for &l in &clique { used[l.to_usize()] = true; }
+            found.push(CardinalityConstraint { lits: clique, bound: 1 });
+            stats.at_most_one += 1;
+        }
+
+        // == AT-MOST-K ==
+        // Seeds: literals occurring negated in longer clauses, by variable index
+        let seeds: Vec<Lit> = candidates.iter().copied()
+            .filter(|&l| db.occurrences(l.not()).any(|i| db.clauses[i].as_ref().unwrap().len() > 2))
+            .collect();
+        for seed in seeds {
+            if budget == 0 { break; }
+            if used[seed.to_usize()] { continue; }
+            for bound in 2..=MAX_DETECTED_BOUND {
+                let Some(lits) = self.grow_at_most(seed, bound, &db, &used, &mut scratch, &mut budget, strategy) else { continue };
+                for &l in &lits { used[l.to_usize()] = true; }
+                found.push(CardinalityConstraint { lits, bound });
+                stats.at_most_k += 1;
+                break;
+            }
+        }
+        if found.is_empty() { return stats; }
+
+        // == REPLACEMENT ==
+        let mut marks = vec![false; self.num_vars * 2];
+        for constraint in &found {
+            for &l in &constraint.lits { marks[l.not().to_usize()] = true; }
+            for &l in &constraint.lits {
+                let encoding: Vec<usize> = db.occurrences(l.not())
+                    .filter(|&i| {
+                        let clause = db.clauses[i].as_ref().unwrap();
+                        clause.len() == constraint.bound + 1 && clause.iter().all(|m| marks[m.to_usize()])
+                    })
+                    .collect();
+                for i in encoding {
+                    db.remove(i);
+                    stats.removed += 1;
+                }
+            }
+            for &l in &constraint.lits { marks[l.not().to_usize()] = false; }
+        }
+        self.replace_clause_database(db.into_clauses());
+        for constraint in found {
+            if !self.add_at_most(constraint.lits, constraint.bound) { break; }
+        }
+        stats
+    }
+
+    /// Greedy cliques of at least `MIN_CLIQUE_SIZE` mutually excluding literals among
+    /// 'candidates' (not 'used'), each literal in at most one clique.
+    fn exclusion_cliques(
+        &mut self,
+        candidates: &[Lit],
+        used: &[bool],
+        scratch: &mut Scratch,
+        budget: &mut usize,
+        strategy: &mut dyn BranchingStrategy,
+    ) -> Vec<Vec<Lit>> {
+        let mut starters = Vec::new();
+        for &lit in candidates {
+            if *budget == 0 { break; }
+            if used[lit.to_usize()] || self.assignments[lit.var()] != VarValue::Unassigned { continue; }
+            let Some(excluded) = self.excluded_by(lit, budget, strategy) else { continue };
+            scratch.degree[lit.to_usize()] = excluded.len();
+            if excluded.len() + 1 >= MIN_CLIQUE_SIZE { starters.push(lit); }
+        }
+        let degree = std::mem::take(&mut scratch.degree);
+        starters.sort_by_key(|&l| (std::cmp::Reverse(degree[l.to_usize()]), l.to_usize()));
+
+        let mut cliques: Vec<Vec<Lit>> = Vec::new();
+        for &start in &starters {
+            if *budget == 0 { break; }
+            if scratch.taken[start.to_usize()] { continue; }
+            let Some(excluded) = self.excluded_by(start, budget, strategy) else { continue };
+            // 'open': excluded by every member of the clique so far
+            let members: Vec<Lit> = excluded.into_iter()
+                .filter(|l| degree[l.to_usize()] > 0 && !scratch.taken[l.to_usize()])
+                .collect();
+            for &l in &members { scratch.open[l.to_usize()] = true; }
+
+            let mut clique = vec![start];
+            while let Some(next) = members.iter().copied()
+                .filter(|l| scratch.open[l.to_usize()])
+                .max_by_key(|&l| (degree[l.to_usize()], std::cmp::Reverse(l.to_usize())))
+            {
+                scratch.open[next.to_usize()] = false;
+                let Some(excluded) = self.excluded_by(next, budget, strategy) else { continue };
+                for &l in &excluded { scratch.keep[l.to_usize()] = true; }
+                for &l in &members {
+                    if !scratch.keep[l.to_usize()] { scratch.open[l.to_usize()] = false; }
+                }
+                for &l in &excluded { scratch.keep[l.to_usize()] = false; }
+                clique.push(next);
+            }
+            for &l in &members { scratch.open[l.to_usize()] = false; }
+
+            if clique.len() >= MIN_CLIQUE_SIZE {
+                for &l in &clique { scratch.taken[l.to_usize()] = true; }
+                cliques.push(clique);
+            }
+        }
+
+        scratch.degree = degree;
+        for &l in candidates { scratch.degree[l.to_usize()] = 0; }
+        for &l in cliques.iter().flatten() { scratch.taken[l.to_usize()] = false; }
+        cliques
+    }
+
+    /// Grows an at-most-'bound' constraint (bound > 1) from 'seed', verified by propagation.
+    ///
+    /// All members have the polarity of the seed, which keeps the auxiliary literals of counter
+    /// encodings out. The first 'bound' members are seeds, each occurring negated in a clause
+    /// shortened by the previous ones. Then a literal joins if every 'bound'-subset of the members
+    /// falsifies it and it falsifies the other members together with every ('bound' - 1)-subset.
+    /// Literals are tried by variable index (encodings usually allocate their auxiliary variables last).
+    #[allow(clippy::too_many_arguments)]
+    fn grow_at_most(
+        &mut self,
+        seed: Lit,
+        bound: usize,
+        db: &ClauseDatabase,
+        used: &[bool],
+        scratch: &mut Scratch,
+        budget: &mut usize,
+        strategy: &mut dyn BranchingStrategy,
+    ) -> Option<Vec<Lit>> {
+        let same_sign = |l: Lit| l.is_neg() == seed.is_neg();
+        let mut members = vec![seed];
+        while members.len() < bound {
+            let consistent = self.assume_all(&members, budget);
+            let mut interacting = Vec::new();
+            if consistent {
+                for &t in &self.trail[self.trail_lim[0]..] {
+                    for i in db.occurrences(t.not()) {
+                        for &l in db.clauses[i].as_ref().unwrap() {
+                            let candidate = l.not();
+                            if scratch.seen[candidate.to_usize()] || used[candidate.to_usize()] { continue; }
+                            if self.assignments[l.var()] != VarValue::Unassigned { continue; }
+                            scratch.seen[candidate.to_usize()] = true;
+                            interacting.push(candidate);
+                        }
+                    }
+                }
+                for &l in &interacting { scratch.seen[l.to_usize()] = false; }
+            }
+            self.backtrack(0, strategy);
+            let next = interacting.into_iter().filter(|&l| same_sign(l) && !members.contains(&l.not())).min_by_key(|l| l.var())?;
+            members.push(next);
+        }
+
+        // Literals falsified by the seeds
+        if !self.assume_all(&members, budget) {
+            self.backtrack(0, strategy);
+            return None;
+        }
+        let mut open: Vec<Lit> = self.trail[self.trail_lim[0]..].iter()
+            .map(|l| l.not())
+            .filter(|&l| same_sign(l) && !used[l.to_usize()] && !members.contains(&l.not()))
+            .collect();
+        self.backtrack(0, strategy);
+        open.sort_by_key(|l| l.var());
+
+        let mut alive = vec![true; open.len()];
+        for i in 0..open.len() {
+            if members.len() == MAX_DETECTED_SIZE || *budget == 0 { break; }
+            if !alive[i] { continue; }
+            let candidate = open[i];
+            let mut accepted = true;
+            let mut dead = Vec::new();
+            for_each_subset(&members, bound - 1, &mut |subset| {
+                if !accepted { return; }
+                let mut assumptions = subset.to_vec();
+                assumptions.push(candidate);
+                if self.assume_all(&assumptions, budget) {
+                    let falsified = |l: &Lit| Self::value_lit(&self.assignments, *l) == VarValue::False;
+                    accepted = members.iter().all(|m| subset.contains(m) || falsified(m));
+                    dead.extend((i + 1..open.len()).filter(|&j| alive[j] && !falsified(&open[j])));
+                }
+                self.backtrack(0, strategy);
+            });
+            if accepted {
+                members.push(candidate);
+                for j in dead { alive[j] = false; }
+            }
+        }
+        (members.len() >= bound + 2).then_some(members)
+    }
+
+    /// Assigns 'lits' on fresh decision levels and propagates.
+    /// Returns false if that fails (the caller backtracks in any case).
+    fn assume_all(&mut self, lits: &[Lit], budget: &mut usize) -> bool {
+        for &lit in lits {
+            match Self::value_lit(&self.assignments, lit) {
+                VarValue::True => continue,
+                VarValue::False => return false,
+                VarValue::Unassigned => {}
+            }
+            let start = self.trail.len();
+            self.trail_lim.push(start);
+            self.unchecked_enqueue(lit, None);
+            let conflict = self.propagate();
+            *budget = budget.saturating_sub(self.trail.len() - start);
+            if conflict.is_some() { return false; }
+        }
+        true
+    }
+
+    /// The literals falsified by propagating 'lit' on top of the current assignment,
+    /// or None if that fails. Returns to the current decision level.
+    fn excluded_by(&mut self, lit: Lit, budget: &mut usize, strategy: &mut dyn BranchingStrategy) -> Option<Vec<Lit>> {
+        let level = self.decision_level();
+        let start = self.trail.len();
+        self.trail_lim.push(start);
+        self.unchecked_enqueue(lit, None);
+        let conflict = self.propagate();
+        *budget = budget.saturating_sub(self.trail.len() - start);
+        let excluded = conflict.is_none().then(|| self.trail[start + 1..].iter().map(|l| l.not()).collect());
+        self.backtrack(level, strategy);
+        excluded
+    }
+}
+
+/// Calls 'f' with every subset of 'lits' of the given size.
+fn for_each_subset(lits: &[Lit], size: usize, f: &mut dyn FnMut(&[Lit])) {
+    fn recurse(lits: &[Lit], size: usize, from: usize, chosen: &mut Vec<Lit>, f: &mut dyn FnMut(&[Lit])) {
+        if chosen.len() == size {
+            f(chosen);
+            return;
+        }
+        for i in from..lits.len() {
+            chosen.push(lits[i]);
+            recurse(lits, size, i + 1, chosen, f);
+            chosen.pop();
+        }
+    }
+    recurse(lits, size, 0, &mut Vec::with_capacity(size), f);
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use crate::bignum::{BigUint, Decimal};
+use crate::{Lit, RandomStrategy, Solver, VarValue};
+
+// =========================================================================
+// Exact Model Counting (#SAT) with Component Caching
+// =========================================================================
+
+/// Number of component counts cached before the cache is flushed.
+const CACHE_LIMIT: usize = 1 << 20;
+
+/// A connected component of the residual formula: unassigned variables linked by unsatisfied
+/// clauses (both sorted).
+struct Component {
+    vars: Vec<usize>,
+    clauses: Vec<usize>,
+}
+
+/// DPLL-style counter on top of the solver's propagation: after each decision, the residual
+/// formula splits into components that are counted independently, and the count of a component
+/// is cached under its variables and clauses, which determine its residual clauses.
+struct Counter {
+    occurrences: Vec<Vec<usize>>, // Var -> clauses containing it
+    projected: Vec<bool>,
+    weights: Vec<Decimal>,        // Literal -> weight
+    cache: HashMap<Vec<u32>, Decimal>,
+    // Visited marks of the component search
+    var_stamp: Vec<u32>,
+    clause_stamp: Vec<u32>,
+    stamp: u32,
+    strategy: RandomStrategy, // Receives the unassignment hooks of backtracking
+}
+
+impl Solver {
+    /// Counts the models of the clauses added so far, or their distinct projections onto the
+    /// 'projection' variables. Native XORs must have been encoded (see `weighted_count`).
+    pub fn count(&mut self, projection: Option<&[usize]>) -> BigUint {
+        self.weighted_count(projection, &[]).trunc()
+    }
+
+    /// Sums the weights of the models (of their projections), where the weight of a model is the
+    /// product of the weights of its projected literals; literals missing in 'weights' weigh 1.
+    /// Counts the clauses added so far: it must be called before `solve` simplifies them, and XORs
+    /// need `xor_propagation` to be switched off before they are added, so that they are encoded.
+    pub fn weighted_count(&mut self, projection: Option<&[usize]>, weights: &[(Lit, Decimal)]) -> Decimal {
+        if !self.ok { return Decimal::zero(); }
+        if self.propagate().is_some() {
+            self.ok = false;
+            return Decimal::zero();
+        }
+
+        let mut occurrences = vec![Vec::new(); self.num_vars];
+        for (c, clause) in self.clauses.iter().enumerate().filter(|(_, c)| !c.learned) {
+            for lit in &clause.lits { occurrences[lit.var()].push(c); }
+        }
+        let mut projected = vec![projection.is_none(); self.num_vars];
+        for &var in projection.unwrap_or(&[]) { projected[var] = true; }
+        let mut lit_weights = vec![Decimal::one(); self.num_vars * 2];
+        for (lit, weight) in weights { lit_weights[lit.to_usize()] = weight.clone(); }
+
+        let mut counter = Counter {
+            occurrences,
+            projected,
+            weights: lit_weights,
+            cache: HashMap::new(),
+            var_stamp: vec![0; self.num_vars],
+            clause_stamp: vec![0; self.clauses.len()],
+            stamp: 0,
+            strategy: RandomStrategy::new(self.num_vars),
+        };
+        let vars: Vec<usize> = (0..self.num_vars).collect();
+        let assigned = self.trail.clone();
+        counter.count_residual(self, &vars, &assigned)
+    }
+}
+
+impl Counter {
+    /// Weight of the 'assigned' literals times the count of the residual formula over the
+    /// unassigned variables among 'vars' (closed under the unsatisfied clauses linking them).
+    fn count_residual(&mut self, solver: &mut Solver, vars: &[usize], assigned: &[Lit]) -> Decimal {
+        let mut count = Decimal::one();
+        for &lit in assigned.iter().filter(|l| self.projected[l.var()]) {
+            count = &count * &self.weights[lit.to_usize()];
+        }
+        let (components, free) = self.components(solver, vars);
+        for var in free.into_iter().filter(|&var| self.projected[var]) {
+            let both = &self.weights[Lit::new(var, false).to_usize()] + &self.weights[Lit::new(var, true).to_usize()];
+            count = &count * &both;
+        }
+        for component in components {
+            if count.is_zero() { break; }
+            count = &count * &self.count_component(solver, component);
+        }
+        count
+    }
+
+    /// Counts a component by branching on its most frequent projected variable. A component without
+    /// projected variables counts 1 if it is satisfiable, which the first model found settles.
+    fn count_component(&mut self, solver: &mut Solver, component: Component) -> Decimal {
+        let mut key: Vec<u32> = component.vars.iter().map(|&v| v as u32).collect();
+        key.push(u32::MAX);
+        key.extend(component.clauses.iter().map(|&c| c as u32));
+        if let Some(count) = self.cache.get(&key) { return count.clone(); }
+
+        let existential = !component.vars.iter().any(|&var| self.projected[var]);
+        let mut occurrences: HashMap<usize, usize> = HashMap::new();
+        for &c in &component.clauses {
+            for lit in &solver.clauses[c].lits {
+                if existential || self.projected[lit.var()] { *occurrences.entry(lit.var()).or_insert(0) += 1; }
+            }
+        }
+        let var = component.vars.iter().copied()
+            .filter(|&var| solver.assignments[var] == VarValue::Unassigned && occurrences.contains_key(&var))
+            .max_by_key(|var| occurrences[var])
+            .unwrap_or(component.vars[0]);
+
+        let mut total = Decimal::zero();
+        for lit in [Lit::new(var, false), Lit::new(var, true)] {
+            let level = solver.decision_level();
+            let start = solver.trail.len();
+            solver.trail_lim.push(start);
+            solver.unchecked_enqueue(lit, None);
+            if solver.propagate().is_none() {
+                let assigned = solver.trail[start..].to_vec();
+                total = &total + &self.count_residual(solver, &component.vars, &assigned);
+            }
+            solver.backtrack(level, &mut self.strategy);
+            if existential && !total.is_zero() { break; }
+        }
+
+        if self.cache.len() >= CACHE_LIMIT { self.cache.clear(); }
+        self.cache.insert(key, total.clone());
+        total
+    }
+
+    /// Splits the unassigned variables among 'vars' into the components of the unsatisfied clauses
+    /// and the free variables, which occur in none.
+    fn components(&mut self, solver: &Solver, vars: &[usize]) -> (Vec<Component>, Vec<usize>) {
+        self.stamp += 1;
+        let stamp = self.stamp;
+        let mut components = Vec::new();
+        let mut free = Vec::new();
+        for &root in vars {
+            if solver.assignments[root] != VarValue::Unassigned || self.var_stamp[root] == stamp { continue; }
+            self.var_stamp[root] = stamp;
+            let mut component = Component { vars: vec![root], clauses: Vec::new() };
+            let mut i = 0;
+            while i < component.vars.len() {
+                let var = component.vars[i];
+                i += 1;
+                for &c in &self.occurrences[var] {
+                    if self.clause_stamp[c] == stamp { continue; }
+                    self.clause_stamp[c] = stamp;
+                    let lits = &solver.clauses[c].lits;
+                    if lits.iter().any(|&l| Solver::value_lit(&solver.assignments, l) == VarValue::True) { continue; }
+                    component.clauses.push(c);
+                    for lit in lits {
+                        if solver.assignments[lit.var()] == VarValue::Unassigned && self.var_stamp[lit.var()] != stamp {
+                            self.var_stamp[lit.var()] = stamp;
+                            component.vars.push(lit.var());
+                        }
+                    }
+                }
+            }
+            if component.clauses.is_empty() {
+                free.push(root);
+            } else {
+                component.vars.sort_unstable();
+                component.clauses.sort_unstable();
+                components.push(component);
+            }
+        }
+        (components, free)
+    }
+}
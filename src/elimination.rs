@@ -0,0 +1,120 @@
+use crate::occurrences::ClauseDatabase;
+use crate::{Lit, Solver, VarValue};
+
+// =========================================================================
+// Bounded Variable Elimination
+// =========================================================================
+
+/// Variables with more irredundant occurrences (both polarities together) are kept.
+const MAX_OCCURRENCES: usize = 16;
+/// Variables whose elimination produces a longer resolvent are kept.
+const MAX_RESOLVENT_SIZE: usize = 16;
+
+/// Counters of an elimination round (reported in verbose mode).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EliminationStats {
+    pub eliminated: usize, // Variables removed from the formula
+    pub resolvents: usize, // Clauses added in their place
+}
+
+impl Solver {
+    /// Bounded variable elimination by clause distribution (as in SatELite).
+    ///
+    /// A variable x is eliminated by replacing all clauses containing x or -x by their
+    /// non-tautological resolvents on x, as long as this does not increase the number of clauses.
+    /// The removed clauses are pushed onto the reconstruction stack, witnessed on their literal
+    /// of x; learned clauses containing x are dropped. A later clause, constraint or assumption
+    /// over x brings its clauses back.
+    /// Variables of native constraints and assumptions are never eliminated.
+    /// Must be called at decision level 0.
+    pub fn eliminate_variables(&mut self) -> EliminationStats {
+        let mut stats = EliminationStats::default();
+        self.with_clause_database(|solver, db| {
+            stats = solver.eliminate_variables_in(db);
+            stats.eliminated > 0
+        });
+        stats
+    }
+
+    /// `eliminate_variables` on a clause database shared with other passes (see `PassContext`).
+    pub(crate) fn eliminate_variables_in(&mut self, db: &mut ClauseDatabase) -> EliminationStats {
+        let mut stats = EliminationStats::default();
+        let frozen = self.frozen_variables();
+        let occurrences = |db: &ClauseDatabase, var: usize| db.live[2 * var] + db.live[2 * var + 1];
+        let mut candidates: Vec<usize> = (0..self.num_vars)
+            .filter(|&v| !frozen[v] && self.assignments[v] == VarValue::Unassigned)
+            .filter(|&v| (1..=MAX_OCCURRENCES).contains(&occurrences(db, v)))
+            .collect();
+        // Cheapest first: the number of resolvents is bounded by the product of the occurrences
+        candidates.sort_by_key(|&v| db.live[2 * v] * db.live[2 * v + 1]);
+        let mut marks = vec![false; self.num_vars * 2];
+
+        for var in candidates {
+            let pos = Lit::new(var, false);
+            let irredundant = |db: &ClauseDatabase, lit: Lit| -> Vec<usize> {
+                db.occurrences(lit).filter(|&i| !db.learned[i]).collect()
+            };
+            let (pos_clauses, neg_clauses) = (irredundant(db, pos), irredundant(db, pos.not()));
+            if pos_clauses.len() + neg_clauses.len() > MAX_OCCURRENCES { continue; }
+            let limit = pos_clauses.len() + neg_clauses.len();
+            let Some(resolvents) = resolve_all(db, &pos_clauses, &neg_clauses, pos, limit, &mut marks) else { continue };
+
+            for (clauses, witness) in [(pos_clauses, pos), (neg_clauses, pos.not())] {
+                for i in clauses {
+                    let clause = db.remove(i);
+                    self.reconstruction.push(witness, clause);
+                }
+            }
+            let learned: Vec<usize> = db.occurrences(pos).chain(db.occurrences(pos.not())).collect();
+            for i in learned { db.remove(i); }
+
+            stats.eliminated += 1;
+            for resolvent in resolvents {
+                self.log_add(&resolvent);
+                if resolvent.is_empty() {
+                    self.ok = false;
+                    return stats;
+                }
+                db.add(resolvent, false);
+                stats.resolvents += 1;
+            }
+        }
+        stats
+    }
+}
+
+/// All non-tautological resolvents on 'pivot' of the clauses containing it with the clauses
+/// containing its negation. Returns None if there are more than 'limit' of them
+/// or one of them is longer than `MAX_RESOLVENT_SIZE`.
+fn resolve_all(
+    db: &ClauseDatabase,
+    pos_clauses: &[usize],
+    neg_clauses: &[usize],
+    pivot: Lit,
+    limit: usize,
+    marks: &mut [bool],
+) -> Option<Vec<Vec<Lit>>> {
+    let mut resolvents = Vec::new();
+    for &p in pos_clauses {
+        let clause = db.clauses[p].as_ref().unwrap();
+        let side: Vec<Lit> = clause.iter().copied().filter(|&l| l != pivot).collect();
+        for &l in &side { marks[l.to_usize()] = true; }
+
+        let mut result = Some(());
+        for &n in neg_clauses {
+            let other = db.clauses[n].as_ref().unwrap();
+            if other.iter().any(|&l| marks[l.not().to_usize()]) { continue; } // Tautology
+            let mut resolvent = side.clone();
+            resolvent.extend(other.iter().copied().filter(|&l| l != pivot.not() && !marks[l.to_usize()]));
+            if resolvent.len() > MAX_RESOLVENT_SIZE || resolvents.len() == limit {
+                result = None;
+                break;
+            }
+            resolvents.push(resolvent);
+        }
+
+        for &l in &side { marks[l.to_usize()] = false; }
+        result?;
+    }
+    Some(resolvents)
+}
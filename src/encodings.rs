@@ -0,0 +1,597 @@
+use std::collections::BTreeMap;
+use crate::{Lit, Solver};
+
+// =========================================================================
+// CNF Encodings of Cardinality and Pseudo-Boolean Constraints
+// =========================================================================
+
+/// Members of a group of the commander encoding, which are encoded pairwise.
+const COMMANDER_GROUP: usize = 3;
+
+/// Where an encoding puts its clauses and gets its auxiliary variables from.
+pub trait ClauseSink {
+    /// A fresh variable.
+    fn new_var(&mut self) -> usize;
+    fn add_clause(&mut self, lits: Vec<Lit>);
+}
+
+impl ClauseSink for Solver {
+    fn new_var(&mut self) -> usize {
+        Solver::new_var(self)
+    }
+
+    // An unsatisfiable formula is remembered by the solver
+    fn add_clause(&mut self, lits: Vec<Lit>) {
+        Solver::add_clause(self, lits);
+    }
+}
+
+/// A list of clauses whose auxiliary variables are numbered from 'num_vars' on.
+#[derive(Debug, Clone, Default)]
+pub struct ClauseList {
+    pub num_vars: usize,
+    pub clauses: Vec<Vec<Lit>>,
+}
+
+impl ClauseList {
+    /// An empty list over the variables 0 .. num_vars.
+    pub fn new(num_vars: usize) -> Self {
+        ClauseList { num_vars, clauses: Vec::new() }
+    }
+}
+
+impl ClauseSink for ClauseList {
+    fn new_var(&mut self) -> usize {
+        self.num_vars += 1;
+        self.num_vars - 1
+    }
+
+    fn add_clause(&mut self, lits: Vec<Lit>) {
+        self.clauses.push(lits);
+    }
+}
+
+fn fresh(sink: &mut dyn ClauseSink) -> Lit {
+    Lit::new(sink.new_var(), false)
+}
+
+// =========================================================================
+// At-Most-One
+// =========================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AtMostOneEncoding {
+    /// One binary clause per pair of literals, no auxiliary variables.
+    Pairwise,
+    /// Sinz' sequential counter: auxiliary variable i is true if one of the first i + 1 literals is.
+    #[default]
+    Sequential,
+    /// Groups of 3 literals encoded pairwise, each implying a commander variable, and at most
+    /// one commander true (recursively).
+    Commander,
+}
+
+/// Adds the constraint "at most one of 'lits' is true".
+pub fn at_most_one(sink: &mut dyn ClauseSink, lits: &[Lit], encoding: AtMostOneEncoding) {
+    if lits.len() <= 1 { return; }
+    match encoding {
+        AtMostOneEncoding::Pairwise => pairwise(sink, lits),
+        AtMostOneEncoding::Sequential => {
+            let prefix: Vec<Lit> = (1..lits.len()).map(|_| fresh(sink)).collect();
+            for (i, &lit) in lits.iter().enumerate() {
+                if i < prefix.len() { sink.add_clause(vec![lit.not(), prefix[i]]); }
+                if i > 0 {
+                    sink.add_clause(vec![lit.not(), prefix[i - 1].not()]);
+                    if i < prefix.len() { sink.add_clause(vec![prefix[i - 1].not(), prefix[i]]); }
+                }
+            }
+        }
+        AtMostOneEncoding::Commander if lits.len() <= COMMANDER_GROUP => pairwise(sink, lits),
+        AtMostOneEncoding::Commander => {
+            let mut commanders = Vec::new();
+            for group in lits.chunks(COMMANDER_GROUP) {
+                if let [lit] = group {
+                    commanders.push(*lit);
+                    continue;
+                }
+                pairwise(sink, group);
+                let commander = fresh(sink);
+                for &lit in group { sink.add_clause(vec![lit.not(), commander]); }
+                commanders.push(commander);
+            }
+            at_most_one(sink, &commanders, AtMostOneEncoding::Commander);
+        }
+    }
+}
+
+fn pairwise(sink: &mut dyn ClauseSink, lits: &[Lit]) {
+    for (i, &a) in lits.iter().enumerate() {
+        for &b in &lits[i + 1..] { sink.add_clause(vec![a.not(), b.not()]); }
+    }
+}
+
+// =========================================================================
+// At-Most-k and At-Least-k
+// =========================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CardinalityEncoding {
+    /// A binary tree of unary counters (see `Totalizer`), with at most k + 1 outputs per node.
+    #[default]
+    Totalizer,
+    /// A totalizer counting modulo p ≈ sqrt(k + 1): every node has p - 1 outputs for the
+    /// remainder and one per multiple of p, which takes O(n · k) clauses instead of O(n · k²).
+    ModuloTotalizer,
+    /// Asín et al.: sorting networks over blocks of k + 1 (rounded up to a power of two)
+    /// literals, combined by simplified merges that only keep the k + 1 largest values.
+    CardinalityNetwork,
+    /// Batcher's odd-even merge sort over all literals.
+    SortingNetwork,
+}
+
+/// Adds the constraint "at most 'k' of 'lits' are true". Only the direction the bound needs is
+/// encoded: enough true inputs force the counting outputs, never the other way round.
+pub fn at_most_k(sink: &mut dyn ClauseSink, lits: &[Lit], k: usize, encoding: CardinalityEncoding) {
+    if k >= lits.len() { return; }
+    if k == 0 {
+        for &lit in lits { sink.add_clause(vec![lit.not()]); }
+        return;
+    }
+    let output = match encoding {
+        CardinalityEncoding::Totalizer => Totalizer::new(lits).at_least(sink, k + 1),
+        CardinalityEncoding::ModuloTotalizer => return modulo_totalizer(sink, lits, k),
+        CardinalityEncoding::CardinalityNetwork => {
+            let block = (k + 1).next_power_of_two();
+            let mut width = block;
+            while width < lits.len() { width *= 2; }
+            cardinality_network(sink, &padded(lits, width), block)[k]
+        }
+        CardinalityEncoding::SortingNetwork => sort(sink, &padded(lits, lits.len().next_power_of_two()))[k],
+    };
+    if let Some(output) = output { sink.add_clause(vec![output.not()]); }
+}
+
+/// Adds the constraint "at least 'k' of 'lits' are true", as at most n - k of their negations.
+pub fn at_least_k(sink: &mut dyn ClauseSink, lits: &[Lit], k: usize, encoding: CardinalityEncoding) {
+    if k > lits.len() {
+        sink.add_clause(Vec::new());
+        return;
+    }
+    let negated: Vec<Lit> = lits.iter().map(|l| l.not()).collect();
+    at_most_k(sink, &negated, lits.len() - k, encoding);
+}
+
+/// An incremental totalizer: a binary tree whose nodes count the true inputs below them in unary.
+/// Output i of a node is implied by i + 1 true inputs below it (the other direction is not
+/// encoded, which bounding the sum by assuming outputs false does not need). Outputs are only
+/// encoded when `at_least` asks for them, and inputs can be added later, so that MaxSAT can
+/// raise the bound of a totalizer step by step.
+#[derive(Debug, Clone, Default)]
+pub struct Totalizer {
+    nodes: Vec<TotalizerNode>,
+    root: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct TotalizerNode {
+    children: Option<(usize, usize)>,
+    size: usize,       // Number of inputs below
+    outputs: Vec<Lit>, // Encoded so far: the input itself for a leaf
+}
+
+impl Totalizer {
+    /// A totalizer over 'inputs' without any encoded outputs (no clauses yet).
+    pub fn new(inputs: &[Lit]) -> Self {
+        let mut totalizer = Totalizer::default();
+        if !inputs.is_empty() { totalizer.root = Some(totalizer.build(inputs)); }
+        totalizer
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.root.map_or(0, |root| self.nodes[root].size)
+    }
+
+    /// A literal implied by 'count' true inputs (encoding the outputs up to it), or None if
+    /// 'count' is 0 or there are fewer inputs.
+    pub fn at_least(&mut self, sink: &mut dyn ClauseSink, count: usize) -> Option<Lit> {
+        let root = self.root?;
+        if count == 0 || count > self.nodes[root].size { return None; }
+        self.extend(sink, root, count);
+        Some(self.nodes[root].outputs[count - 1])
+    }
+
+    /// Adds 'inputs' under a new root. Literals returned by `at_least` before keep counting the
+    /// old inputs only; later calls count all of them.
+    pub fn add_inputs(&mut self, inputs: &[Lit]) {
+        if inputs.is_empty() { return; }
+        let new = self.build(inputs);
+        self.root = Some(match self.root {
+            None => new,
+            Some(old) => {
+                let size = self.nodes[old].size + self.nodes[new].size;
+                self.nodes.push(TotalizerNode { children: Some((old, new)), size, outputs: Vec::new() });
+                self.nodes.len() - 1
+            }
+        });
+    }
+
+    fn build(&mut self, inputs: &[Lit]) -> usize {
+        let node = if let [input] = inputs {
+            TotalizerNode { children: None, size: 1, outputs: vec![*input] }
+        } else {
+            let (left, right) = inputs.split_at(inputs.len() / 2);
+            let children = (self.build(left), self.build(right));
+            TotalizerNode { children: Some(children), size: inputs.len(), outputs: Vec::new() }
+        };
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Encodes the outputs of 'node' up to 'limit', with the clauses for the sums of the outputs
+    /// of its children that reach a new output. Smaller sums were encoded with the old outputs.
+    fn extend(&mut self, sink: &mut dyn ClauseSink, node: usize, limit: usize) {
+        let limit = limit.min(self.nodes[node].size);
+        let old = self.nodes[node].outputs.len();
+        if old >= limit { return; }
+        let (a, b) = self.nodes[node].children.expect("a leaf has its only output");
+        self.extend(sink, a, limit);
+        self.extend(sink, b, limit);
+        for _ in old..limit {
+            let output = fresh(sink);
+            self.nodes[node].outputs.push(output);
+        }
+        let (left, right, outputs) = (&self.nodes[a].outputs, &self.nodes[b].outputs, &self.nodes[node].outputs);
+        for i in 0..=left.len() {
+            for j in 0..=right.len() {
+                if i + j <= old || i + j > limit { continue; }
+                let mut clause = vec![outputs[i + j - 1]];
+                if i > 0 { clause.push(left[i - 1].not()); }
+                if j > 0 { clause.push(right[j - 1].not()); }
+                sink.add_clause(clause);
+            }
+        }
+    }
+}
+
+/// Generalized totalizer over weighted 'inputs': one output per reachable sum of weights,
+/// sums of at least 'limit' sharing the output of 'limit', implied by inputs of that sum.
+/// There are no outputs without inputs.
+pub fn weighted_totalizer(sink: &mut dyn ClauseSink, inputs: &[(u64, Lit)], limit: u64) -> Vec<(u64, Lit)> {
+    match inputs {
+        [] => return Vec::new(),
+        &[(weight, lit)] => return vec![(weight.min(limit), lit)],
+        _ => {}
+    }
+    let (left, right) = inputs.split_at(inputs.len() / 2);
+    let a = weighted_totalizer(sink, left, limit);
+    let b = weighted_totalizer(sink, right, limit);
+    let mut outputs: BTreeMap<u64, Lit> = BTreeMap::new();
+    let with_zero = |sums: &[(u64, Lit)]| std::iter::once((0, None)).chain(sums.iter().map(|&(v, l)| (v, Some(l)))).collect::<Vec<_>>();
+    for (va, la) in with_zero(&a) {
+        for &(vb, lb) in &with_zero(&b) {
+            let value = (va + vb).min(limit);
+            if value == 0 { continue; }
+            let o = *outputs.entry(value).or_insert_with(|| fresh(sink));
+            let mut clause = vec![o];
+            clause.extend(la.map(|l| l.not()));
+            clause.extend(lb.map(|l| l.not()));
+            sink.add_clause(clause);
+        }
+    }
+    outputs.into_iter().collect()
+}
+
+/// At most 'k' (> 0) of 'lits' with a modulo totalizer, see `CardinalityEncoding::ModuloTotalizer`.
+fn modulo_totalizer(sink: &mut dyn ClauseSink, lits: &[Lit], k: usize) {
+    let mut p = 2;
+    while p * p < k + 1 { p += 1; }
+    let (quotient, remainder) = (k / p, k % p);
+    let (lower, upper) = modulo_node(sink, lits, p, quotient + 1);
+    // Forbid a quotient above k / p, and the quotient k / p with a remainder above k % p
+    if let Some(&u) = upper.get(quotient) { sink.add_clause(vec![u.not()]); }
+    if let Some(&l) = lower.get(remainder) {
+        match quotient {
+            0 => sink.add_clause(vec![l.not()]),
+            q => if let Some(&u) = upper.get(q - 1) { sink.add_clause(vec![l.not(), u.not()]); },
+        }
+    }
+}
+
+/// The unary remainder (output r - 1: at least r modulo p) and quotient (output q - 1: at least
+/// q times p, up to 'limit') of the true literals among 'lits'. Remainders of the children that
+/// add up to p or more set the carry, which raises the quotient by one.
+fn modulo_node(sink: &mut dyn ClauseSink, lits: &[Lit], p: usize, limit: usize) -> (Vec<Lit>, Vec<Lit>) {
+    if let [lit] = lits { return (vec![*lit], Vec::new()); }
+    let (left, right) = lits.split_at(lits.len() / 2);
+    let (lower_a, upper_a) = modulo_node(sink, left, p, limit);
+    let (lower_b, upper_b) = modulo_node(sink, right, p, limit);
+    let lower: Vec<Lit> = (0..(p - 1).min(lits.len())).map(|_| fresh(sink)).collect();
+    // The carry may also be chosen for remainders below p, so the quotient is only capped by 'limit'
+    let upper: Vec<Lit> = (0..if lits.len() >= p { limit } else { 0 }).map(|_| fresh(sink)).collect();
+    let carry = (lower_a.len() + lower_b.len() >= p).then(|| fresh(sink));
+
+    let premise = |a: &[Lit], b: &[Lit], i: usize, j: usize| {
+        let mut clause = Vec::new();
+        if i > 0 { clause.push(a[i - 1].not()); }
+        if j > 0 { clause.push(b[j - 1].not()); }
+        clause
+    };
+    for i in 0..=lower_a.len() {
+        for j in 0..=lower_b.len() {
+            if i + j == 0 { continue; }
+            let mut clause = premise(&lower_a, &lower_b, i, j);
+            if i + j < p {
+                clause.push(lower[i + j - 1]);
+                clause.extend(carry);
+            } else {
+                let carry = carry.unwrap();
+                if i + j > p {
+                    let mut with_rest = clause.clone();
+                    with_rest.push(lower[i + j - p - 1]);
+                    sink.add_clause(with_rest);
+                }
+                clause.push(carry);
+            }
+            sink.add_clause(clause);
+        }
+    }
+    for i in 0..=upper_a.len() {
+        for j in 0..=upper_b.len() {
+            if i + j > 0 {
+                let mut clause = premise(&upper_a, &upper_b, i, j);
+                clause.push(upper[(i + j).min(upper.len()) - 1]);
+                sink.add_clause(clause);
+            }
+            if let Some(carry) = carry {
+                let mut clause = premise(&upper_a, &upper_b, i, j);
+                clause.push(carry.not());
+                clause.push(upper[(i + j + 1).min(upper.len()) - 1]);
+                sink.add_clause(clause);
+            }
+        }
+    }
+    (lower, upper)
+}
+
+// Wires of the sorting networks, None being the constant false of the padding
+type Wire = Option<Lit>;
+
+fn padded(lits: &[Lit], width: usize) -> Vec<Wire> {
+    let mut wires: Vec<Wire> = lits.iter().copied().map(Some).collect();
+    wires.resize(width, None);
+    wires
+}
+
+/// A comparator: the maximum (OR) and minimum (AND) of 'a' and 'b', implied by the inputs.
+fn comparator(sink: &mut dyn ClauseSink, a: Wire, b: Wire) -> (Wire, Wire) {
+    match (a, b) {
+        (None, x) | (x, None) => (x, None),
+        (Some(a), Some(b)) => {
+            let (max, min) = (fresh(sink), fresh(sink));
+            sink.add_clause(vec![a.not(), max]);
+            sink.add_clause(vec![b.not(), max]);
+            sink.add_clause(vec![a.not(), b.not(), min]);
+            (Some(max), Some(min))
+        }
+    }
+}
+
+fn every_other(wires: &[Wire], first: usize) -> Vec<Wire> {
+    wires.iter().skip(first).step_by(2).copied().collect()
+}
+
+/// Sorts a power-of-two number of wires in decreasing order (Batcher's odd-even merge sort).
+fn sort(sink: &mut dyn ClauseSink, wires: &[Wire]) -> Vec<Wire> {
+    if wires.len() == 1 { return wires.to_vec(); }
+    let (left, right) = wires.split_at(wires.len() / 2);
+    let a = sort(sink, left);
+    let b = sort(sink, right);
+    merge(sink, &a, &b)
+}
+
+/// Merges two sorted sequences of the same power-of-two length.
+fn merge(sink: &mut dyn ClauseSink, a: &[Wire], b: &[Wire]) -> Vec<Wire> {
+    if a.len() == 1 {
+        let (max, min) = comparator(sink, a[0], b[0]);
+        return vec![max, min];
+    }
+    let odd = merge(sink, &every_other(a, 0), &every_other(b, 0));
+    let even = merge(sink, &every_other(a, 1), &every_other(b, 1));
+    let mut merged = vec![odd[0]];
+    for i in 0..even.len() - 1 {
+        let (max, min) = comparator(sink, even[i], odd[i + 1]);
+        merged.push(max);
+        merged.push(min);
+    }
+    merged.push(even[even.len() - 1]);
+    merged
+}
+
+/// The largest c + 1 values of two sorted sequences of the same power-of-two length c.
+fn simplified_merge(sink: &mut dyn ClauseSink, a: &[Wire], b: &[Wire]) -> Vec<Wire> {
+    if a.len() == 1 {
+        let (max, min) = comparator(sink, a[0], b[0]);
+        return vec![max, min];
+    }
+    let odd = simplified_merge(sink, &every_other(a, 0), &every_other(b, 0));
+    let even = simplified_merge(sink, &every_other(a, 1), &every_other(b, 1));
+    let mut merged = vec![odd[0]];
+    for i in 0..a.len() / 2 {
+        let (max, min) = comparator(sink, odd[i + 1], even[i]);
+        merged.push(max);
+        merged.push(min);
+    }
+    merged
+}
+
+/// The largest 'block' values of the wires, whose number is 'block' times a power of two.
+fn cardinality_network(sink: &mut dyn ClauseSink, wires: &[Wire], block: usize) -> Vec<Wire> {
+    if wires.len() == block { return sort(sink, wires); }
+    let (left, right) = wires.split_at(wires.len() / 2);
+    let a = cardinality_network(sink, left, block);
+    let b = cardinality_network(sink, right, block);
+    let mut merged = simplified_merge(sink, &a, &b);
+    merged.truncate(block);
+    merged
+}
+
+// =========================================================================
+// Pseudo-Boolean Constraints
+// =========================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PbEncoding {
+    /// A reduced ordered BDD over the terms by decreasing coefficient, whose nodes are shared
+    /// between the budgets they cannot tell apart (intervals as in Abío et al.).
+    #[default]
+    Bdd,
+    /// Eén and Sörensson's adder network: full and half adders sum the coefficients bit by bit
+    /// into a binary number, which a lexicographic comparison bounds.
+    Adder,
+}
+
+/// Adds the constraint "sum of coefficient · literal <= bound".
+pub fn pb_at_most(sink: &mut dyn ClauseSink, terms: &[(u64, Lit)], bound: u64, encoding: PbEncoding) {
+    let mut terms: Vec<(u64, Lit)> = terms.iter().copied().filter(|&(a, _)| a > 0).collect();
+    if terms.iter().map(|&(a, _)| a).sum::<u64>() <= bound { return; }
+    terms.sort_by_key(|&(a, _)| std::cmp::Reverse(a));
+    match encoding {
+        PbEncoding::Bdd => {
+            let mut suffix_sums = vec![0; terms.len() + 1];
+            for i in (0..terms.len()).rev() { suffix_sums[i] = suffix_sums[i + 1] + terms[i].0 as i128; }
+            let mut bdd = PbBdd { terms: &terms, suffix_sums, layers: vec![BTreeMap::new(); terms.len()] };
+            match bdd.node(sink, 0, bound as i128).0 {
+                BddNode::True => {}
+                BddNode::False => sink.add_clause(Vec::new()),
+                BddNode::Var(root) => sink.add_clause(vec![root]),
+            }
+        }
+        PbEncoding::Adder => adder(sink, &terms, bound),
+    }
+}
+
+/// Adds the constraint "sum of coefficient · literal >= bound", as an upper bound on the
+/// coefficients of the negated literals.
+pub fn pb_at_least(sink: &mut dyn ClauseSink, terms: &[(u64, Lit)], bound: u64, encoding: PbEncoding) {
+    let total: u64 = terms.iter().map(|&(a, _)| a).sum();
+    if bound > total {
+        sink.add_clause(Vec::new());
+        return;
+    }
+    let negated: Vec<(u64, Lit)> = terms.iter().map(|&(a, l)| (a, l.not())).collect();
+    pb_at_most(sink, &negated, total - bound, encoding);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BddNode {
+    True,
+    False,
+    Var(Lit), // Implies the constraint over the remaining terms
+}
+
+/// The BDD of "sum of the terms from i on <= budget", built top-down.
+struct PbBdd<'a> {
+    terms: &'a [(u64, Lit)],
+    suffix_sums: Vec<i128>,
+    layers: Vec<BTreeMap<i128, (i128, BddNode)>>, // Term -> (budget interval start -> (end, node))
+}
+
+impl PbBdd<'_> {
+    /// The node for the terms from 'i' on under 'budget', and the interval of budgets it stands for.
+    fn node(&mut self, sink: &mut dyn ClauseSink, i: usize, budget: i128) -> (BddNode, i128, i128) {
+        if budget < 0 { return (BddNode::False, i128::MIN, -1); }
+        if budget >= self.suffix_sums[i] { return (BddNode::True, self.suffix_sums[i], i128::MAX); }
+        if let Some((&start, &(end, node))) = self.layers[i].range(..=budget).next_back() && budget <= end {
+            return (node, start, end);
+        }
+        let (a, lit) = (self.terms[i].0 as i128, self.terms[i].1);
+        let (high, high_start, high_end) = self.node(sink, i + 1, budget - a);
+        let (low, low_start, low_end) = self.node(sink, i + 1, budget);
+        let start = high_start.saturating_add(a).max(low_start);
+        let end = high_end.saturating_add(a).min(low_end);
+        let node = if high == low { high } else {
+            let var = fresh(sink);
+            match high {
+                BddNode::True => {}
+                BddNode::False => sink.add_clause(vec![var.not(), lit.not()]),
+                BddNode::Var(h) => sink.add_clause(vec![var.not(), lit.not(), h]),
+            }
+            match low {
+                BddNode::True => {}
+                BddNode::False => sink.add_clause(vec![var.not()]),
+                BddNode::Var(l) => sink.add_clause(vec![var.not(), l]),
+            }
+            BddNode::Var(var)
+        };
+        self.layers[i].insert(start, (end, node));
+        (node, start, end)
+    }
+}
+
+/// Sums the terms into binary with full and half adders (both directions of their definitions)
+/// and forbids every sum above 'bound': a 1 where the bound has a 0 and equal higher bits.
+fn adder(sink: &mut dyn ClauseSink, terms: &[(u64, Lit)], bound: u64) {
+    let mut buckets: Vec<Vec<Lit>> = vec![Vec::new(); 64];
+    for &(a, lit) in terms {
+        for (bit, bucket) in buckets.iter_mut().enumerate() {
+            if (a >> bit) & 1 == 1 { bucket.push(lit); }
+        }
+    }
+    let mut bits: Vec<Option<Lit>> = Vec::new();
+    let mut i = 0;
+    while i < buckets.len() {
+        while buckets[i].len() >= 2 {
+            let inputs: Vec<Lit> = if buckets[i].len() >= 3 {
+                buckets[i].drain(..3).collect()
+            } else {
+                buckets[i].drain(..2).collect()
+            };
+            let (sum, carry) = (fresh(sink), fresh(sink));
+            define_parity(sink, sum, &inputs);
+            define_at_least_two(sink, carry, &inputs);
+            buckets[i].push(sum);
+            if i + 1 == buckets.len() { buckets.push(Vec::new()); }
+            buckets[i + 1].push(carry);
+        }
+        bits.push(buckets[i].first().copied());
+        i += 1;
+    }
+
+    let bound_bit = |j: usize| j < 64 && (bound >> j) & 1 == 1;
+    'bits: for (i, &bit) in bits.iter().enumerate() {
+        let Some(bit) = bit else { continue };
+        if bound_bit(i) { continue; }
+        let mut clause = vec![bit.not()];
+        for (j, &higher) in bits.iter().enumerate().skip(i + 1) {
+            match (higher, bound_bit(j)) {
+                (Some(h), true) => clause.push(h.not()),
+                (Some(h), false) => clause.push(h),
+                (None, true) => continue 'bits, // This higher bit is already below the bound
+                (None, false) => {}
+            }
+        }
+        sink.add_clause(clause);
+    }
+}
+
+/// 'output' <-> XOR of 'inputs': one clause per assignment of the inputs.
+fn define_parity(sink: &mut dyn ClauseSink, output: Lit, inputs: &[Lit]) {
+    for assignment in 0..1u32 << inputs.len() {
+        let mut clause: Vec<Lit> = inputs.iter().enumerate()
+            .map(|(k, &l)| if (assignment >> k) & 1 == 1 { l.not() } else { l })
+            .collect();
+        clause.push(if assignment.count_ones() % 2 == 1 { output } else { output.not() });
+        sink.add_clause(clause);
+    }
+}
+
+/// 'output' <-> at least two of the 2 or 3 'inputs' are true: every pair of them implies the
+/// output, which implies one of every n - 1 inputs.
+fn define_at_least_two(sink: &mut dyn ClauseSink, output: Lit, inputs: &[Lit]) {
+    for (k, &a) in inputs.iter().enumerate() {
+        for &b in &inputs[k + 1..] { sink.add_clause(vec![a.not(), b.not(), output]); }
+        let mut others: Vec<Lit> = inputs.iter().enumerate().filter(|&(m, _)| m != k).map(|(_, &l)| l).collect();
+        others.push(output.not());
+        sink.add_clause(others);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::preprocessing::apply_replacements;
+use crate::{BranchingStrategy, Lit, Solver, SolverOptions, VarValue};
+
+// =========================================================================
+// Model Enumeration (AllSAT) with Projection
+// =========================================================================
+
+/// Iterator over the models of a `Solver`, one per assignment of the projection variables
+/// (see `Solver::models`). Each model is blocked by a clause before the next one is searched.
+pub struct Models<'a> {
+    solver: &'a mut Solver,
+    strategy: ProjectionFirst<'a>,
+    projection: Vec<usize>,
+    num_vars: usize,       // Variables of the formula when enumeration started (the length of the models)
+    options: SolverOptions, // Options of the solver, restored when the iterator is dropped
+}
+
+/// Branching strategy wrapper: with decision-based blocking, the projection variables are decided
+/// first (false first), so that the decisions on them imply the whole projection of the model.
+struct ProjectionFirst<'a> {
+    inner: &'a mut dyn BranchingStrategy,
+    projection: Vec<usize>,
+    decision_blocking: bool,
+    projected: Option<Vec<bool>>, // Representative var -> projected, computed after preprocessing
+    decisions: Vec<Lit>,          // Decisions on projected variables of the last model
+}
+
+impl Solver {
+    /// Enumerates the models of the formula that differ on the 'projection' variables
+    /// (all variables if None): one model per distinct projection, until the formula is exhausted.
+    /// Every model is blocked by a clause over the projection, which joins the formula, so learned
+    /// clauses are reused by later searches. Blocked clause and variable elimination are switched
+    /// off while it runs: they leave variables whose values come from reconstruction rather than
+    /// the search, and blocking by decisions would miss them. The options are restored when the
+    /// iterator is dropped.
+    pub fn models<'a>(&'a mut self, projection: Option<&[usize]>, strategy: &'a mut dyn BranchingStrategy) -> Models<'a> {
+        let options = self.options.clone();
+        self.options.blocked_clauses = false;
+        self.options.elimination = false;
+        let num_vars = self.num_vars;
+        let projection = projection.map_or_else(|| (0..num_vars).collect(), <[usize]>::to_vec);
+        let strategy = ProjectionFirst {
+            inner: strategy,
+            projection: projection.clone(),
+            decision_blocking: false,
+            projected: None,
+            decisions: Vec::new(),
+        };
+        Models { solver: self, strategy, projection, num_vars, options }
+    }
+}
+
+impl Models<'_> {
+    /// Blocks models by the negated decisions on projection variables instead of the whole projection.
+    /// The projection variables are then branched on first, so the decisions fix the projection,
+    /// and the blocking clauses are usually much shorter.
+    pub fn decision_blocking(mut self, enabled: bool) -> Self {
+        self.strategy.decision_blocking = enabled;
+        self
+    }
+}
+
+impl Iterator for Models<'_> {
+    type Item = Vec<bool>;
+
+    fn next(&mut self) -> Option<Vec<bool>> {
+        self.strategy.projected = None;
+        if !self.solver.solve(&mut self.strategy, false) { return None; }
+
+        let model = self.solver.model()[..self.num_vars].to_vec();
+        let blocking = if self.strategy.decision_blocking {
+            self.strategy.decisions.iter().map(|l| l.not()).collect()
+        } else {
+            self.projection.iter().map(|&var| Lit::new(var, model[var])).collect()
+        };
+        self.solver.add_clause(blocking);
+        Some(model)
+    }
+}
+
+impl Drop for Models<'_> {
+    fn drop(&mut self) {
+        self.solver.options = std::mem::take(&mut self.options);
+    }
+}
+
+impl BranchingStrategy for ProjectionFirst<'_> {
+    fn pick_branch(&mut self, solver: &Solver) -> Option<Lit> {
+        if !self.decision_blocking { return self.inner.pick_branch(solver); }
+
+        // Substitutions are only known once the search has started
+        let projected = self.projected.get_or_insert_with(|| {
+            let mut projected = vec![false; solver.num_vars];
+            let lits: Vec<Lit> = self.projection.iter().map(|&var| Lit::new(var, false)).collect();
+            for lit in apply_replacements(&lits, &solver.replacements) { projected[lit.var()] = true; }
+            projected
+        });
+        if let Some(var) = (0..projected.len()).find(|&var| projected[var] && solver.assignments[var] == VarValue::Unassigned) {
+            return Some(Lit::new(var, true));
+        }
+        let lit = self.inner.pick_branch(solver);
+        if lit.is_none() {
+            self.decisions = solver.trail.iter().copied()
+                .filter(|l| projected[l.var()] && solver.level[l.var()] > 0 && solver.reason[l.var()].is_none())
+                .collect();
+        }
+        lit
+    }
+
+    fn on_conflict(&mut self, involved_vars: &[usize]) { self.inner.on_conflict(involved_vars); }
+    fn on_assign(&mut self, var: usize) { self.inner.on_assign(var); }
+    fn on_unassign(&mut self, var: usize, old_value: bool) { self.inner.on_unassign(var, old_value); }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use crate::preprocessing::apply_replacements;
+use crate::{Clause, Lit, Solver, VarValue};
+
+// =========================================================================
+// Equivalent Literal Substitution
+// =========================================================================
+
+/// Finds classes of equivalent literals as the strongly connected components
+/// of the binary implication graph (clause (a v b) yields -a -> b and -b -> a).
+///
+/// Every class is mapped onto its literal with the smallest variable index, in the
+/// same format `apply_replacements` expects: var -> (representative var, invert).
+/// Returns a literal that ends up in the same component as its negation (UNSAT) as an error.
+pub fn find_equivalences(binaries: &[[Lit; 2]], num_vars: usize) -> Result<HashMap<usize, (usize, bool)>, Lit> {
+    let num_nodes = num_vars * 2;
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); num_nodes];
+    for &[a, b] in binaries {
+        adj[a.not().to_usize()].push(b.to_usize());
+        adj[b.not().to_usize()].push(a.to_usize());
+    }
+
+    let components = strongly_connected_components(&adj);
+
+    let mut replacements = HashMap::new();
+    let mut component_of = vec![usize::MAX; num_nodes];
+    for (id, members) in components.iter().enumerate() {
+        for &node in members { component_of[node] = id; }
+    }
+
+    for members in components.iter().filter(|m| m.len() > 1) {
+        let lits: Vec<Lit> = members.iter().map(|&code| Lit::new(code >> 1, code & 1 == 1)).collect();
+        if let Some(&lit) = lits.iter().find(|l| component_of[l.to_usize()] == component_of[l.not().to_usize()]) {
+            return Err(lit); // x <-> -x
+        }
+
+        let repr = *lits.iter().min_by_key(|l| l.var()).unwrap();
+        for &lit in &lits {
+            if lit.var() == repr.var() { continue; }
+            // lit <-> repr, so the positive literal of lit's variable maps to repr (flipped if lit is negative).
+            // The dual component produces the same entry, so inserting twice is harmless.
+            replacements.insert(lit.var(), (repr.var(), lit.is_neg() != repr.is_neg()));
+        }
+    }
+    Ok(replacements)
+}
+
+/// Iterative version of Tarjan's algorithm (the graphs can be deep enough to overflow the call stack).
+fn strongly_connected_components(adj: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let n = adj.len();
+    let mut index = vec![UNVISITED; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack: Vec<usize> = Vec::new();
+    let mut components = Vec::new();
+    let mut counter = 0;
+
+    for start in 0..n {
+        if index[start] != UNVISITED { continue; }
+
+        // Call stack of (node, index of the next outgoing edge to explore)
+        let mut calls: Vec<(usize, usize)> = vec![(start, 0)];
+        index[start] = counter;
+        lowlink[start] = counter;
+        counter += 1;
+        stack.push(start);
+        on_stack[start] = true;
+
+        while let Some(&(v, edge)) = calls.last() {
+            if edge < adj[v].len() {
+                calls.last_mut().unwrap().1 += 1;
+                let w = adj[v][edge];
+                if index[w] == UNVISITED {
+                    index[w] = counter;
+                    lowlink[w] = counter;
+                    counter += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[v] = lowlink[v].min(index[w]);
+                }
+                continue;
+            }
+
+            // All edges of 'v' explored
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[v]);
+            }
+            if lowlink[v] == index[v] {
+                let mut members = Vec::new();
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    members.push(w);
+                    if w == v { break; }
+                }
+                components.push(members);
+            }
+        }
+    }
+    components
+}
+
+impl Solver {
+    /// Detects equivalent literals in the binary implication graph and replaces every
+    /// class by its representative throughout the clause database.
+    /// Returns the number of substituted variables; sets `ok = false` if the formula is UNSAT.
+    /// Must be called at decision level 0.
+    pub fn substitute_equivalent_literals(&mut self) -> usize {
+        if !self.ok || self.decision_level() != 0 { return 0; }
+        if self.propagate().is_some() {
+            self.ok = false;
+            return 0;
+        }
+
+        let binaries = self.root_binary_clauses();
+        let replacements = match find_equivalences(&binaries, self.num_vars) {
+            Ok(replacements) => replacements,
+            Err(lit) => {
+                // 'lit' implies its negation through binary clauses, and vice versa
+                self.log_add(&[lit.not()]);
+                self.ok = false;
+                return 0;
+            }
+        };
+        let count = replacements.len();
+        if count > 0 {
+            self.apply_substitution(replacements);
+        }
+        count
+    }
+
+    /// Rewrites the clause database with 'replacements' (var -> (var, invert)) and
+    /// records every substituted variable on the reconstruction stack.
+    pub(crate) fn apply_substitution(&mut self, replacements: HashMap<usize, (usize, bool)>) {
+        let mut vars: Vec<usize> = replacements.keys().copied().collect();
+        vars.sort_unstable();
+
+        for &var in &vars {
+            let lit = Lit::new(var, false);
+            let repr = apply_replacements(&[lit], &replacements)[0];
+            self.reconstruction.push_equivalence(lit, repr);
+
+            // Root assignments of a substituted variable carry over to its representative
+            let mapped = match self.assignments[var] {
+                VarValue::Unassigned => continue,
+                VarValue::True => repr,
+                VarValue::False => repr.not(),
+            };
+            match Self::value_lit(&self.assignments, mapped) {
+                VarValue::Unassigned => self.unchecked_enqueue(mapped, None),
+                VarValue::False => { self.ok = false; return; }
+                VarValue::True => {}
+            }
+        }
+
+        let rewritten: Vec<Clause> = std::mem::take(&mut self.clauses).into_iter()
+            .map(|c| Clause { lits: apply_replacements(&c.lits, &replacements), learned: c.learned })
+            .collect();
+        self.replacements.extend(replacements);
+        self.replace_clause_database(rewritten);
+        if self.ok && !self.cardinalities.is_empty() { self.attach_cardinalities(); }
+        if self.ok && !self.pbs.is_empty() { self.attach_pbs(); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RandomStrategy;
+
+    fn lit(var: usize, neg: bool) -> Lit {
+        Lit::new(var, neg)
+    }
+
+    #[test]
+    fn test_class_mapped_to_representative() {
+        // x0 -> -x1 -> x2 -> x0: one class, x1 appears negated; x3 only implies x0
+        let binaries = [
+            [lit(0, true), lit(1, true)],
+            [lit(1, false), lit(2, false)],
+            [lit(2, true), lit(0, false)],
+            [lit(3, true), lit(0, false)],
+        ];
+        let replacements = find_equivalences(&binaries, 4).unwrap();
+        assert_eq!(replacements.len(), 2);
+        assert_eq!(replacements[&1], (0, true));
+        assert_eq!(replacements[&2], (0, false));
+        assert_eq!(apply_replacements(&[lit(1, true), lit(2, true), lit(3, false)], &replacements), vec![lit(0, false), lit(0, true), lit(3, false)]);
+    }
+
+    #[test]
+    fn test_literal_equivalent_to_its_negation() {
+        // x0 -> x1 -> -x0 and -x0 -> x1 -> x0
+        let clauses = [
+            vec![lit(0, true), lit(1, false)],
+            vec![lit(1, true), lit(0, true)],
+            vec![lit(0, false), lit(1, false)],
+            vec![lit(1, true), lit(0, false)],
+        ];
+        let binaries: Vec<[Lit; 2]> = clauses.iter().map(|c| [c[0], c[1]]).collect();
+        assert!(matches!(find_equivalences(&binaries, 2), Err(l) if l.var() <= 1));
+
+        let mut solver = Solver::new(2);
+        solver.options.preprocessing = "els".parse().unwrap();
+        for clause in &clauses {
+            solver.add_clause(clause.clone());
+        }
+        assert!(!solver.solve(&mut RandomStrategy::new(2), false));
+    }
+
+    #[test]
+    fn test_model_of_substituted_variables() {
+        // x1 = -x0 and x2 = x1 by binary clauses, plus two clauses that keep x3 in the formula
+        let clauses = [
+            vec![lit(0, false), lit(1, false)],
+            vec![lit(0, true), lit(1, true)],
+            vec![lit(1, true), lit(2, false)],
+            vec![lit(2, true), lit(1, false)],
+            vec![lit(3, false), lit(2, false), lit(0, false)],
+            vec![lit(3, true), lit(0, false)],
+        ];
+        let mut solver = Solver::new(4);
+        for clause in &clauses {
+            solver.add_clause(clause.clone());
+        }
+        assert_eq!(solver.substitute_equivalent_literals(), 2);
+        assert!(solver.clauses.iter().all(|c| c.lits.iter().all(|l| l.var() != 1 && l.var() != 2)));
+        assert!(solver.solve(&mut RandomStrategy::new(4), false));
+        let model = solver.model();
+        assert_eq!((model[1], model[2]), (!model[0], !model[0]));
+        assert!(clauses.iter().all(|c| c.iter().any(|l| model[l.var()] != l.is_neg())));
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+use crate::{Clause, Lit, Solver};
+
+// =========================================================================
+// Gate Extraction
+// =========================================================================
+
+/// Gates with more inputs are only recognized syntactically (AND/OR).
+const MAX_SEMANTIC_INPUTS: usize = 4;
+/// Variables with more candidate input pairs (see `candidate_inputs`) skip the unions of pairs.
+const MAX_INPUT_PAIRS: usize = 24;
+
+const NONE: usize = usize::MAX;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GateKind {
+    And,
+    Or,
+    /// The output is the XOR of the input literals.
+    Xor,
+    /// Inputs are [condition, then, else].
+    Ite,
+    /// The output is true iff at least two of the three input literals are true.
+    Majority,
+}
+
+/// A definition 'output = kind(inputs)' encoded (Tseitin style) by the clauses 'clauses'.
+#[derive(Debug, Clone)]
+pub struct Gate {
+    pub kind: GateKind,
+    pub output: usize,
+    pub inputs: Vec<Lit>,
+    /// Indices of the defining clauses, which together are equivalent to the definition.
+    pub clauses: Vec<usize>,
+}
+
+/// Finds gate definitions in the clauses, at most one per output variable.
+/// - AND/OR gates of any size syntactically: a clause (o | -a | -b ..) with binaries (-o | a), (-o | b) ..
+/// - Gates with up to 4 inputs semantically: the clauses containing the output variable and
+///   otherwise only candidate inputs must force the output to a value for every input assignment.
+///   The truth table is then matched against AND, OR, XOR, ITE and majority.
+///
+/// Definitions may still be cyclic (a gate using a variable that is defined later on), see `Circuit`.
+pub fn find_gates(clauses: &[Clause], num_vars: usize) -> Vec<Gate> {
+    let mut gates = Vec::new();
+    let mut defined = vec![false; num_vars];
+
+    // 1. Syntactic AND/OR gates
+    let mut binaries: HashMap<(Lit, Lit), usize> = HashMap::new();
+    let mut binary_count = vec![0usize; num_vars * 2];
+    for (i, c) in clauses.iter().enumerate() {
+        if let [a, b] = c.lits[..] {
+            binaries.insert(binary_key(a, b), i);
+            binary_count[a.to_usize()] += 1;
+            binary_count[b.to_usize()] += 1;
+        }
+    }
+    for (i, c) in clauses.iter().enumerate() {
+        if c.lits.len() < 3 { continue; }
+        for &o in &c.lits {
+            if defined[o.var()] || binary_count[o.not().to_usize()] < c.lits.len() - 1 { continue; }
+            // (o | -a | -b ..) and (-o | a), (-o | b) .. define o = AND(a, b, ..)
+            let mut used = vec![i];
+            let complete = c.lits.iter().filter(|&&l| l != o).all(|&l| {
+                match binaries.get(&binary_key(o.not(), l.not())) {
+                    Some(&idx) => { used.push(idx); true }
+                    None => false,
+                }
+            });
+            if !complete { continue; }
+            let (kind, inputs) = if o.is_neg() {
+                // -x = AND(-a, -b ..) is x = OR(a, b ..)
+                (GateKind::Or, c.lits.iter().copied().filter(|&l| l != o).collect())
+            } else {
+                (GateKind::And, c.lits.iter().filter(|&&l| l != o).map(|l| l.not()).collect())
+            };
+            defined[o.var()] = true;
+            gates.push(Gate { kind, output: o.var(), inputs, clauses: used });
+            break;
+        }
+    }
+
+    // 2. Semantic gates with few inputs. Binary clauses only occur in AND/OR definitions,
+    // which are found above, so they are left out.
+    let mut occurs: Vec<Vec<usize>> = vec![Vec::new(); num_vars];
+    for (i, c) in clauses.iter().enumerate() {
+        if c.lits.len() < 3 || c.lits.len() > MAX_SEMANTIC_INPUTS + 1 { continue; }
+        for lit in &c.lits {
+            occurs[lit.var()].push(i);
+        }
+    }
+    // Tseitin encodings usually number outputs after their inputs, so the clauses of an XOR
+    // (which define each of its variables) are read as a definition of the largest one
+    let mut seen_clauses: HashSet<Vec<usize>> = HashSet::new();
+    let mut groups: Vec<(VarSet, usize)> = Vec::new();
+    for (var, occ) in occurs.iter().enumerate().rev() {
+        if defined[var] || occ.len() < 2 { continue; }
+        // The definition forces the output both ways
+        let polarity = |neg: bool| occ.iter().any(|&i| clauses[i].lits.contains(&Lit::new(var, neg)));
+        if !polarity(false) || !polarity(true) { continue; }
+
+        // The clauses of 'var' sorted by their other variables
+        groups.clear();
+        groups.extend(occ.iter().filter_map(|&i| {
+            var_set(clauses[i].lits.iter().map(|l| l.var()).filter(|&v| v != var)).map(|set| (set, i))
+        }));
+        groups.sort_unstable();
+        for inputs in candidate_inputs(&groups) {
+            let Some(mut gate) = check_semantic_gate(var, &inputs, &groups, clauses) else { continue };
+            gate.clauses.sort_unstable();
+            if seen_clauses.insert(gate.clauses.clone()) {
+                defined[var] = true;
+                gates.push(gate);
+            }
+            break;
+        }
+    }
+    gates
+}
+
+fn binary_key(a: Lit, b: Lit) -> (Lit, Lit) {
+    if a.to_usize() <= b.to_usize() { (a, b) } else { (b, a) }
+}
+
+/// Up to `MAX_SEMANTIC_INPUTS` distinct variables in increasing order, padded with NONE.
+type VarSet = [usize; MAX_SEMANTIC_INPUTS];
+
+fn var_set(vars: impl Iterator<Item = usize>) -> Option<VarSet> {
+    let mut set = [NONE; MAX_SEMANTIC_INPUTS];
+    let mut len = 0;
+    for v in vars {
+        if set[..len].contains(&v) { continue; }
+        if len == MAX_SEMANTIC_INPUTS { return None; }
+        set[len] = v;
+        len += 1;
+    }
+    set[..len].sort_unstable();
+    Some(set)
+}
+
+fn set_vars(set: &VarSet) -> &[usize] {
+    &set[..set.iter().position(|&v| v == NONE).unwrap_or(MAX_SEMANTIC_INPUTS)]
+}
+
+/// Candidate input sets, smallest first: the other variables of each clause,
+/// unions of two variable pairs, and all neighbours together.
+fn candidate_inputs(groups: &[(VarSet, usize)]) -> Vec<VarSet> {
+    let runs: Vec<(VarSet, usize)> = groups.chunk_by(|a, b| a.0 == b.0).map(|run| (run[0].0, run.len())).collect();
+    let mut candidates: Vec<VarSet> = runs.iter().map(|&(set, _)| set).filter(|set| set_vars(set).len() >= 2).collect();
+
+    // ITE and majority gates consist of two ternary clauses for each of some pairs of their inputs
+    let pairs: Vec<&VarSet> = runs.iter().filter(|(set, count)| set_vars(set).len() == 2 && *count >= 2).map(|(set, _)| set).collect();
+    if pairs.len() <= MAX_INPUT_PAIRS {
+        for (k, a) in pairs.iter().enumerate() {
+            for b in &pairs[k + 1..] {
+                if let Some(union) = var_set(set_vars(a).iter().chain(set_vars(b)).copied())
+                    && set_vars(&union).len() == 3 {
+                    candidates.push(union);
+                }
+            }
+        }
+    }
+    if let Some(neighbours) = var_set(runs.iter().flat_map(|(set, _)| set_vars(set).iter().copied())) {
+        candidates.push(neighbours);
+    }
+
+    candidates.sort_unstable_by_key(|set| (set_vars(set).len(), *set));
+    candidates.dedup();
+    candidates
+}
+
+/// Builds the truth table of 'target' over 'inputs' from the clauses that contain 'target'
+/// and otherwise only inputs, and matches it against the known gate types.
+/// Every input assignment must force 'target' to exactly one value, so the clauses
+/// are equivalent to the definition. Inputs the table does not depend on are dropped.
+fn check_semantic_gate(target: usize, inputs: &VarSet, groups: &[(VarSet, usize)], clauses: &[Clause]) -> Option<Gate> {
+    let inputs = set_vars(inputs);
+    let k = inputs.len();
+    // The clauses over subsets of the inputs. A clause over m of the k inputs forces the output
+    // in 2^(k-m) of the 2^k input assignments, so together they must cover at least all of them.
+    let subset = |set: &VarSet| set_vars(set).iter().all(|v| inputs.contains(v));
+    let covered: usize = groups.iter().filter(|(set, _)| subset(set)).map(|(set, _)| 1 << (k - set_vars(set).len())).sum();
+    if covered < 1 << k { return None; }
+
+    // Relevant clauses as (positive input bits, negative input bits, target literal)
+    let mut relevant = Vec::new();
+    let mut used = Vec::new();
+    'clauses: for &(_, idx) in groups.iter().filter(|(set, _)| subset(set)) {
+        let (mut pos, mut neg, mut target_lit) = (0u32, 0u32, None);
+        for lit in &clauses[idx].lits {
+            if lit.var() == target {
+                if target_lit.is_some_and(|t: Lit| t != *lit) { continue 'clauses; } // Tautology
+                target_lit = Some(*lit);
+                continue;
+            }
+            let bit = inputs.iter().position(|&v| v == lit.var()).unwrap();
+            if lit.is_neg() { neg |= 1 << bit; } else { pos |= 1 << bit; }
+        }
+        if pos & neg != 0 { continue; } // Tautology
+        relevant.push((pos, neg, target_lit.unwrap()));
+        used.push(idx);
+    }
+    if relevant.len() < 2 { return None; }
+
+    let mut table = 0u32; // Bit i: value of 'target' under input assignment i
+    for i in 0..(1u32 << k) {
+        let (mut forced_true, mut forced_false) = (false, false);
+        for &(pos, neg, lit) in &relevant {
+            // The clause is satisfied by the inputs if a positive input is 1 or a negative one 0
+            if i & pos != 0 || !i & neg != 0 { continue; }
+            if lit.is_neg() { forced_false = true; } else { forced_true = true; }
+        }
+        if forced_true == forced_false { return None; } // Forbidden or unconstrained
+        if forced_true { table |= 1 << i; }
+    }
+
+    let (table, support) = project(table, k);
+    let inputs: Vec<usize> = support.iter().map(|&j| inputs[j]).collect();
+    let (kind, lits) = classify(table, &inputs)?;
+    Some(Gate { kind, output: target, inputs: lits, clauses: used })
+}
+
+/// Removes the inputs the truth table does not depend on.
+/// Returns the reduced table and the positions of the remaining inputs.
+fn project(table: u32, k: usize) -> (u32, Vec<usize>) {
+    let depends = |j: usize| (0..(1u32 << k)).any(|i| (table >> i) & 1 != (table >> (i ^ (1 << j))) & 1);
+    let support: Vec<usize> = (0..k).filter(|&j| depends(j)).collect();
+    let mut reduced = 0u32;
+    for i in 0..(1u32 << support.len()) {
+        // Spread the bits of i onto the support positions, the other inputs stay 0
+        let full = support.iter().enumerate().fold(0u32, |acc, (b, &j)| acc | (((i >> b) & 1) << j));
+        reduced |= ((table >> full) & 1) << i;
+    }
+    (reduced, support)
+}
+
+/// Matches a truth table over the variables 'inputs' (bit j of the row index is input j).
+fn classify(table: u32, inputs: &[usize]) -> Option<(GateKind, Vec<Lit>)> {
+    let k = inputs.len();
+    if k < 2 { return None; }
+    let rows = 1u32 << k;
+    let full = (1u32 << rows) - 1;
+    let row_lits = |i: u32| -> Vec<Lit> { inputs.iter().enumerate().map(|(j, &v)| Lit::new(v, (i >> j) & 1 == 0)).collect() };
+
+    if table.count_ones() == 1 {
+        // True in exactly one row: the AND of the literals true in that row
+        return Some((GateKind::And, row_lits(table.trailing_zeros())));
+    }
+    if (!table & full).count_ones() == 1 {
+        // False in exactly one row: the OR of the literals false in that row
+        let lits = row_lits((!table & full).trailing_zeros()).iter().map(|l| l.not()).collect();
+        return Some((GateKind::Or, lits));
+    }
+    let parity = (0..rows).filter(|i| i.count_ones() % 2 == 1).fold(0u32, |acc, i| acc | (1 << i));
+    if table == parity || table == !parity & full {
+        let mut lits: Vec<Lit> = inputs.iter().map(|&v| Lit::new(v, false)).collect();
+        if table != parity { lits[0] = lits[0].not(); }
+        return Some((GateKind::Xor, lits));
+    }
+    if k != 3 { return None; }
+
+    let eval = |lit_of: &dyn Fn(u32, usize) -> bool, f: &dyn Fn(&[bool]) -> bool, order: [usize; 3]| {
+        (0..rows).all(|i| {
+            let values = [lit_of(i, order[0]), lit_of(i, order[1]), lit_of(i, order[2])];
+            f(&values) == ((table >> i) & 1 == 1)
+        })
+    };
+    for flips in 0..8u32 {
+        let lit_of = |i: u32, j: usize| ((i >> j) & 1 == 1) != ((flips >> j) & 1 == 1);
+        let lits = |order: [usize; 3]| order.iter().map(|&j| Lit::new(inputs[j], (flips >> j) & 1 == 1)).collect();
+        if eval(&lit_of, &|x| (x[0] as u8 + x[1] as u8 + x[2] as u8) >= 2, [0, 1, 2]) {
+            return Some((GateKind::Majority, lits([0, 1, 2])));
+        }
+        // The condition is never negated: ITE(-c, t, e) is ITE(c, e, t)
+        for order in [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]] {
+            if (flips >> order[0]) & 1 == 0 && eval(&lit_of, &|x| if x[0] { x[1] } else { x[2] }, order) {
+                return Some((GateKind::Ite, lits(order)));
+            }
+        }
+    }
+    None
+}
+
+/// The gates of a formula as a circuit: an acyclic set of definitions in topological order.
+pub struct Circuit {
+    gates: Vec<Gate>,
+    gate_of: Vec<usize>, // Variable -> index of the gate defining it (NONE for free variables)
+}
+
+impl Circuit {
+    /// Extracts the gates of the clauses (see `find_gates`). Definitions that would close
+    /// a cycle are dropped, which turns their outputs into inputs of the circuit.
+    pub fn from_clauses(clauses: &[Clause], num_vars: usize) -> Self {
+        let gates = find_gates(clauses, num_vars);
+        let mut definition = vec![NONE; num_vars];
+        for (g, gate) in gates.iter().enumerate() { definition[gate.output] = g; }
+
+        // Kahn's algorithm over the gates, 'pending' counts inputs defined by unprocessed gates
+        let mut users: Vec<Vec<usize>> = vec![Vec::new(); gates.len()];
+        let mut pending = vec![0usize; gates.len()];
+        for (g, gate) in gates.iter().enumerate() {
+            for lit in &gate.inputs {
+                let d = definition[lit.var()];
+                if d != NONE {
+                    users[d].push(g);
+                    pending[g] += 1;
+                }
+            }
+        }
+        let mut ready: Vec<usize> = (0..gates.len()).filter(|&g| pending[g] == 0).collect();
+        let mut done = vec![false; gates.len()];
+        let mut order = Vec::with_capacity(gates.len());
+        let mut dropped = 0;
+        let mut next_unprocessed = 0;
+        let mut stamp = vec![NONE; gates.len()];
+        let mut walks = 0;
+        loop {
+            while let Some(g) = ready.pop() {
+                if done[g] { continue; } // Dropped before its inputs were processed
+                done[g] = true;
+                order.push(g);
+                for &u in &users[g] {
+                    pending[u] -= 1;
+                    if pending[u] == 0 { ready.push(u); }
+                }
+            }
+            if order.len() + dropped == gates.len() { break; }
+
+            // All remaining gates wait on a cycle: walk along unprocessed inputs until a gate
+            // repeats, which lies on the cycle, and drop it
+            while done[next_unprocessed] { next_unprocessed += 1; }
+            walks += 1;
+            let mut g = next_unprocessed;
+            while stamp[g] != walks {
+                stamp[g] = walks;
+                g = gates[g].inputs.iter()
+                    .map(|l| definition[l.var()])
+                    .find(|&d| d != NONE && !done[d])
+                    .unwrap();
+            }
+            done[g] = true;
+            dropped += 1;
+            for &u in &users[g] {
+                pending[u] -= 1;
+                if pending[u] == 0 { ready.push(u); }
+            }
+        }
+
+        let mut gate_of = vec![NONE; num_vars];
+        let mut slots: Vec<Option<Gate>> = gates.into_iter().map(Some).collect();
+        let gates: Vec<Gate> = order.into_iter().map(|g| slots[g].take().unwrap()).collect();
+        for (g, gate) in gates.iter().enumerate() { gate_of[gate.output] = g; }
+        Circuit { gates, gate_of }
+    }
+
+    /// The gates in topological order: every input is defined by an earlier gate or free.
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    /// The gate defining 'var', if any.
+    pub fn gate(&self, var: usize) -> Option<&Gate> {
+        self.gate_of.get(var).and_then(|&g| self.gates.get(g))
+    }
+
+    /// Variables used as gate inputs that are not defined by a gate.
+    pub fn inputs(&self) -> Vec<usize> {
+        let mut vars: Vec<usize> = self.gates.iter()
+            .flat_map(|g| g.inputs.iter().map(|l| l.var()))
+            .filter(|&v| self.gate_of[v] == NONE)
+            .collect();
+        vars.sort_unstable();
+        vars.dedup();
+        vars
+    }
+
+    /// Gate outputs that are not used as inputs by other gates.
+    pub fn outputs(&self) -> Vec<usize> {
+        let mut used = vec![false; self.gate_of.len()];
+        for lit in self.gates.iter().flat_map(|g| &g.inputs) { used[lit.var()] = true; }
+        self.gates.iter().map(|g| g.output).filter(|&v| !used[v]).collect()
+    }
+
+    /// Writes the circuit in a readable form with DIMACS variable numbers, one gate per line.
+    pub fn dump(&self, out: &mut dyn Write) -> io::Result<()> {
+        let numbers = |vars: Vec<usize>| vars.iter().map(|v| (v + 1).to_string()).collect::<Vec<_>>().join(" ");
+        writeln!(out, "c {} gates", self.gates.len())?;
+        writeln!(out, "c inputs: {}", numbers(self.inputs()))?;
+        writeln!(out, "c outputs: {}", numbers(self.outputs()))?;
+        for gate in &self.gates {
+            writeln!(out, "{}", gate)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for GateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GateKind::And => "AND",
+            GateKind::Or => "OR",
+            GateKind::Xor => "XOR",
+            GateKind::Ite => "ITE",
+            GateKind::Majority => "MAJ",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// DIMACS numbering, e.g. "3 = AND(1, -2)"
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inputs: Vec<String> = self.inputs.iter()
+            .map(|l| format!("{}{}", if l.is_neg() { "-" } else { "" }, l.var() + 1))
+            .collect();
+        write!(f, "{} = {}({})", self.output + 1, self.kind, inputs.join(", "))
+    }
+}
+
+impl Solver {
+    /// The circuit encoded by the irredundant clauses under the root assignment.
+    pub fn circuit(&self) -> Circuit {
+        let clauses: Vec<Clause> = self.root_clauses().into_iter().filter(|c| !c.learned).collect();
+        Circuit::from_clauses(&clauses, self.num_vars)
+    }
+}
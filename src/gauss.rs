@@ -0,0 +1,352 @@
+use std::collections::HashSet;
+use crate::preprocessing::{apply_replacements, cut_xor, gauss_jordan, xor_blocks, xor_to_cnf, BitRow, XorConstraint};
+use crate::{Lit, Solver, VarValue};
+
+// =========================================================================
+// Native XOR Constraints: Gauss-Jordan Propagation
+// =========================================================================
+
+const NONE: usize = usize::MAX;
+
+/// A system of XOR constraints kept in reduced row echelon form during search,
+/// as one independent block per connected component of the XORs.
+pub(crate) struct GaussMatrix {
+    blocks: Vec<XorBlock>,
+    location: Vec<(usize, usize)>, // Variable -> (block, column), (NONE, NONE) if not in the matrix
+}
+
+/// The rows of one connected component over its own dense columns.
+///
+/// Every row has a "basic" column that occurs in no other row, and watches one
+/// unassigned non-basic column. As long as both are unassigned the row cannot propagate.
+/// - When a watched column is assigned, the row looks for another unassigned non-basic column.
+///   If there is none, the basic variable is implied (or the row is checked for a conflict).
+/// - When a basic column is assigned, the row pivots on one of its unassigned non-basic
+///   columns, which eliminates that column from all other rows.
+///
+/// The matrix is never restored on backtrack: any basis describes the same system.
+/// A watch only stays on an assigned column if it was the last one assigned in the row,
+/// so backtracking can never leave a row with a single unassigned variable behind.
+struct XorBlock {
+    vars: Vec<usize>,            // Column -> variable
+    rows: Vec<BitRow>,
+    basic: Vec<usize>,           // Row -> basic column
+    row_of_basic: Vec<usize>,    // Column -> row it is basic in (NONE for non-basic columns)
+    watch: Vec<usize>,           // Row -> watched non-basic column
+    watchers: Vec<Vec<usize>>,   // Column -> rows watching it
+}
+
+/// Reason clause for 'lit' (first) or a conflict clause: the other variables
+/// of the row with the literals their current values falsify.
+type Explanation = Vec<Lit>;
+
+impl GaussMatrix {
+    /// Builds the matrix from XORs over unassigned variables (at decision level 0).
+    /// Rows reduced to a single variable are returned in 'units' instead of being kept.
+    /// Returns None if the system is inconsistent.
+    pub(crate) fn new(xors: &[XorConstraint], num_vars: usize, units: &mut Vec<Lit>) -> Option<Self> {
+        let mut matrix = GaussMatrix { blocks: Vec::new(), location: vec![(NONE, NONE); num_vars] };
+        for (vars, rows) in xor_blocks(xors, num_vars) {
+            let block = XorBlock::new(vars, rows, units)?;
+            if block.rows.is_empty() { continue; }
+            for (col, &v) in block.vars.iter().enumerate() {
+                matrix.location[v] = (matrix.blocks.len(), col);
+            }
+            matrix.blocks.push(block);
+        }
+        Some(matrix)
+    }
+
+    pub(crate) fn num_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub(crate) fn num_rows(&self) -> usize {
+        self.blocks.iter().map(|b| b.rows.len()).sum()
+    }
+
+    pub(crate) fn num_columns(&self) -> usize {
+        self.blocks.iter().map(|b| b.vars.len()).sum()
+    }
+
+    /// Processes the assignment of 'var'. Implied literals are appended to 'implied' with their
+    /// reason clauses (they may already be assigned by then, the caller checks).
+    /// Returns the conflict clause if a row is violated.
+    pub(crate) fn assigned(
+        &mut self,
+        var: usize,
+        values: &[VarValue],
+        level: &[usize],
+        implied: &mut Vec<(Lit, Explanation)>,
+    ) -> Option<Explanation> {
+        let (b, col) = self.location.get(var).copied().unwrap_or((NONE, NONE));
+        if b == NONE { return None; }
+        self.blocks[b].assigned(col, values, level, implied)
+    }
+}
+
+impl XorBlock {
+    fn new(vars: Vec<usize>, mut rows: Vec<BitRow>, units: &mut Vec<Lit>) -> Option<Self> {
+        // Gauss-Jordan elimination: afterwards every pivot column occurs in exactly one row
+        let basic = gauss_jordan(&mut rows, vars.len());
+        if rows[basic.len()..].iter().any(|r| r.rhs) { return None; } // 0 = 1
+        rows.truncate(basic.len());
+
+        let mut block = XorBlock {
+            row_of_basic: vec![NONE; vars.len()],
+            watchers: vec![Vec::new(); vars.len()],
+            vars,
+            rows: Vec::new(),
+            basic: Vec::new(),
+            watch: Vec::new(),
+        };
+        for (row, col) in rows.into_iter().zip(basic) {
+            let Some(other) = row.ones().find(|&c| c != col) else {
+                units.push(Lit::new(block.vars[col], !row.rhs));
+                continue;
+            };
+            let r = block.rows.len();
+            block.row_of_basic[col] = r;
+            block.watchers[other].push(r);
+            block.rows.push(row);
+            block.basic.push(col);
+            block.watch.push(other);
+        }
+        Some(block)
+    }
+
+    /// Processes the assignment of the variable in column 'col' (see `GaussMatrix::assigned`).
+    fn assigned(
+        &mut self,
+        col: usize,
+        values: &[VarValue],
+        level: &[usize],
+        implied: &mut Vec<(Lit, Explanation)>,
+    ) -> Option<Explanation> {
+        let r = self.row_of_basic[col];
+        if r != NONE {
+            // Move the basis away from the assigned variable if possible
+            let watched = self.watch[r];
+            let candidate = self.rows[r].ones().find(|&c| c != col && c != watched && self.is_unassigned(c, values));
+            return match candidate {
+                Some(new_basic) => self.pivot(r, new_basic, values, level, implied),
+                None => self.evaluate(r, values, implied),
+            };
+        }
+
+        let rows = std::mem::take(&mut self.watchers[col]);
+        let mut kept = Vec::with_capacity(rows.len());
+        let mut conflict = None;
+        for (i, &r) in rows.iter().enumerate() {
+            if conflict.is_some() {
+                kept.extend_from_slice(&rows[i..]);
+                break;
+            }
+            let basic = self.basic[r];
+            let replacement = self.rows[r].ones().find(|&c| c != col && c != basic && self.is_unassigned(c, values));
+            match replacement {
+                Some(new_watch) => {
+                    self.watch[r] = new_watch;
+                    self.watchers[new_watch].push(r);
+                }
+                None => {
+                    kept.push(r);
+                    conflict = self.evaluate(r, values, implied);
+                }
+            }
+        }
+        self.watchers[col].extend(kept);
+        conflict
+    }
+
+    /// Makes 'new_basic' the basic column of row 'r' and eliminates it from all other rows.
+    fn pivot(
+        &mut self,
+        r: usize,
+        new_basic: usize,
+        values: &[VarValue],
+        level: &[usize],
+        implied: &mut Vec<(Lit, Explanation)>,
+    ) -> Option<Explanation> {
+        let old_basic = self.basic[r];
+        self.row_of_basic[old_basic] = NONE;
+        self.row_of_basic[new_basic] = r;
+        self.basic[r] = new_basic;
+
+        let pivot = self.rows[r].clone();
+        let mut conflict = None;
+        for r2 in 0..self.rows.len() {
+            if r2 == r || !self.rows[r2].get_bit(new_basic) { continue; }
+            self.rows[r2].xor_with(&pivot);
+
+            let old_watch = self.watch[r2];
+            if self.rows[r2].get_bit(old_watch) { continue; } // Watch survived the elimination
+
+            // Re-watch an unassigned non-basic column. If there is none, watch the one assigned
+            // last (the old basic of 'r' is in the row and was just assigned), then evaluate.
+            let list = &mut self.watchers[old_watch];
+            if let Some(pos) = list.iter().position(|&x| x == r2) { list.swap_remove(pos); }
+            let basic = self.basic[r2];
+            let new_watch = match self.rows[r2].ones().find(|&c| c != basic && self.is_unassigned(c, values)) {
+                Some(c) => c,
+                None => {
+                    let latest = self.rows[r2].ones()
+                        .filter(|&c| c != basic)
+                        .max_by_key(|&c| level[self.vars[c]])
+                        .unwrap();
+                    if conflict.is_none() { conflict = self.evaluate(r2, values, implied); }
+                    latest
+                }
+            };
+            self.watch[r2] = new_watch;
+            self.watchers[new_watch].push(r2);
+        }
+        conflict
+    }
+
+    /// Row 'r' has at most one unassigned variable: implies it or checks the parity.
+    fn evaluate(&self, r: usize, values: &[VarValue], implied: &mut Vec<(Lit, Explanation)>) -> Option<Explanation> {
+        let mut parity = self.rows[r].rhs;
+        let mut open = None;
+        let mut explanation = vec![Lit::new(0, false)]; // Slot for the implied literal
+        for c in self.rows[r].ones() {
+            let var = self.vars[c];
+            match values[var] {
+                VarValue::Unassigned => {
+                    if open.is_some() { return None; } // Still two unassigned variables
+                    open = Some(var);
+                }
+                VarValue::True => {
+                    parity = !parity;
+                    explanation.push(Lit::new(var, true));
+                }
+                VarValue::False => explanation.push(Lit::new(var, false)),
+            }
+        }
+
+        match open {
+            Some(var) => {
+                // var = rhs ^ (sum of the assigned variables)
+                let lit = Lit::new(var, !parity);
+                explanation[0] = lit;
+                implied.push((lit, explanation));
+                None
+            }
+            None if parity => {
+                explanation.swap_remove(0);
+                Some(explanation)
+            }
+            None => None,
+        }
+    }
+
+    fn is_unassigned(&self, col: usize, values: &[VarValue]) -> bool {
+        values[self.vars[col]] == VarValue::Unassigned
+    }
+}
+
+impl Solver {
+    /// Adds the XOR constraint 'xor' (the variables sum to 'rhs' modulo 2) to the formula.
+    /// With `options.xor_propagation` it is kept as a native constraint, otherwise it is encoded
+    /// into clauses, cut into pieces of at most `options.xor_cut_size` variables
+    /// (uncut while a proof is written).
+    /// Must be called at decision level 0. Returns false if the formula became UNSAT.
+    pub fn add_xor(&mut self, xor: XorConstraint) -> bool {
+        let lits: Vec<Lit> = xor.vars.iter().map(|&v| Lit::new(v, false)).collect();
+        if !self.restore_clauses(&lits) { return false; }
+        let xor = self.simplify_xor(&xor);
+        match xor.vars.len() {
+            0 => {
+                if xor.rhs { self.ok = false; }
+                self.ok
+            }
+            1 => self.add_clause_internal(vec![Lit::new(xor.vars[0], !xor.rhs)], false),
+            _ if self.options.xor_propagation && self.proof.is_none() => {
+                self.xors.push(xor);
+                true
+            }
+            _ => {
+                // A proof refers to the direct encoding, so the XOR is not cut then
+                let cut_size = if self.proof.is_some() { xor.vars.len() } else { self.options.xor_cut_size };
+                let pieces = cut_xor(&xor.vars, xor.rhs, cut_size, &mut || self.new_var());
+                for piece in pieces {
+                    for clause in xor_to_cnf(&piece.vars, piece.rhs) {
+                        if !self.add_clause_internal(clause.lits, false) { return false; }
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    /// Maps the variables of 'xor' onto their representatives and folds root assignments
+    /// into the right-hand side. Variables occurring twice cancel out.
+    fn simplify_xor(&self, xor: &XorConstraint) -> XorConstraint {
+        let lits: Vec<Lit> = xor.vars.iter().map(|&v| Lit::new(v, false)).collect();
+        let mut rhs = xor.rhs;
+        let mut vars = Vec::with_capacity(lits.len());
+        for lit in apply_replacements(&lits, &self.replacements) {
+            rhs ^= lit.is_neg();
+            match self.assignments[lit.var()] {
+                VarValue::Unassigned => vars.push(lit.var()),
+                VarValue::True => rhs = !rhs,
+                VarValue::False => {}
+            }
+        }
+        // x ^ x = 0
+        vars.sort_unstable();
+        let mut reduced: Vec<usize> = Vec::with_capacity(vars.len());
+        for v in vars {
+            if reduced.last() == Some(&v) { reduced.pop(); } else { reduced.push(v); }
+        }
+        XorConstraint { vars: reduced, rhs }
+    }
+
+    /// Builds the Gauss-Jordan matrix from the native XORs for the current root assignment.
+    /// Must be called at decision level 0 after propagation;
+    /// sets `ok = false` if the XOR system is inconsistent.
+    pub(crate) fn attach_xors(&mut self) {
+        self.gauss = None;
+        if !self.ok { return; }
+
+        let mut simplified = Vec::with_capacity(self.xors.len() + self.derived_xors.len());
+        for xor in self.xors.iter().chain(&self.derived_xors) {
+            let xor = self.simplify_xor(xor);
+            if xor.vars.is_empty() {
+                if xor.rhs { self.ok = false; return; }
+                continue;
+            }
+            simplified.push(xor);
+        }
+        if simplified.is_empty() { return; }
+
+        let mut units = Vec::new();
+        let Some(matrix) = GaussMatrix::new(&simplified, self.num_vars, &mut units) else {
+            self.ok = false;
+            return;
+        };
+        if matrix.num_rows() > 0 { self.gauss = Some(matrix); }
+        for unit in units {
+            if !self.add_clause_internal(vec![unit], false) { return; }
+        }
+    }
+
+    /// Keeps XOR constraints found during preprocessing for propagation during search
+    /// (unless they are already known).
+    pub(crate) fn keep_xors(&mut self, xors: Vec<XorConstraint>) {
+        let mut known: HashSet<(Vec<usize>, bool)> = self.derived_xors.iter().map(|x| (x.vars.clone(), x.rhs)).collect();
+        for mut xor in xors {
+            xor.vars.sort_unstable();
+            if known.insert((xor.vars.clone(), xor.rhs)) { self.derived_xors.push(xor); }
+        }
+    }
+
+    /// Variables of the native XOR constraints of the formula (after substitution).
+    pub(crate) fn xor_variables(&self) -> Vec<bool> {
+        let mut frozen = vec![false; self.num_vars];
+        for xor in &self.xors {
+            for v in self.simplify_xor(xor).vars { frozen[v] = true; }
+        }
+        frozen
+    }
+}
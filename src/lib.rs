@@ -1,7 +1,48 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
 
 // Import the new module
+pub mod aig;
+pub mod approxmc;
+pub mod backbone;
+pub mod bignum;
+pub mod blocked;
+pub mod cardinality;
+pub mod counting;
+pub mod elimination;
+pub mod encodings;
+pub mod enumeration;
+pub mod equivalence;
+pub mod gates;
+pub mod gauss;
+pub mod marco;
+pub mod maxsat;
+pub mod mus;
+pub mod occurrences;
+pub mod pb;
+pub mod pipeline;
 pub mod preprocessing;
+pub mod probing;
+pub mod proof;
+pub mod reconstruction;
+pub mod sampling;
+pub mod sls;
+pub mod subsumption;
+pub mod xor_finder;
+
+pub use bignum::{BigUint, Decimal};
+use cardinality::CardinalityPropagator;
+pub use cardinality::CardinalityConstraint;
+use gauss::GaussMatrix;
+use pb::PbPropagator;
+pub use pb::PbConstraint;
+use pipeline::Pipeline;
+pub use pipeline::PipelineConfig;
+use proof::Proof;
+use preprocessing::apply_replacements;
+pub use preprocessing::XorConstraint;
+use reconstruction::ReconstructionStack;
 
 // =========================================================================
 // Core Types (Must be pub for benchmarking)
@@ -83,42 +124,71 @@ pub trait BranchingStrategy {
     fn on_unassign(&mut self, var: usize, old_value: bool);
 }
 
+/// Xorshift algorithm: fast, lightweight pseudo-random numbers, reproducible from the seed.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng { state: seed.max(1) } // The all-zero state is a fixed point
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// A random bit.
+    pub(crate) fn coin(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+}
+
 pub struct RandomStrategy {
-    rng_state: u64,
-    num_vars: usize,
+    rng: Rng,
 }
 
 impl RandomStrategy {
-    pub fn new(num_vars: usize) -> Self {
+    /// The number of variables is taken from the solver on every decision
+    /// (it can grow through auxiliary variables), the argument is kept for compatibility.
+    pub fn new(_num_vars: usize) -> Self {
         Self {
-            rng_state: 123456789, // Fixed seed for reproducibility
-            num_vars,
+            rng: Rng::new(123456789), // Fixed seed for reproducibility
         }
     }
 
-    /// Xorshift algorithm: Fast, lightweight pseudo-random numbers
+    /// A strategy whose decisions (order and polarity) follow the random numbers of 'seed'.
+    pub fn with_seed(seed: u64) -> Self {
+        Self { rng: Rng::new(seed) }
+    }
+
     fn next_rand(&mut self) -> usize {
-        let mut x = self.rng_state;
-        x ^= x << 13;
-        x ^= x >> 7;
-        x ^= x << 17;
-        self.rng_state = x;
-        x as usize
+        self.rng.next_u64() as usize
     }
 }
 
 impl BranchingStrategy for RandomStrategy {
     fn pick_branch(&mut self, solver: &Solver) -> Option<Lit> {
+        let num_vars = solver.assignments.len();
+        if num_vars == 0 { return None; }
+
         // 1. Pick a random start index
-        let start = self.next_rand() % self.num_vars;
+        let start = self.next_rand() % num_vars;
         
         // 2. Linear scan from start to find the first Unassigned variable.
         // This guarantees we always find a variable if one exists.
-        for i in 0..self.num_vars {
-            let idx = (start + i) % self.num_vars;
+        for i in 0..num_vars {
+            let idx = (start + i) % num_vars;
             if solver.assignments[idx] == VarValue::Unassigned {
-                // Pick random polarity (True/False)
-                let is_neg = (self.next_rand() % 2) == 0;
+                // Pick random polarity (True/False), unless local search suggests one
+                let random_neg = self.next_rand().is_multiple_of(2);
+                let is_neg = solver.target_phase(idx).map_or(random_neg, |value| !value);
                 return Some(Lit::new(idx, is_neg));
             }
         }
@@ -135,6 +205,90 @@ impl BranchingStrategy for RandomStrategy {
 // solver
 // =========================================================================
 
+/// Switches and limits for the optional simplification passes run by `solve`.
+#[derive(Debug, Clone)]
+pub struct SolverOptions {
+    /// Failed literal probing (with hyper-binary resolution) before search.
+    pub probing: bool,
+    /// Maximum number of literals propagated during one probing round.
+    pub probe_budget: usize,
+    /// Equivalent literal substitution based on SCCs of the binary implication graph.
+    pub equivalences: bool,
+    /// Blocked clause elimination (pass "bce") and blocked binary clause addition before
+    /// substitution (pass "bca"). Eliminated clauses come back when later clauses, constraints or
+    /// assumptions use their witness variables, but "bca" adds clauses the formula does not imply:
+    /// it only runs before the first `solve`, and nothing may be added or assumed after it.
+    pub blocked_clauses: bool,
+    /// Extend blocked clause elimination by covered literal addition.
+    pub covered_clauses: bool,
+    /// XOR extraction with Gaussian elimination (units, substitutions and reduced rows).
+    pub gauss: bool,
+    /// Keep XOR constraints (added with `add_xor` or found by Gaussian elimination) as native
+    /// constraints, propagated during search by an incrementally maintained Gauss-Jordan matrix.
+    /// Otherwise `add_xor` encodes them into clauses.
+    pub xor_propagation: bool,
+    /// Largest XORs (number of variables) the syntactic XOR finder looks for.
+    pub xor_max_size: usize,
+    /// Maximum number of variables per piece when an XOR is encoded into clauses.
+    /// Longer XORs are cut into a chain of pieces linked by auxiliary variables.
+    pub xor_cut_size: usize,
+    /// Bounded variable elimination (pass "bve"). The clauses of an eliminated variable come back
+    /// when later clauses, constraints or assumptions use it.
+    pub elimination: bool,
+    /// Cardinality constraint detection (pass "card"), replacing at-most-k encodings by native constraints.
+    pub cardinality: bool,
+    /// The simplification passes run by the first `solve` before search, as a `Pipeline`
+    /// configuration (e.g. `"[sub,bve]*3,probe,gauss".parse()?`). Passes disabled by their switch
+    /// above are skipped. Only the cheap substitution of equivalent literals by default: the other
+    /// passes pay off on structured instances but slow down easy ones.
+    pub preprocessing: PipelineConfig,
+    /// The passes run at restarts during search (inprocessing), also on the clauses learned so far.
+    /// Empty (the default) to switch inprocessing off.
+    pub inprocessing: PipelineConfig,
+    /// Conflicts before the first inprocessing, growing like `rephase_interval`.
+    pub inprocess_interval: u64,
+    /// Rephasing by local search: now and then, restart and run a bounded SLS walk from the saved
+    /// phases, whose best assignment becomes the target phases of the decisions (see `target_phase`).
+    pub rephasing: bool,
+    /// Conflicts before the first rephasing. Every later gap is longer by as many conflicts
+    /// (after 1000, 3000, 6000, ... conflicts by default), so local search gets rarer over time.
+    pub rephase_interval: u64,
+    /// Maximum number of flips of one local search walk.
+    pub rephase_flips: u64,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        SolverOptions {
+            probing: true,
+            probe_budget: 200_000,
+            equivalences: true,
+            blocked_clauses: true,
+            covered_clauses: true,
+            gauss: true,
+            xor_propagation: true,
+            xor_max_size: 8,
+            xor_cut_size: 4,
+            elimination: true,
+            cardinality: true,
+            preprocessing: "els".parse().expect("valid default pipeline"),
+            inprocessing: PipelineConfig::default(),
+            inprocess_interval: 2000,
+            rephasing: false,
+            rephase_interval: 1000,
+            rephase_flips: 100_000,
+        }
+    }
+}
+
+/// Why a literal was propagated, or which constraint is in conflict.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Reason {
+    Clause(usize),    // Index into 'clauses'
+    Explained(usize), // Index into 'explanations' (clauses derived from native constraints)
+    Cardinality(usize), // Index of a native cardinality constraint (clause generated on demand)
+}
+
 #[derive(Copy, Clone)]
 enum Action {
     Conflict(usize),      // A clause became empty -> Conflict
@@ -143,56 +297,163 @@ enum Action {
 
 pub struct Solver {
     pub clauses: Vec<Clause>,
+    pub options: SolverOptions,
     // Map: Literal -> List of Clauses watching this literal
     watches: Vec<Vec<Watcher>>, 
     
     pub assignments: Vec<VarValue>,
     level: Vec<usize>,          // Decision level of assignment
-    reason: Vec<Option<usize>>, // Clause that forced this assignment (None = Decision)
+    reason: Vec<Option<Reason>>, // Constraint that forced this assignment (None = Decision)
+    // Reason clauses of native constraints, tagged with the trail length at creation.
+    // Computed eagerly (the constraint may change later) and dropped on backtrack.
+    explanations: Vec<(usize, Vec<Lit>)>,
     
     trail: Vec<Lit>,       // Chronological stack of assignments
     trail_lim: Vec<usize>, // Indices in trail separating decision levels
     q_head: usize,         // Queue pointer for propagation
     num_vars: usize,       // Added num_vars to struct for easy access
+    ok: bool,              // False once the formula is known to be UNSAT at the root
+
+    // Simplifications that remove variables from the clause database
+    replacements: HashMap<usize, (usize, bool)>, // Substituted var -> (representative var, invert)
+    reconstruction: ReconstructionStack,
+    model: Vec<bool>,      // Satisfying assignment of the last successful 'solve'
+    validation_clauses: Vec<Vec<Lit>>, // Clauses replaced by derived XOR rows, checked in debug builds
+
+    // Native XOR constraints and the matrix propagating them (rebuilt for every 'solve').
+    // 'xors' are part of the formula, 'derived_xors' are implied by the clauses.
+    xors: Vec<XorConstraint>,
+    derived_xors: Vec<XorConstraint>,
+    gauss: Option<GaussMatrix>,
+
+    // Native cardinality constraints of the formula and their counters (rebuilt after substitutions)
+    cardinalities: Vec<CardinalityConstraint>,
+    cardinality: CardinalityPropagator,
+
+    // Native pseudo-Boolean constraints of the formula and their watches (rebuilt after substitutions)
+    pbs: Vec<PbConstraint>,
+    pb: PbPropagator,
+
+    // Assumptions of the running `solve_with_assumptions` (over representatives once search starts)
+    // and the failed assumptions of the last call
+    assumptions: Vec<Lit>,
+    core: Vec<Lit>,
+
+    // Last value of every variable (phase saving) and the best assignment of the last local
+    // search (empty before the first rephasing), plus the counters scheduling rephasing
+    // and inprocessing
+    saved_phases: Vec<bool>,
+    target_phases: Vec<bool>,
+    conflicts: u64,
+    rephases: u64,
+    inprocessings: u64,
+    preprocessed: bool, // The preprocessing pipeline ran (only the first `solve` runs it)
+
+    // DRAT proof of unsatisfiability, if requested, and the number of root trail literals logged as units
+    proof: Option<Proof>,
+    proof_units: usize,
     
     // We reuse these vectors during conflict analysis to avoid heap allocation overhead.
     analyze_seen: Vec<bool>,
     analyze_toclear: Vec<usize>, // Tracks which bits in 'seen' we set to true
     analyze_clause: Vec<Lit>,
+    analyze_reason: Vec<Lit>,    // Reason clauses generated on demand
 }
 
 impl Solver {
     pub fn new(num_vars: usize) -> Self {
         Solver {
             clauses: Vec::new(),
+            options: SolverOptions::default(),
             watches: vec![Vec::new(); num_vars * 2],
             assignments: vec![VarValue::Unassigned; num_vars],
             level: vec![0; num_vars],
             reason: vec![None; num_vars],
+            explanations: Vec::new(),
             trail: Vec::with_capacity(num_vars),
             trail_lim: Vec::new(),
             q_head: 0,
             num_vars,
+            ok: true,
+            replacements: HashMap::new(),
+            reconstruction: ReconstructionStack::new(),
+            model: Vec::new(),
+            validation_clauses: Vec::new(),
+            xors: Vec::new(),
+            derived_xors: Vec::new(),
+            gauss: None,
+            cardinalities: Vec::new(),
+            cardinality: CardinalityPropagator::default(),
+            pbs: Vec::new(),
+            pb: PbPropagator::default(),
+            assumptions: Vec::new(),
+            core: Vec::new(),
+            saved_phases: vec![false; num_vars],
+            target_phases: Vec::new(),
+            conflicts: 0,
+            rephases: 0,
+            inprocessings: 0,
+            preprocessed: false,
+            proof: None,
+            proof_units: 0,
             
             // Pre-allocate buffers
             analyze_seen: vec![false; num_vars],
             analyze_toclear: Vec::with_capacity(num_vars),
             analyze_clause: Vec::with_capacity(num_vars),
+            analyze_reason: Vec::new(),
         }
     }
 
+    /// Adds a fresh variable and returns its index.
+    pub fn new_var(&mut self) -> usize {
+        let var = self.num_vars;
+        self.num_vars += 1;
+        self.assignments.push(VarValue::Unassigned);
+        self.level.push(0);
+        self.reason.push(None);
+        self.watches.push(Vec::new());
+        self.watches.push(Vec::new());
+        self.analyze_seen.push(false);
+        self.saved_phases.push(false);
+        var
+    }
+
     /// Adds a clause to the formula and sets up watchers.
-    pub fn add_clause(&mut self, mut lits: Vec<Lit>) -> bool {
-        if lits.is_empty() { return false; } // Empty clause = UNSAT
-        
+    /// Must be called at decision level 0. Returns false if the formula became UNSAT.
+    pub fn add_clause(&mut self, lits: Vec<Lit>) -> bool {
+        self.log_input(&lits);
+        // Variables substituted by earlier simplifications must not come back
+        let lits = if self.replacements.is_empty() { lits } else { apply_replacements(&lits, &self.replacements) };
+        // So do clauses removed by simplifications that this one could conflict with
+        if !self.restore_clauses(&lits) { return false; }
+        self.add_clause_internal(lits, false)
+    }
+
+    fn add_clause_internal(&mut self, mut lits: Vec<Lit>, learned: bool) -> bool {
+        if !self.ok { return false; }
+
         // Normalize clause
         lits.sort_by_key(|l| l.to_usize());
         lits.dedup();
 
+        // Simplify against the root assignment: drop tautologies and satisfied clauses,
+        // remove falsified literals so that we never watch a literal that is already False.
+        if lits.windows(2).any(|w| w[0].var() == w[1].var()) { return true; }
+        if lits.iter().any(|&l| Self::value_lit(&self.assignments, l) == VarValue::True) { return true; }
+        lits.retain(|&l| Self::value_lit(&self.assignments, l) == VarValue::Unassigned);
+        if self.proof.is_some() { self.log_stored(&lits); }
+
+        if lits.is_empty() { // Empty clause = UNSAT
+            self.ok = false;
+            return false;
+        }
+
         // Handle Unit Clause (size 1) immediately
         if lits.len() == 1 {
             self.unchecked_enqueue(lits[0], None);
-            return self.propagate().is_none(); 
+            self.ok = self.propagate().is_none();
+            return self.ok;
         }
 
         let clause_idx = self.clauses.len() as u32;
@@ -201,31 +462,82 @@ impl Solver {
         self.watches[lits[0].not().to_usize()].push(Watcher { clause_idx, blocker: lits[1] });
         self.watches[lits[1].not().to_usize()].push(Watcher { clause_idx, blocker: lits[0] });
         
-        self.clauses.push(Clause { lits, learned: false });
+        self.clauses.push(Clause { lits, learned });
         true
     }
 
-    /// Helper to clear and rebuild watches.
-    /// This is needed after preprocessing modifies the clause database.
-    fn rebuild_watches(&mut self) {
-        // Clear all watch lists
-        for w in &mut self.watches {
-            w.clear();
+    /// Clauses that are binary under the root assignment: not satisfied, exactly two unassigned literals.
+    fn root_binary_clauses(&self) -> Vec<[Lit; 2]> {
+        let mut binaries = Vec::new();
+        for clause in &self.clauses {
+            let mut open = [clause.lits[0]; 2];
+            let mut count = 0;
+            for &l in &clause.lits {
+                match Self::value_lit(&self.assignments, l) {
+                    VarValue::True => { count = 3; break; }
+                    VarValue::Unassigned => {
+                        if count < 2 { open[count] = l; }
+                        count += 1;
+                    }
+                    VarValue::False => {}
+                }
+                if count > 2 { break; }
+            }
+            if count == 2 { binaries.push(open); }
         }
+        binaries
+    }
 
-        // Re-add watchers for all clauses
-        for (i, clause) in self.clauses.iter().enumerate() {
-            if clause.lits.len() > 1 {
-                let c_idx = i as u32;
-                self.watches[clause.lits[0].not().to_usize()].push(Watcher { clause_idx: c_idx, blocker: clause.lits[1] });
-                self.watches[clause.lits[1].not().to_usize()].push(Watcher { clause_idx: c_idx, blocker: clause.lits[0] });
-            }
+    /// Replaces the whole clause database (at decision level 0) and re-attaches all watches.
+    /// Reasons of root assignments point into the old database, but they are never looked at
+    /// during analysis, so we simply clear them.
+    fn replace_clause_database(&mut self, clauses: Vec<Clause>) {
+        self.clauses.clear();
+        for w in &mut self.watches { w.clear(); }
+        for &lit in &self.trail { self.reason[lit.var()] = None; }
+
+        for clause in clauses {
+            if !self.add_clause_internal(clause.lits, clause.learned) { return; }
         }
+        if self.propagate().is_some() { self.ok = false; }
+        self.log_sync();
+    }
+
+    /// The satisfying assignment (indexed by variable) found by the last call to `solve`
+    /// that returned true, including variables removed by preprocessing.
+    pub fn model(&self) -> &[bool] {
+        &self.model
+    }
+
+    /// The value local search suggests for 'var' (see `SolverOptions::rephasing`), if any.
+    /// Branching strategies may follow it when they pick the polarity of a decision.
+    pub fn target_phase(&self, var: usize) -> Option<bool> {
+        self.target_phases.get(var).copied()
+    }
+
+    /// The clauses not satisfied by the root assignment, without their false literals.
+    pub(crate) fn root_clauses(&self) -> Vec<Clause> {
+        self.clauses.iter()
+            .filter(|c| !c.lits.iter().any(|&l| Self::value_lit(&self.assignments, l) == VarValue::True))
+            .map(|c| Clause {
+                lits: c.lits.iter().copied().filter(|&l| Self::value_lit(&self.assignments, l) == VarValue::Unassigned).collect(),
+                learned: c.learned,
+            })
+            .collect()
+    }
+
+    /// Variables of native constraints (XORs, cardinality and PB constraints) and assumptions.
+    /// Simplifications that flip variables during model reconstruction must leave them alone.
+    pub(crate) fn frozen_variables(&self) -> Vec<bool> {
+        let mut frozen = self.xor_variables();
+        for var in self.cardinality_variables().chain(self.pb_variables()) { frozen[var] = true; }
+        for lit in apply_replacements(&self.assumptions, &self.replacements) { frozen[lit.var()] = true; }
+        frozen
     }
 
     /// Assigns a value to a literal and adds it to the propagation trail.
     #[inline(always)]
-    fn unchecked_enqueue(&mut self, lit: Lit, reason: Option<usize>) {
+    fn unchecked_enqueue(&mut self, lit: Lit, reason: Option<Reason>) {
         let var = lit.var();
         if self.assignments[var] != VarValue::Unassigned { return; }
         
@@ -252,13 +564,18 @@ impl Solver {
 
     /// The heart of the solver: Boolean Constraint Propagation (BCP).
     /// Uses the Two-Watched Literals scheme with Blocking Literals.
-    fn propagate(&mut self) -> Option<usize> {
+    fn propagate(&mut self) -> Option<Reason> {
         let mut conflict = None;
 
         while self.q_head < self.trail.len() {
             let p = self.trail[self.q_head];
             self.q_head += 1;
 
+            // Native cardinality constraints count every processed literal (see `backtrack`)
+            if !self.cardinality.is_empty() && let Some(conflict) = self.propagate_cardinalities(p) {
+                return Some(conflict);
+            }
+
             // 'p' is True. We process clauses watching '!p' (because '!p' became False).
             let falsified_lit_idx = p.to_usize();
             
@@ -336,8 +653,8 @@ impl Solver {
 
                 if let Some(act) = action {
                     match act {
-                        Action::Conflict(idx) => conflict = Some(idx),
-                        Action::Enqueue(lit, reason) => self.unchecked_enqueue(lit, Some(reason)),
+                        Action::Conflict(idx) => conflict = Some(Reason::Clause(idx)),
+                        Action::Enqueue(lit, reason) => self.unchecked_enqueue(lit, Some(Reason::Clause(reason))),
                     }
                 }
                 i += 1;
@@ -348,27 +665,76 @@ impl Solver {
             self.watches[falsified_lit_idx] = watchers;
 
             if let Some(c) = conflict { return Some(c); }
+
+            // Native PB constraints watching '!p'
+            if !self.pb.is_empty() && let Some(conflict) = self.propagate_pbs(p) {
+                return Some(conflict);
+            }
+
+            // Native XOR constraints
+            if let Some(matrix) = &mut self.gauss {
+                let mut implied = Vec::new();
+                let xor_conflict = matrix.assigned(p.var(), &self.assignments, &self.level, &mut implied);
+                for (lit, explanation) in implied {
+                    match Self::value_lit(&self.assignments, lit) {
+                        VarValue::True => {}
+                        VarValue::Unassigned => {
+                            let reason = self.explain(explanation);
+                            self.unchecked_enqueue(lit, Some(reason));
+                        }
+                        VarValue::False => return Some(self.explain(explanation)),
+                    }
+                }
+                if let Some(explanation) = xor_conflict { return Some(self.explain(explanation)); }
+            }
         }
         None
     }
 
+    /// Stores the reason clause of a native constraint until we backtrack past the current trail.
+    fn explain(&mut self, lits: Vec<Lit>) -> Reason {
+        self.explanations.push((self.trail.len(), lits));
+        Reason::Explained(self.explanations.len() - 1)
+    }
+
+    /// The clause behind the reason for 'implied' (first), or behind a conflict (None).
+    fn reason_lits(&self, reason: Reason, implied: Option<Lit>) -> Cow<'_, [Lit]> {
+        match reason {
+            Reason::Clause(idx) => Cow::Borrowed(&self.clauses[idx].lits),
+            Reason::Explained(idx) => Cow::Borrowed(&self.explanations[idx].1),
+            Reason::Cardinality(c) => {
+                let mut lits = Vec::new();
+                self.cardinality_reason(c, implied, &mut lits);
+                Cow::Owned(lits)
+            }
+        }
+    }
+
     /// 1-UIP Conflict Analysis.
     /// Returns the learned clause and the backtracking level.
-    fn analyze(&mut self, conflict_idx: usize) -> (Vec<Lit>, usize) {
+    fn analyze(&mut self, conflict: Reason) -> (Vec<Lit>, usize) {
         // Fast reset of analysis buffers (O(k) where k is variables involved)
         for &var in &self.analyze_toclear { self.analyze_seen[var] = false; }
         self.analyze_toclear.clear();
         self.analyze_clause.clear();
 
         let mut counter = 0; // Number of literals from current decision level
-        let mut current_clause_idx = Some(conflict_idx);
+        let mut current_reason = Some(conflict);
         let mut p: Option<Lit> = None;
         let mut index = self.trail.len();
+        let mut generated = std::mem::take(&mut self.analyze_reason);
 
         loop {
-            if let Some(c_idx) = current_clause_idx {
-                let clause = &self.clauses[c_idx];
-                for (i, &lit) in clause.lits.iter().enumerate() {
+            if let Some(reason) = current_reason {
+                let lits = match reason {
+                    Reason::Clause(idx) => &self.clauses[idx].lits,
+                    Reason::Explained(idx) => &self.explanations[idx].1,
+                    Reason::Cardinality(c) => {
+                        self.cardinality_reason(c, p, &mut generated);
+                        &generated
+                    }
+                };
+                for (i, &lit) in lits.iter().enumerate() {
                     // Skip the literal we are resolving on (unless it's the very first iteration)
                     if i == 0 && p.is_some() && lit == p.unwrap() { continue; }
                     
@@ -392,7 +758,7 @@ impl Solver {
             let current_lit = self.trail[index];
             
             p = Some(current_lit);
-            current_clause_idx = self.reason[current_lit.var()];
+            current_reason = self.reason[current_lit.var()];
             
             self.analyze_seen[current_lit.var()] = false; 
             counter -= 1;
@@ -401,22 +767,55 @@ impl Solver {
             if counter == 0 { break; }
         }
 
+        self.analyze_reason = generated;
         if let Some(lit) = p { self.analyze_clause.insert(0, lit.not()); }
 
+        // The literal with the highest level goes to index 1, so it becomes the second watch.
         let backtrack_level = if self.analyze_clause.len() > 1 {
-            self.analyze_clause.iter().skip(1).map(|l| self.level[l.var()]).max().unwrap_or(0)
+            let mut max_i = 1;
+            for i in 2..self.analyze_clause.len() {
+                if self.level[self.analyze_clause[i].var()] > self.level[self.analyze_clause[max_i].var()] { max_i = i; }
+            }
+            self.analyze_clause.swap(1, max_i);
+            self.level[self.analyze_clause[1].var()]
         } else { 0 };
 
         (self.analyze_clause.clone(), backtrack_level)
     }
 
+    /// Collects the failed assumptions in 'core': the negation of the true literal 'p' is an
+    /// assumption, and so are the decisions its implication depends on (below the free decisions).
+    fn analyze_final(&mut self, p: Lit) {
+        self.core.clear();
+        self.core.push(p.not());
+        if self.level[p.var()] == 0 { return; }
+
+        self.analyze_seen[p.var()] = true;
+        for i in (self.trail_lim[0]..self.trail.len()).rev() {
+            let lit = self.trail[i];
+            let var = lit.var();
+            if !self.analyze_seen[var] { continue; }
+            self.analyze_seen[var] = false;
+            match self.reason[var] {
+                None => self.core.push(lit),
+                Some(reason) => {
+                    for l in self.reason_lits(reason, Some(lit)).into_owned() {
+                        if l.var() != var && self.level[l.var()] > 0 { self.analyze_seen[l.var()] = true; }
+                    }
+                }
+            }
+        }
+    }
+
     fn backtrack(&mut self, level: usize, strategy: &mut dyn BranchingStrategy) {
         while self.decision_level() > level {
             let limit = *self.trail_lim.last().unwrap();
             while self.trail.len() > limit {
                 let lit = self.trail.pop().unwrap();
                 let var = lit.var();
+                if self.trail.len() < self.q_head { self.cardinality.unassigned(lit); }
                 let old_val = self.assignments[var] == VarValue::True;
+                self.saved_phases[var] = old_val;
                 
                 self.assignments[var] = VarValue::Unassigned;
                 self.reason[var] = None;
@@ -426,44 +825,85 @@ impl Solver {
             }
             self.trail_lim.pop();
         }
+        while self.explanations.last().is_some_and(|&(created, _)| created >= self.trail.len()) {
+            self.explanations.pop();
+        }
         self.q_head = self.trail.len();
     }
 
-    /// Main CDCL Loop
+    /// Searches for a model, after simplifying the formula if this is the first call.
+    /// Returns true if one was found (see `model`).
+    /// If the formula is unsatisfiable, a proof (see `set_proof`) is closed with the empty clause.
     pub fn solve(&mut self, strategy: &mut dyn BranchingStrategy, verbose: bool) -> bool {
-        // == PREPROCESSING STEP ==
-        if verbose { println!("Running Preprocessing: Gaussian Elimination + Substitution..."); }
-        
-        // Pass reference. Only replace if result is Some(...) indicating actual simplification.
-        //if let Some(result) = preprocessing::preprocess(&self.clauses, self.num_vars) {
-        if false { // Disable preprocessing for now
-            // if verbose {
-            //     println!("Preprocessing successful.");
-            //     println!("  - Original clauses: {}", self.clauses.len());
-            //     println!("  - New clauses: {}", result.clauses.len());
-            //     println!("  - Found Units: {}", result.units.len());
-            // }
-
-            // self.clauses = result.clauses;
-            // self.rebuild_watches(); // Rebuild watches for the new clause set
-            
-            // // Apply found unit literals
-            // for lit in result.units {
-            //     self.unchecked_enqueue(lit, None);
-            // }
-        } else {
-            if verbose { println!("Preprocessing found no simplifications. Keeping original clauses."); }
-            // Do NOT rebuild watches, do NOT touch self.clauses. Fast path.
+        self.solve_with_assumptions(&[], strategy, verbose)
+    }
+
+    /// Like `solve`, but only looks for models in which all 'assumptions' are true. They are not
+    /// added to the formula, so learned clauses stay valid for later calls. If there is no such
+    /// model, `core` tells which assumptions are to blame.
+    /// Clauses that simplifications removed on the assumed variables are restored first, and the
+    /// variables are frozen against preprocessing for this call.
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Lit], strategy: &mut dyn BranchingStrategy, verbose: bool) -> bool {
+        self.assumptions = assumptions.to_vec();
+        self.core.clear();
+        let sat = self.restore_clauses(assumptions) && self.search(strategy, verbose);
+        if !self.core.is_empty() {
+            let failed = std::mem::take(&mut self.core);
+            self.core = assumptions.iter().copied()
+                .filter(|&l| failed.contains(&apply_replacements(&[l], &self.replacements)[0]))
+                .collect();
+        }
+        self.assumptions.clear();
+
+        if !sat && self.core.is_empty() { self.log_add(&[]); }
+        if let Some(proof) = &mut self.proof { proof.flush_output(); }
+        sat
+    }
+
+    /// The assumptions of the last `solve_with_assumptions` that failed which cannot all be true
+    /// (empty if the formula itself is unsatisfiable).
+    pub fn core(&self) -> &[Lit] {
+        &self.core
+    }
+
+    /// Main CDCL Loop
+    fn search(&mut self, strategy: &mut dyn BranchingStrategy, verbose: bool) -> bool {
+        if !self.ok { return false; }
+
+        // == PREPROCESSING ==
+        if !self.preprocessed {
+            let mut pipeline = Pipeline::from_config(&self.options.preprocessing);
+            let ok = pipeline.run(self, strategy, verbose);
+            self.preprocessed = true;
+            if !ok { return false; }
+        }
+
+        // == XOR PROPAGATION ==
+        // Native XORs of the formula are always attached, derived ones only exist if enabled
+        self.attach_xors();
+        if verbose && let Some(matrix) = &self.gauss {
+            println!("XOR propagation: {} rows over {} variables in {} blocks", matrix.num_rows(), matrix.num_columns(), matrix.num_blocks());
+        }
+        if verbose && !self.cardinality.is_empty() {
+            println!("Cardinality propagation: {} constraints", self.cardinality.len());
+        }
+        if verbose && !self.pb.is_empty() {
+            println!("PB propagation: {} constraints", self.pb.len());
         }
+        if !self.ok { return false; }
+        self.assumptions = apply_replacements(&self.assumptions, &self.replacements);
 
         loop {
             // 1. Propagate assignments
-            if let Some(conflict_idx) = self.propagate() {
+            if let Some(conflict) = self.propagate() {
                 // Conflict found!
-                if self.decision_level() == 0 { return false; } // Conflict at root = UNSAT
+                if self.decision_level() == 0 { // Conflict at root = UNSAT
+                    self.ok = false;
+                    return false;
+                }
 
                 // 2. Analyze conflict
-                let (learned_clause, backtrack_level) = self.analyze(conflict_idx);
+                let (learned_clause, backtrack_level) = self.analyze(conflict);
                 let involved_vars: Vec<usize> = learned_clause.iter().map(|l| l.var()).collect();
                 strategy.on_conflict(&involved_vars);
 
@@ -485,18 +925,49 @@ impl Solver {
                          self.watches[c0.not().to_usize()].push(Watcher { clause_idx: lidx, blocker: c0 });
                     }
                     
+                    self.log_add(&learned_clause);
                     self.clauses.push(Clause { lits: learned_clause, learned: true });
-                    self.unchecked_enqueue(c0, Some(lidx as usize));
+                    self.unchecked_enqueue(c0, Some(Reason::Clause(lidx as usize)));
                 }
+
+                self.conflicts += 1;
+            } else if self.options.rephasing && self.rephase_due() {
+                // Propagation is complete, so restarting from level 0 loses no implications
+                self.rephase(strategy, verbose);
+            } else if self.inprocessing_due() {
+                if !self.inprocess(strategy, verbose) { return false; }
             } else {
-                // No conflict. Pick next decision.
-                match strategy.pick_branch(self) {
+                // No conflict. The assumptions come first, one per decision level
+                // (an empty level if one already holds), then we pick the next decision.
+                let mut decision = None;
+                while decision.is_none() && self.decision_level() < self.assumptions.len() {
+                    let lit = self.assumptions[self.decision_level()];
+                    match Self::value_lit(&self.assignments, lit) {
+                        VarValue::True => self.trail_lim.push(self.trail.len()),
+                        VarValue::Unassigned => decision = Some(lit),
+                        VarValue::False => {
+                            self.analyze_final(lit.not());
+                            self.backtrack(0, strategy);
+                            return false;
+                        }
+                    }
+                }
+                match decision.or_else(|| strategy.pick_branch(self)) {
                     Some(lit) => {
                         self.trail_lim.push(self.trail.len());
                         self.unchecked_enqueue(lit, None);
                         strategy.on_assign(lit.var());
                     }
-                    None => return true, // All assigned -> SAT
+                    None => { // All assigned -> SAT
+                        self.model = self.assignments.iter().map(|&v| v == VarValue::True).collect();
+                        self.reconstruction.extend_model(&mut self.model);
+                        debug_assert!(
+                            self.validation_clauses.iter().all(|c| c.iter().any(|l| self.model[l.var()] != l.is_neg())),
+                            "model violates a clause replaced by Gaussian elimination"
+                        );
+                        self.backtrack(0, strategy);
+                        return true;
+                    }
                 }
             }
         }
@@ -507,27 +978,86 @@ impl Solver {
 // Helpers for Parsing and Running
 // =========================================================================
 
-/// Parses simple string format "[1, -2, 3]" or "1 -2 3"
+/// A parsed input: clauses plus native XOR and PB constraints over 'num_vars' variables,
+/// the objective to minimize (OPB), the weighted soft clauses (WCNF, 'clauses' are hard) or the
+/// clauses of numbered groups (GCNF, 'clauses' are group 0), and the variables models are
+/// projected onto and literal weights for model counting.
+#[derive(Debug, Clone, Default)]
+pub struct Formula {
+    pub clauses: Vec<Vec<Lit>>,
+    pub xors: Vec<XorConstraint>,
+    pub pbs: Vec<PbConstraint>,
+    pub objective: Option<Vec<(i64, Lit)>>,
+    pub soft: Vec<(u64, Vec<Lit>)>,
+    pub groups: Vec<(usize, Vec<Lit>)>,
+    pub projection: Option<Vec<usize>>,
+    pub weights: Vec<(Lit, Decimal)>,
+    pub num_vars: usize,
+}
+
+/// Parses simple string format "[1, -2, 3]" or "1 -2 3".
+/// XOR lines (see `parse_formula`) are encoded into clauses, cut into pieces of at most
+/// `SolverOptions::xor_cut_size` variables, whose linking variables come after those of the input.
 pub fn parse_custom_format(content: &str) -> (Vec<Vec<Lit>>, usize) {
+    let formula = parse_formula(content);
+    let (mut clauses, mut num_vars) = (formula.clauses, formula.num_vars);
+    let cut_size = SolverOptions::default().xor_cut_size;
+    for xor in &formula.xors {
+        let pieces = preprocessing::cut_xor(&xor.vars, xor.rhs, cut_size, &mut || { num_vars += 1; num_vars - 1 });
+        for piece in pieces {
+            clauses.extend(preprocessing::xor_to_cnf(&piece.vars, piece.rhs).into_iter().map(|c| c.lits));
+        }
+    }
+    (clauses, num_vars)
+}
+
+/// Like `parse_custom_format`, but keeps the XOR constraints and the annotations for model counting.
+/// Lines like "x1 -2 3 0" are XOR constraints in the CryptoMiniSat convention:
+/// the XOR of the literals is True, so a negated literal flips the parity.
+/// Comment lines "c ind 1 5 7 0" list the projection variables (the independent support),
+/// "c p weight -3 0.25 0" gives a literal weight; the other literal of the variable weighs
+/// 1 - 0.25 unless it has a weight line too.
+pub fn parse_formula(content: &str) -> Formula {
     let mut clauses = Vec::new();
+    let mut xors = Vec::new();
     let mut max_var_idx = 0;
+
+    let mut projection: Option<Vec<usize>> = None;
+    for rest in content.lines().filter_map(|l| l.trim().strip_prefix("c ind ")) {
+        let vars = projection.get_or_insert_with(Vec::new);
+        for val in rest.split_whitespace().filter_map(|t| t.parse::<i32>().ok()).filter(|&v| v != 0) {
+            let (_, var_idx) = parse_lit(val);
+            vars.push(var_idx);
+            if var_idx > max_var_idx { max_var_idx = var_idx; }
+        }
+    }
     
     // Check if "p cnf" exists. If so, we should skip everything before it.
     let start_parsing = content.lines().position(|l| l.starts_with("p cnf"));
     
     // Create an iterator that either starts from "p cnf" + 1 or from the beginning
-    let lines_iter = match start_parsing {
-        Some(idx) => content.lines().skip(idx + 1),
-        None => content.lines().skip(0),
-    };
+    let lines_iter = content.lines().skip(start_parsing.map_or(0, |idx| idx + 1));
 
     for line in lines_iter {
         let line = line.trim();
         // Skip comments and empty lines
         if line.is_empty() || line.starts_with('c') || line.starts_with('%') || line.starts_with('0') { continue; }
         
+        // XOR constraint
+        if let Some(rest) = line.strip_prefix('x') {
+            let mut xor = XorConstraint { vars: Vec::new(), rhs: true };
+            for val in rest.split_whitespace().filter_map(|t| t.parse::<i32>().ok()).filter(|&v| v != 0) {
+                let (lit, var_idx) = parse_lit(val);
+                xor.vars.push(var_idx);
+                xor.rhs ^= lit.is_neg();
+                if var_idx > max_var_idx { max_var_idx = var_idx; }
+            }
+            xors.push(xor);
+            continue;
+        }
+
         // Sanitize input
-        let cleaned = line.replace('[', " ").replace(']', " ").replace(',', " ");
+        let cleaned = line.replace(['[', ']', ','], " ");
         let mut current_clause = Vec::new();
         
         for token in cleaned.split_whitespace() {
@@ -540,22 +1070,161 @@ pub fn parse_custom_format(content: &str) -> (Vec<Vec<Lit>>, usize) {
         }
         if !current_clause.is_empty() { clauses.push(current_clause); }
     }
-    (clauses, max_var_idx + 1)
+    if let Some(vars) = &mut projection {
+        vars.sort_unstable();
+        vars.dedup();
+    }
+
+    let mut weights: HashMap<Lit, Decimal> = HashMap::new();
+    for rest in content.lines().filter_map(|l| l.trim().strip_prefix("c p weight ")) {
+        let tokens: Vec<&str> = rest.split_whitespace().collect();
+        if let [lit, weight, ..] = tokens[..] && let (Ok(val), Ok(weight)) = (lit.parse::<i32>(), weight.parse::<Decimal>()) && val != 0 {
+            let (lit, var_idx) = parse_lit(val);
+            weights.insert(lit, weight);
+            if var_idx > max_var_idx { max_var_idx = var_idx; }
+        }
+    }
+    let complements: Vec<(Lit, Decimal)> = weights.iter()
+        .filter(|(lit, _)| !weights.contains_key(&lit.not()))
+        .filter_map(|(lit, w)| Some((lit.not(), Decimal::one().checked_sub(w)?)))
+        .collect();
+    weights.extend(complements);
+    let mut weights: Vec<(Lit, Decimal)> = weights.into_iter().collect();
+    weights.sort_by_key(|(lit, _)| lit.to_usize());
+
+    Formula { clauses, xors, projection, weights, num_vars: max_var_idx + 1, ..Formula::default() }
+}
+
+/// Parses the OPB format of the pseudo-Boolean competitions: "*" comment lines, an optional
+/// objective "min: +2 x1 -3 ~x2 ;" and constraints like "+3 x1 +2 x2 -1 x3 >= 2 ;" (or "=", "<=").
+/// Statements end with ";", "~x1" is the negation of "x1". Products of literals are rejected.
+pub fn parse_opb(content: &str) -> Result<Formula, String> {
+    let mut formula = Formula::default();
+    let text: Vec<&str> = content.lines().filter(|l| !l.trim_start().starts_with('*')).collect();
+    for statement in text.join("\n").split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        if let Some(rest) = statement.strip_prefix("min:") {
+            formula.objective = Some(parse_opb_terms(rest, &mut formula.num_vars)?);
+            continue;
+        }
+        let Some((lhs, relation, rhs)) = [">=", "<=", "="].iter()
+            .find_map(|&r| statement.split_once(r).map(|(lhs, rhs)| (lhs, r, rhs)))
+        else {
+            return Err(format!("missing relation in '{}'", statement));
+        };
+        let terms = parse_opb_terms(lhs, &mut formula.num_vars)?;
+        let bound: i64 = rhs.trim().parse().map_err(|_| format!("invalid right-hand side in '{}'", statement))?;
+        let negated = || PbConstraint { terms: terms.iter().map(|&(a, l)| (-a, l)).collect(), bound: -bound };
+        match relation {
+            ">=" => formula.pbs.push(PbConstraint { terms, bound }),
+            "<=" => formula.pbs.push(negated()),
+            _ => {
+                formula.pbs.push(negated());
+                formula.pbs.push(PbConstraint { terms, bound });
+            }
+        }
+    }
+    Ok(formula)
+}
+
+/// Parses weighted partial MaxSAT instances in the WCNF format, both the 2022 one
+/// ("h 1 -2 0" hard, "5 1 -2 0" soft with weight 5) and the old one with a "p wcnf" header,
+/// where weights of at least its optional 'top' value mark hard clauses.
+pub fn parse_wcnf(content: &str) -> Result<Formula, String> {
+    let mut formula = Formula::default();
+    let mut top = None;
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('c') { continue; }
+        let mut tokens = line.split_whitespace();
+        let first = tokens.next().unwrap();
+        if first == "p" {
+            if tokens.next() != Some("wcnf") { return Err(format!("invalid header '{}'", line)); }
+            top = tokens.nth(2).map(|t| t.parse::<u64>().map_err(|_| format!("invalid top weight in '{}'", line))).transpose()?;
+            continue;
+        }
+        let weight = match first {
+            "h" => None,
+            _ => Some(first.parse::<u64>().map_err(|_| format!("invalid weight in '{}'", line))?),
+        };
+        let mut clause = Vec::new();
+        for token in tokens {
+            let val: i32 = token.parse().map_err(|_| format!("invalid literal '{}' in '{}'", token, line))?;
+            if val == 0 { break; }
+            let (lit, var_idx) = parse_lit(val);
+            clause.push(lit);
+            formula.num_vars = formula.num_vars.max(var_idx + 1);
+        }
+        match weight {
+            Some(w) if top.is_none_or(|top| w < top) => formula.soft.push((w, clause)),
+            _ => formula.clauses.push(clause),
+        }
+    }
+    Ok(formula)
+}
+
+/// Parses group-oriented CNF (GCNF) as in the MUS competitions: a "p gcnf" header, then clauses
+/// prefixed by their group "{3} 1 -2 0". Group 0 holds the hard clauses, which go to 'clauses'.
+pub fn parse_gcnf(content: &str) -> Result<Formula, String> {
+    let mut formula = Formula::default();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('c') { continue; }
+        if line.starts_with('p') {
+            if line.split_whitespace().nth(1) != Some("gcnf") { return Err(format!("invalid header '{}'", line)); }
+            continue;
+        }
+        let (group, rest) = line.strip_prefix('{').and_then(|l| l.split_once('}'))
+            .ok_or_else(|| format!("missing group in '{}'", line))?;
+        let group: usize = group.trim().parse().map_err(|_| format!("invalid group in '{}'", line))?;
+        let mut clause = Vec::new();
+        for token in rest.split_whitespace() {
+            let val: i32 = token.parse().map_err(|_| format!("invalid literal '{}' in '{}'", token, line))?;
+            if val == 0 { break; }
+            let (lit, var_idx) = parse_lit(val);
+            clause.push(lit);
+            formula.num_vars = formula.num_vars.max(var_idx + 1);
+        }
+        match group {
+            0 => formula.clauses.push(clause),
+            _ => formula.groups.push((group, clause)),
+        }
+    }
+    Ok(formula)
+}
+
+/// Parses "coefficient literal" pairs, raising 'num_vars' to cover their variables.
+fn parse_opb_terms(text: &str, num_vars: &mut usize) -> Result<Vec<(i64, Lit)>, String> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let mut terms = Vec::with_capacity(tokens.len() / 2);
+    for pair in tokens.chunks(2) {
+        let coefficient: i64 = pair[0].parse().map_err(|_| format!("invalid coefficient '{}' in '{}'", pair[0], text.trim()))?;
+        let Some(&token) = pair.get(1) else { return Err(format!("missing literal in '{}'", text.trim())) };
+        let (is_neg, name) = match token.strip_prefix('~') {
+            Some(name) => (true, name),
+            None => (false, token),
+        };
+        let var: usize = name.strip_prefix('x').and_then(|v| v.parse().ok()).filter(|&v| v > 0)
+            .ok_or_else(|| format!("invalid literal '{}' in '{}'", token, text.trim()))?;
+        *num_vars = (*num_vars).max(var);
+        terms.push((coefficient, Lit::new(var - 1, is_neg)));
+    }
+    Ok(terms)
 }
 
 pub fn parse_lit(val: i32) -> (Lit, usize) {
-    let var_idx = (val.abs() as usize) - 1; 
+    let var_idx = (val.unsigned_abs() as usize) - 1;
     let is_neg = val < 0;
     (Lit::new(var_idx, is_neg), var_idx)
 }
 
 /// Convenience function to parse and solve a string content
 pub fn run_solver_on_content(content: &str, verbose: bool) -> bool {
-    let (clauses, num_vars) = parse_custom_format(content);
-    let mut solver = Solver::new(num_vars);
-    for clause_lits in clauses {
+    let formula = parse_formula(content);
+    let mut solver = Solver::new(formula.num_vars);
+    for clause_lits in formula.clauses {
         solver.add_clause(clause_lits);
     }
-    let mut strategy = RandomStrategy::new(num_vars);
+    for xor in formula.xors {
+        solver.add_xor(xor);
+    }
+    let mut strategy = RandomStrategy::new(formula.num_vars);
     solver.solve(&mut strategy, verbose)
 }
\ No newline at end of file
@@ -1,15 +1,39 @@
 use std::env;
 use std::fs;
 use satgpt::*;
+use satgpt::backbone::{self, BackboneAlgorithm};
+use satgpt::gates::Circuit;
+use satgpt::maxsat::{self, MaxSatAlgorithm};
+use satgpt::marco::{self, Subset};
+use satgpt::mus;
+use satgpt::sls::{self, SlsAlgorithm, SlsOptions};
+use satgpt::sampling::{self, SamplingMode};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     
-    // Simple argument parser: find the first argument that doesn't start with "--"
-    let path = args.iter().skip(1).find(|&a| !a.starts_with("--"));
+    // Simple argument parser: an optional subcommand, then the first argument that doesn't start
+    // with "--" (and is not the value following "--proof", "--limit", "--samples", "--seed",
+    // "--time-limit" or "--chunked")
+    let counting = args.get(1).is_some_and(|a| a == "count");
+    let sampling = args.get(1).is_some_and(|a| a == "sample");
+    let mus = args.get(1).is_some_and(|a| a == "mus");
+    let diagnosis = args.get(1).is_some_and(|a| a == "marco");
+    let backbone = args.get(1).is_some_and(|a| a == "backbone");
+    let value_of = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1));
+    let proof_path = value_of("--proof");
+    let limit = value_of("--limit");
+    let values = [proof_path, limit, value_of("--samples"), value_of("--seed"), value_of("--time-limit"), value_of("--chunked")];
+    let path = args.iter().skip(1 + (counting || sampling || mus || diagnosis || backbone) as usize).find(|&a| !a.starts_with("--") && !values.contains(&Some(a)));
 
     if path.is_none() {
-        eprintln!("Usage: {} <path_to_formula>", args[0]);
+        eprintln!("Usage: {} [--circuit] [--proof <drat_file>] [--linear] [--enumerate [--limit <n>] [--decision-blocking]] <path_to_formula>", args[0]);
+        eprintln!("       {} --sls [--walksat] [--time-limit <seconds>] [--seed <s>] <path_to_formula>", args[0]);
+        eprintln!("       {} count [--approx] <path_to_formula>", args[0]);
+        eprintln!("       {} sample [--samples <n>] [--seed <s>] [--random-phase] [--json] <path_to_formula>", args[0]);
+        eprintln!("       {} mus <path_to_formula>", args[0]);
+        eprintln!("       {} marco [--mcs] [--time-limit <seconds>] <path_to_formula>", args[0]);
+        eprintln!("       {} backbone [--chunked <k> | --core-based] <path_to_formula>", args[0]);
         std::process::exit(1);
     }
     let path = path.unwrap();
@@ -19,11 +43,149 @@ fn main() {
         Err(e) => { eprintln!("Error reading file: {}", e); std::process::exit(1); }
     };
 
+    if counting {
+        count(&content, args.iter().any(|a| a == "--approx"));
+        return;
+    }
+
+    if mus || diagnosis {
+        let formula = if path.ends_with(".gcnf") {
+            match parse_gcnf(&content) {
+                Ok(f) => f,
+                Err(e) => { eprintln!("Error parsing GCNF: {}", e); std::process::exit(1); }
+            }
+        } else {
+            // Every clause is a group of its own, numbered from 1
+            let mut formula = parse_formula(&content);
+            formula.groups = std::mem::take(&mut formula.clauses).into_iter().enumerate().map(|(i, c)| (i + 1, c)).collect();
+            formula
+        };
+        if mus {
+            extract_mus(&formula);
+        } else {
+            let time_limit = match value_of("--time-limit").map(|t| t.parse::<f64>()) {
+                None => None,
+                Some(Ok(t)) if t >= 0.0 => Some(std::time::Duration::from_secs_f64(t)),
+                Some(_) => { eprintln!("Invalid time limit"); std::process::exit(1); }
+            };
+            diagnose(&formula, args.iter().any(|a| a == "--mcs"), time_limit);
+        }
+        return;
+    }
+
+    if backbone {
+        let algorithm = match value_of("--chunked").map(|k| k.parse::<usize>()) {
+            Some(Ok(k)) if k > 0 => BackboneAlgorithm::Chunked(k),
+            Some(_) => { eprintln!("Invalid chunk size"); std::process::exit(1); }
+            None if args.iter().any(|a| a == "--core-based") => BackboneAlgorithm::CoreBased,
+            None => BackboneAlgorithm::Iterative,
+        };
+        compute_backbone(&content, algorithm);
+        return;
+    }
+
+    if sampling {
+        let number = |flag: &str, default: u64| match value_of(flag).map(|n| n.parse::<u64>()) {
+            None => default,
+            Some(Ok(n)) => n,
+            Some(Err(_)) => { eprintln!("Invalid value of {}", flag); std::process::exit(1); }
+        };
+        let mode = if args.iter().any(|a| a == "--random-phase") { SamplingMode::RandomPhase } else { SamplingMode::Uniform };
+        sample(&content, number("--samples", 10) as usize, number("--seed", 1), mode, args.iter().any(|a| a == "--json"));
+        return;
+    }
+
+    // Print the gates recognized in the formula instead of solving it
+    if args.iter().any(|a| a == "--circuit") {
+        let formula = parse_formula(&content);
+        let clauses: Vec<Clause> = formula.clauses.into_iter().map(|lits| Clause { lits, learned: false }).collect();
+        let circuit = Circuit::from_clauses(&clauses, formula.num_vars);
+        if let Err(e) = circuit.dump(&mut std::io::stdout().lock()) {
+            eprintln!("Error writing circuit: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // MaxSAT instances, with output following the MaxSAT Evaluation conventions
+    if path.ends_with(".wcnf") {
+        let formula = match parse_wcnf(&content) {
+            Ok(f) => f,
+            Err(e) => { eprintln!("Error parsing WCNF: {}", e); std::process::exit(1); }
+        };
+        let algorithm = if args.iter().any(|a| a == "--linear") { MaxSatAlgorithm::Linear } else { MaxSatAlgorithm::CoreGuided };
+        match maxsat::solve_maxsat(&formula, algorithm, true) {
+            Some(solution) => {
+                println!("s OPTIMUM FOUND");
+                println!("v {}", solution.model.iter().map(|&b| if b { '1' } else { '0' }).collect::<String>());
+            }
+            None => println!("s UNSATISFIABLE"),
+        }
+        return;
+    }
+
+    if args.iter().any(|a| a == "--sls") {
+        let mut options = SlsOptions::default();
+        if args.iter().any(|a| a == "--walksat") { options.algorithm = SlsAlgorithm::WalkSat; }
+        options.time_limit = match value_of("--time-limit").map(|t| t.parse::<f64>()) {
+            None => Some(std::time::Duration::from_secs(60)),
+            Some(Ok(t)) if t >= 0.0 => Some(std::time::Duration::from_secs_f64(t)),
+            Some(_) => { eprintln!("Invalid time limit"); std::process::exit(1); }
+        };
+        if let Some(seed) = value_of("--seed") {
+            options.seed = match seed.parse() {
+                Ok(s) => s,
+                Err(_) => { eprintln!("Invalid value of --seed"); std::process::exit(1); }
+            };
+        }
+        run_sls(&content, &options);
+        return;
+    }
+
+    if args.iter().any(|a| a == "--enumerate") {
+        let limit = match limit.map(|n| n.parse::<usize>()) {
+            None => usize::MAX,
+            Some(Ok(n)) => n,
+            Some(Err(_)) => { eprintln!("Invalid model limit"); std::process::exit(1); }
+        };
+        enumerate(&content, limit, args.iter().any(|a| a == "--decision-blocking"));
+        return;
+    }
+
     println!("Solving {}", path);
     let start = std::time::Instant::now();
     
     // Call the library function
-    let result = run_solver_on_content(&content, true);
+    let result = match proof_path {
+        None if path.ends_with(".opb") => run_opb(&content),
+        None => run_solver_on_content(&content, true),
+        Some(_) if path.ends_with(".opb") => {
+            eprintln!("Proofs are only written for CNF input");
+            std::process::exit(1);
+        }
+        Some(proof_path) => {
+            let file = match fs::File::create(proof_path) {
+                Ok(f) => f,
+                Err(e) => { eprintln!("Error creating proof file: {}", e); std::process::exit(1); }
+            };
+            let formula = parse_formula(&content);
+            let mut solver = Solver::new(formula.num_vars);
+            solver.set_proof(proof::Proof::new(Box::new(std::io::BufWriter::new(file)), false));
+            for clause_lits in formula.clauses {
+                solver.add_clause(clause_lits);
+            }
+            for xor in formula.xors {
+                solver.add_xor(xor);
+            }
+            let mut strategy = RandomStrategy::new(formula.num_vars);
+            let result = solver.solve(&mut strategy, true);
+            if let Err(e) = solver.flush_proof() {
+                eprintln!("Error writing proof: {}", e);
+                std::process::exit(1);
+            }
+            result
+        }
+    };
     
     let duration = start.elapsed();
 
@@ -37,10 +199,211 @@ fn main() {
     println!("--------------------------------------------------");
 }
 
+/// Solves an OPB instance, minimizing its objective if it has one.
+fn run_opb(content: &str) -> bool {
+    let formula = match parse_opb(content) {
+        Ok(f) => f,
+        Err(e) => { eprintln!("Error parsing OPB: {}", e); std::process::exit(1); }
+    };
+    let mut solver = Solver::new(formula.num_vars);
+    for pb in formula.pbs {
+        solver.add_pb(pb.terms, pb.bound);
+    }
+    let mut strategy = RandomStrategy::new(formula.num_vars);
+    match &formula.objective {
+        None => solver.solve(&mut strategy, true),
+        Some(objective) => {
+            let optimum = solver.minimize(objective, &mut strategy, true);
+            if let Some(value) = optimum { println!("Optimum: {}", value); }
+            optimum.is_some()
+        }
+    }
+}
+
+/// Runs local search on a CNF instance. Prints the model found, or after the time limit the
+/// best assignment, which falsifies the fewest clauses.
+fn run_sls(content: &str, options: &SlsOptions) {
+    let formula = parse_formula(content);
+    if !formula.xors.is_empty() {
+        eprintln!("Local search only takes clauses, not XOR constraints");
+        std::process::exit(1);
+    }
+    println!("Searching with {:?}", options.algorithm);
+    let start = std::time::Instant::now();
+    let clauses: Vec<Clause> = formula.clauses.into_iter().map(|lits| Clause { lits, learned: false }).collect();
+    let result = sls::LocalSearch::new(formula.num_vars, &clauses).solve(None, options);
+    let lits: Vec<String> = result.assignment.iter().enumerate().map(|(var, &value)| if value { format!("{}", var + 1) } else { format!("-{}", var + 1) }).collect();
+    println!("v {} 0", lits.join(" "));
+
+    println!("--------------------------------------------------");
+    if result.falsified == 0 {
+        println!("Result: SATISFIABLE");
+    } else {
+        println!("Result: UNKNOWN (best assignment falsifies {} clauses)", result.falsified);
+    }
+    println!("Flips:  {}", result.flips);
+    println!("Time:   {:.4}s", start.elapsed().as_secs_f64());
+    println!("--------------------------------------------------");
+}
+
+/// Prints up to 'limit' models of a CNF instance as they are found, as "v" lines over its
+/// projection variables ("c ind" lines) or all variables.
+fn enumerate(content: &str, limit: usize, decision_blocking: bool) {
+    let start = std::time::Instant::now();
+    let formula = parse_formula(content);
+    let mut solver = Solver::new(formula.num_vars);
+    for clause_lits in formula.clauses {
+        solver.add_clause(clause_lits);
+    }
+    for xor in formula.xors {
+        solver.add_xor(xor);
+    }
+    let projection = formula.projection.unwrap_or_else(|| (0..formula.num_vars).collect());
+    let mut strategy = RandomStrategy::new(formula.num_vars);
+    let mut count = 0;
+    for model in solver.models(Some(&projection), &mut strategy).decision_blocking(decision_blocking).take(limit) {
+        let lits: Vec<String> = projection.iter().map(|&var| if model[var] { format!("{}", var + 1) } else { format!("-{}", var + 1) }).collect();
+        println!("v {} 0", lits.join(" "));
+        count += 1;
+    }
+
+    println!("--------------------------------------------------");
+    println!("Models: {}", count);
+    println!("Time:   {:.4}s", start.elapsed().as_secs_f64());
+    println!("--------------------------------------------------");
+}
+
+/// Prints a minimal unsatisfiable subset of the groups of 'formula' as a "v" line of group ids
+/// (clause numbers for CNF), after checking that it is one.
+fn extract_mus(formula: &Formula) {
+    let start = std::time::Instant::now();
+    let Some(ids) = mus::extract_mus(formula) else {
+        println!("s SATISFIABLE");
+        return;
+    };
+    if !mus::is_mus(formula, &ids) {
+        eprintln!("Error: the extracted subset is not a minimal unsatisfiable subset");
+        std::process::exit(1);
+    }
+    println!("s UNSATISFIABLE");
+    println!("v {} 0", ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" "));
+
+    println!("--------------------------------------------------");
+    println!("MUS:    {} of {} groups, verified minimal", ids.len(), formula.groups.iter().map(|(id, _)| id).collect::<std::collections::HashSet<_>>().len());
+    println!("Time:   {:.4}s", start.elapsed().as_secs_f64());
+    println!("--------------------------------------------------");
+}
+
+/// Prints the backbone of a CNF instance as a "v" line of DIMACS literals.
+fn compute_backbone(content: &str, algorithm: BackboneAlgorithm) {
+    let start = std::time::Instant::now();
+    let formula = parse_formula(content);
+    let Some(lits) = backbone::backbone(&formula, algorithm) else {
+        println!("s UNSATISFIABLE");
+        return;
+    };
+    println!("s SATISFIABLE");
+    let lits: Vec<String> = lits.iter().map(|l| if l.is_neg() { format!("-{}", l.var() + 1) } else { format!("{}", l.var() + 1) }).collect();
+    println!("v {} 0", lits.join(" "));
+
+    println!("--------------------------------------------------");
+    println!("Backbone: {} of {} variables", lits.len(), formula.num_vars);
+    println!("Time:     {:.4}s", start.elapsed().as_secs_f64());
+    println!("--------------------------------------------------");
+}
+
+/// Streams the MUSes and MCSes of 'formula' (or only its MCSes) as "mus" and "mcs" lines of
+/// group ids as they are found, until all are found or the time limit passes.
+fn diagnose(formula: &Formula, mcs_only: bool, time_limit: Option<std::time::Duration>) {
+    let start = std::time::Instant::now();
+    let time_limit = time_limit.unwrap_or(std::time::Duration::MAX);
+    let line = |kind: &str, ids: &[usize]| println!("{} {} 0", kind, ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" "));
+    let (mut muses, mut mcses) = (0, 0);
+    let interrupted = if mcs_only {
+        let mut enumeration = marco::mcses(formula).time_limit(time_limit);
+        for mcs in enumeration.by_ref() {
+            line("mcs", &mcs);
+            mcses += 1;
+        }
+        enumeration.interrupted()
+    } else {
+        let mut enumeration = marco::marco(formula).time_limit(time_limit);
+        for subset in enumeration.by_ref() {
+            match subset {
+                Subset::Mus(ids) => { line("mus", &ids); muses += 1; }
+                Subset::Mcs(ids) => { line("mcs", &ids); mcses += 1; }
+            }
+        }
+        enumeration.interrupted()
+    };
+
+    println!("--------------------------------------------------");
+    if !mcs_only { println!("MUSes:    {}", muses); }
+    println!("MCSes:    {}", mcses);
+    println!("Complete: {}", if interrupted { "no (time limit)" } else { "yes" });
+    println!("Time:     {:.4}s", start.elapsed().as_secs_f64());
+    println!("--------------------------------------------------");
+}
+
+/// Streams 'count' samples of a CNF instance, restricted to its sampling set ("c ind" lines, or
+/// all variables), as "v" lines or as one JSON array of literals per line.
+fn sample(content: &str, count: usize, seed: u64, mode: SamplingMode, json: bool) {
+    let start = std::time::Instant::now();
+    let formula = parse_formula(content);
+    let projection = formula.projection.clone().unwrap_or_else(|| (0..formula.num_vars).collect());
+    let mut sampled = 0;
+    for model in sampling::samples(&formula, None, mode, seed).take(count) {
+        let lits: Vec<String> = projection.iter().map(|&var| if model[var] { format!("{}", var + 1) } else { format!("-{}", var + 1) }).collect();
+        if json {
+            println!("[{}]", lits.join(", "));
+        } else {
+            println!("v {} 0", lits.join(" "));
+        }
+        sampled += 1;
+    }
+    if json { return; }
+
+    println!("--------------------------------------------------");
+    println!("Samples: {}", sampled);
+    println!("Time:    {:.4}s", start.elapsed().as_secs_f64());
+    println!("--------------------------------------------------");
+}
+
+/// Counts the models of a CNF instance, projected onto its "c ind" variables and weighted by its
+/// "c p weight" lines if it has any, or estimates the unweighted count with ApproxMC.
+fn count(content: &str, approximate: bool) {
+    let start = std::time::Instant::now();
+    let formula = parse_formula(content);
+    if approximate {
+        let estimate = approxmc::approx_count(&formula, &approxmc::ApproxMcOptions::default());
+        println!("--------------------------------------------------");
+        println!("Estimate: {}", estimate);
+        println!("Time:     {:.4}s", start.elapsed().as_secs_f64());
+        println!("--------------------------------------------------");
+        return;
+    }
+    let mut solver = Solver::new(formula.num_vars);
+    solver.options.xor_propagation = false;
+    for clause_lits in formula.clauses {
+        solver.add_clause(clause_lits);
+    }
+    for xor in formula.xors {
+        solver.add_xor(xor);
+    }
+    let count = solver.weighted_count(formula.projection.as_deref(), &formula.weights);
+
+    println!("--------------------------------------------------");
+    println!("Count:  {}", count);
+    println!("Time:   {:.4}s", start.elapsed().as_secs_f64());
+    println!("--------------------------------------------------");
+}
+
 // Integration Tests
 #[cfg(test)]
 mod tests {
     use super::*;
+    use satgpt::aig::{Aig, AigEncoder, AigEncoding, AigLit};
+    use satgpt::encodings::{self, AtMostOneEncoding, CardinalityEncoding, ClauseList, PbEncoding, Totalizer};
     use std::fs;
     use std::path::PathBuf;
 
@@ -60,6 +423,1073 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_failed_literal_probing() {
+        let to_lits = |clause: &[i32]| clause.iter().map(|&l| Lit::new(l.unsigned_abs() as usize - 1, l < 0)).collect();
+
+        // Probing x1 fails, which leaves x1 v x3 from the last clause: both polarities of x3 imply x4
+        let clauses: [&[i32]; 4] = [&[-1, 2], &[-1, -2], &[-3, 4], &[3, 1, 4]];
+        let mut solver = Solver::new(4);
+        for clause in clauses { solver.add_clause(to_lits(clause)); }
+        let stats = solver.probe(&mut RandomStrategy::new(4));
+        assert_eq!((stats.failed, stats.lifted), (1, 1));
+        assert_eq!(solver.assignments[0], VarValue::False);
+        assert_eq!(solver.assignments[3], VarValue::True);
+        assert_eq!(solver.assignments[2], VarValue::Unassigned);
+
+        // x1 implies x2, which implies x3 and x4 and through them x5: x5 is implied by x2 alone
+        let clauses: [&[i32]; 4] = [&[-1, 2], &[-2, 3], &[-2, 4], &[-3, -4, 5]];
+        let mut solver = Solver::new(5);
+        for clause in clauses { solver.add_clause(to_lits(clause)); }
+        let stats = solver.probe(&mut RandomStrategy::new(5));
+        assert_eq!((stats.failed, stats.lifted, stats.hyper_binaries), (0, 0, 1));
+        let resolvent: Vec<Lit> = to_lits(&[-2, 5]);
+        assert!(solver.clauses.iter().any(|c| c.lits.len() == 2 && resolvent.iter().all(|l| c.lits.contains(l))));
+        assert!(solver.assignments.iter().all(|&v| v == VarValue::Unassigned));
+        assert!(solver.solve(&mut RandomStrategy::new(5), false));
+
+        // x1 implies x2 and x3 and through them x4: the resolvent -x1 v x4 is dominated by the probe
+        let clauses: [&[i32]; 3] = [&[-1, 2], &[-1, 3], &[-2, -3, 4]];
+        let mut solver = Solver::new(4);
+        for clause in clauses { solver.add_clause(to_lits(clause)); }
+        let stats = solver.probe(&mut RandomStrategy::new(4));
+        assert_eq!(stats.hyper_binaries, 1);
+        let resolvent: Vec<Lit> = to_lits(&[-1, 4]);
+        assert!(solver.clauses.iter().any(|c| c.lits.len() == 2 && resolvent.iter().all(|l| c.lits.contains(l))));
+    }
+
+    #[test]
+    fn test_models_satisfy_original_clauses() {
+        let sat_dir = PathBuf::from("cnf/sat");
+        if !sat_dir.exists() { return; }
+
+        // The small aim instances (test_satisfiable_instances solves them all)
+        for entry in fs::read_dir(sat_dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_name().and_then(|s| s.to_str()).is_some_and(|name| name.starts_with("aim") && name.ends_with(".cnf")) {
+                let content = fs::read_to_string(&path).unwrap();
+                let formula = parse_formula(&content);
+                let mut solver = Solver::new(formula.num_vars);
+                for clause_lits in formula.clauses.iter() {
+                    solver.add_clause(clause_lits.clone());
+                }
+                let mut strategy = RandomStrategy::new(formula.num_vars);
+                assert!(solver.solve(&mut strategy, false), "Failed: {:?} should be SAT", path);
+
+                let model = solver.model();
+                for clause in &formula.clauses {
+                    assert!(clause.iter().any(|l| model[l.var()] != l.is_neg()),
+                        "Model of {:?} violates clause {:?}", path, clause);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_blocked_clause_elimination() {
+        let satisfies = |model: &[bool], clauses: &[Vec<Lit>]| clauses.iter().all(|c| c.iter().any(|l| model[l.var()] != l.is_neg()));
+
+        // Tseitin encoded gates (see test_gate_extraction) and the side constraint 9 v 1:
+        // covered literal addition removes more clauses than plain blocked clause elimination
+        let content = "p cnf 9 27\n-4 1 0\n-4 2 0\n4 -1 -2 0\n5 -1 0\n5 3 0\n-5 1 -3 0\n\
+            -6 -1 2 0\n-6 1 3 0\n6 -1 -2 0\n6 1 -3 0\n\
+            7 -1 -2 0\n7 -1 -3 0\n7 -2 -3 0\n-7 1 2 0\n-7 1 3 0\n-7 2 3 0\n\
+            8 1 2 0\n8 -1 -2 0\n-8 -1 2 0\n-8 1 -2 0\n\
+            -9 4 0\n-9 5 0\n-9 -6 0\n-9 7 0\n-9 8 0\n9 -4 -5 6 -7 -8 0\n9 1 0\n";
+        let formula = parse_formula(content);
+        let mut removed = Vec::new();
+        for covered in [false, true] {
+            let mut solver = Solver::new(formula.num_vars);
+            for clause in &formula.clauses {
+                solver.add_clause(clause.clone());
+            }
+            let stats = solver.eliminate_blocked_clauses(covered);
+            removed.push(stats.blocked + stats.covered);
+            assert_eq!(solver.clauses.len() + removed[removed.len() - 1], formula.clauses.len());
+            assert!(solver.solve(&mut RandomStrategy::new(formula.num_vars), false));
+            assert!(satisfies(solver.model(), &formula.clauses), "covered: {}", covered);
+        }
+        assert!(0 < removed[0] && removed[0] < removed[1], "{:?}", removed);
+
+        // Only 1 -> 2 of an equivalence: 1 v -2 is blocked and closes it, so x2 is substituted.
+        // Once the formula has been solved, "bca" adds nothing
+        let formula = parse_formula("p cnf 3 3\n-1 2 0\n1 3 0\n2 3 0\n");
+        let has_x2 = |solver: &Solver| solver.clauses.iter().any(|c| c.lits.iter().any(|l| l.var() == 1));
+        let mut solver = Solver::new(formula.num_vars);
+        solver.options.preprocessing = "bca,els".parse().unwrap();
+        for clause in &formula.clauses {
+            solver.add_clause(clause.clone());
+        }
+        assert!(solver.solve(&mut RandomStrategy::new(formula.num_vars), false));
+        assert!(satisfies(solver.model(), &formula.clauses));
+        assert!(!has_x2(&solver));
+        let mut solver = Solver::new(formula.num_vars);
+        solver.options.preprocessing = PipelineConfig::default();
+        for clause in &formula.clauses {
+            solver.add_clause(clause.clone());
+        }
+        assert!(solver.solve(&mut RandomStrategy::new(formula.num_vars), false));
+        let mut strategy = RandomStrategy::new(formula.num_vars);
+        assert!(pipeline::Pipeline::parse("bca,els").unwrap().run(&mut solver, &mut strategy, false));
+        assert!(has_x2(&solver));
+
+        // Incremental use: clauses and assumptions added after a solve bring back the eliminated
+        // clauses they touch, so every answer matches the formula
+        for seed in 1..300 {
+            let mut clauses = random_clauses(seed, 6, 10, 3);
+            let mut solver = Solver::new(6);
+            solver.options.preprocessing = "bce".parse().unwrap();
+            for clause in &clauses {
+                solver.add_clause(clause.clone());
+            }
+            for (round, clause) in random_clauses(seed + 1000, 6, 4, 2).into_iter().enumerate() {
+                let assumptions = &clause[..round % 2];
+                let mut expected = clauses.clone();
+                expected.extend(assumptions.iter().map(|&l| vec![l]));
+                let sat = solver.solve_with_assumptions(assumptions, &mut RandomStrategy::new(6), false);
+                assert_eq!(sat, !all_models(6, &expected).is_empty(), "{:?} under {:?}", clauses, assumptions);
+                if sat { assert!(satisfies(solver.model(), &expected), "{:?} under {:?}", clauses, assumptions); }
+                solver.add_clause(clause.clone());
+                clauses.push(clause);
+            }
+        }
+    }
+
+    #[test]
+    fn test_xor_clauses() {
+        // x1 ^ x2 ^ x3 = 1, x2 ^ -x3 ^ x4 ^ x5 ^ x6 = 1 (i.e. = 0 without the negation), x1 v x4
+        let content = "p cnf 6 1\nx1 2 3 0\nx2 -3 4 5 6 0\n1 4 0\n";
+        let formula = parse_formula(content);
+        assert_eq!(formula.xors.len(), 2);
+        assert_eq!(formula.num_vars, 6);
+
+        for native in [true, false] {
+            let mut solver = Solver::new(formula.num_vars);
+            solver.options.xor_propagation = native;
+            solver.options.xor_cut_size = 3;
+            for clause_lits in formula.clauses.iter() {
+                solver.add_clause(clause_lits.clone());
+            }
+            for xor in formula.xors.iter() {
+                solver.add_xor(xor.clone());
+            }
+            let mut strategy = RandomStrategy::new(formula.num_vars);
+            assert!(solver.solve(&mut strategy, false));
+
+            let model = solver.model();
+            for xor in &formula.xors {
+                let parity = xor.vars.iter().fold(false, |acc, &v| acc ^ model[v]);
+                assert_eq!(parity, xor.rhs, "Model violates XOR {:?} (native: {})", xor, native);
+            }
+            assert!(model[0] || model[3]);
+        }
+
+        // The same formula as plain clauses: the 5-variable XOR is cut in two by one more variable
+        let (clauses, num_vars) = parse_custom_format(content);
+        assert_eq!(num_vars, 7);
+        let mut projected: Vec<Vec<bool>> = all_models(num_vars, &clauses).into_iter().map(|m| m[..6].to_vec()).collect();
+        projected.dedup();
+        let expected: Vec<Vec<bool>> = all_models(6, &formula.clauses).into_iter()
+            .filter(|m| formula.xors.iter().all(|xor| xor.vars.iter().fold(false, |acc, &v| acc ^ m[v]) == xor.rhs))
+            .collect();
+        assert_eq!(projected, expected);
+
+        // x1 ^ x2 = 1, x2 ^ x3 = 1, x1 ^ x3 = 1 sums to 0 = 1
+        assert!(!run_solver_on_content("p cnf 3 0\nx1 2 0\nx2 3 0\nx1 3 0\n", false));
+
+        // Gaussian elimination on XORs given by their direct CNF encodings, plus a few other clauses
+        let mut removed = 0;
+        for seed in 1..150 {
+            let mut clauses = random_clauses(seed, 10, 3, 3);
+            for xor in random_clauses(seed + 1000, 10, 4, 5) {
+                let mut vars: Vec<usize> = xor.iter().map(|l| l.var()).collect();
+                vars.sort();
+                vars.dedup();
+                let rhs = xor[0].is_neg();
+                for mask in 0..1usize << vars.len() {
+                    if (mask.count_ones() % 2 == 1) == rhs { continue; }
+                    clauses.push(vars.iter().enumerate().map(|(i, &v)| Lit::new(v, mask >> i & 1 == 1)).collect());
+                }
+            }
+            let models = all_models(10, &clauses);
+            for native in [true, false] {
+                let mut solver = Solver::new(10);
+                solver.options.preprocessing = PipelineConfig::default();
+                solver.options.xor_propagation = native;
+                solver.options.xor_cut_size = 3;
+                for clause in &clauses {
+                    solver.add_clause(clause.clone());
+                }
+                removed += solver.gaussian_elimination().removed;
+                let sat = solver.solve(&mut RandomStrategy::new(10), false);
+                assert_eq!(sat, !models.is_empty(), "{:?} (native: {})", clauses, native);
+                if sat { assert!(models.contains(&solver.model()[..10].to_vec()), "{:?} (native: {})", clauses, native); }
+            }
+        }
+        assert!(removed > 0);
+
+        // Exactly-one constraints imply XORs of odd parity, but without parity structure
+        // elimination gains nothing: the clauses stay as they are
+        let formula = parse_formula("p cnf 6 9\n1 2 3 0\n-1 -2 0\n-1 -3 0\n-2 -3 0\n3 4 5 0\n-3 -4 0\n-3 -5 0\n-4 -5 0\n-1 6 0\n");
+        let mut solver = Solver::new(formula.num_vars);
+        for clause in &formula.clauses {
+            solver.add_clause(clause.clone());
+        }
+        let before: Vec<Vec<Lit>> = solver.clauses.iter().map(|c| c.lits.clone()).collect();
+        let stats = solver.gaussian_elimination();
+        assert_eq!((stats.xors, stats.removed, stats.aux_vars), (0, 0, 0));
+        assert_eq!(solver.clauses.iter().map(|c| c.lits.clone()).collect::<Vec<_>>(), before);
+        assert_eq!(solver.assignments.len(), 6);
+
+        // Native XORs propagated by the matrix against their CNF encodings: answers under
+        // assumptions, cores, and the models enumerated with blocking clauses
+        let satisfies = |model: &[bool], clauses: &[Vec<Lit>]| clauses.iter().all(|c| c.iter().any(|l| model[l.var()] != l.is_neg()));
+        for seed in 1..100 {
+            let clauses = random_clauses(seed, 9, 4, 3);
+            let xors: Vec<XorConstraint> = random_clauses(seed + 1000, 9, 4, 6).iter().map(|xor| {
+                let mut vars: Vec<usize> = xor.iter().map(|l| l.var()).collect();
+                vars.sort();
+                vars.dedup();
+                XorConstraint { vars, rhs: xor[0].is_neg() }
+            }).collect();
+            let mut encoded = clauses.clone();
+            for xor in &xors {
+                for mask in 0..1usize << xor.vars.len() {
+                    if (mask.count_ones() % 2 == 1) == xor.rhs { continue; }
+                    encoded.push(xor.vars.iter().enumerate().map(|(i, &v)| Lit::new(v, mask >> i & 1 == 1)).collect());
+                }
+            }
+            let models = all_models(9, &encoded);
+
+            for native in [true, false] {
+                let mut solver = Solver::new(9);
+                solver.options.preprocessing = PipelineConfig::default();
+                solver.options.xor_propagation = native;
+                for clause in &clauses {
+                    solver.add_clause(clause.clone());
+                }
+                for xor in &xors {
+                    solver.add_xor(xor.clone());
+                }
+                for assumptions in random_clauses(seed + 2000, 9, 4, 3) {
+                    let sat = solver.solve_with_assumptions(&assumptions, &mut RandomStrategy::new(9), false);
+                    let units: Vec<Vec<Lit>> = assumptions.iter().map(|&l| vec![l]).collect();
+                    assert_eq!(sat, models.iter().any(|m| satisfies(m, &units)), "seed {} under {:?} (native: {})", seed, assumptions, native);
+                    if sat {
+                        assert!(models.contains(&solver.model()[..9].to_vec()), "seed {} (native: {})", seed, native);
+                    } else {
+                        let core: Vec<Vec<Lit>> = solver.core().iter().map(|&l| vec![l]).collect();
+                        assert!(core.iter().all(|c| assumptions.contains(&c[0])));
+                        assert!(!models.iter().any(|m| satisfies(m, &core)), "seed {} core {:?} (native: {})", seed, core, native);
+                    }
+                }
+                // Cut XORs add auxiliary variables after the first 9
+                let projection: Vec<usize> = (0..9).collect();
+                let mut enumerated: Vec<Vec<bool>> = solver.models(Some(&projection), &mut RandomStrategy::new(9)).map(|m| m[..9].to_vec()).collect();
+                enumerated.sort();
+                assert_eq!(enumerated, models, "seed {} (native: {})", seed, native);
+            }
+        }
+    }
+
+    #[test]
+    fn test_xor_extraction() {
+        // Direct encoding of x1 ^ ... ^ x8 = 0 among some other clauses
+        let mut clauses: Vec<Clause> = random_clauses(1, 10, 20, 3).into_iter().map(|lits| Clause { lits, learned: false }).collect();
+        let first = clauses.len();
+        for mask in 0..1usize << 8 {
+            if mask.count_ones() % 2 == 0 { continue; }
+            let lits = (0..8).map(|v| Lit::new(v, mask >> v & 1 == 1)).collect();
+            clauses.push(Clause { lits, learned: false });
+        }
+        let found = xor_finder::find_xors(&clauses, 10, 8);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].xor.vars.clone(), found[0].xor.rhs), ((0..8).collect(), false));
+        let encoding: Vec<usize> = (first..clauses.len()).collect();
+        assert_eq!((&found[0].clauses, &found[0].defining), (&encoding, &encoding));
+        assert!(xor_finder::find_xors(&clauses, 10, 7).is_empty());
+
+        // x1 ^ x2 ^ x3 = 1 with two of its clauses replaced by the stronger -x1 v -x3:
+        // only the clauses over all three variables are implied by the XOR
+        let to_clause = |clause: &[i32]| Clause { lits: clause.iter().map(|&l| Lit::new(l.unsigned_abs() as usize - 1, l < 0)).collect(), learned: false };
+        let clauses: Vec<Clause> = [&[1, 2, 3][..], &[1, -2, -3], &[-1, -3], &[-1, -2, 3], &[2, 4]].iter().map(|c| to_clause(c)).collect();
+        let found = xor_finder::find_xors(&clauses, 4, 8);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].xor.vars.clone(), found[0].xor.rhs), (vec![0, 1, 2], true));
+        assert_eq!((found[0].clauses.clone(), found[0].defining.clone()), (vec![0, 1, 2, 3], vec![0, 1, 3]));
+
+        // Without the short clause nothing forbids x1 = x3 = 1, x2 = 0
+        let incomplete: Vec<Clause> = clauses.iter().enumerate().filter(|&(i, _)| i != 2).map(|(_, c)| c.clone()).collect();
+        assert!(xor_finder::find_xors(&incomplete, 4, 8).is_empty());
+
+        // x1 ^ .. ^ x10 = 1 cut into pieces of 4 variables, chained by x11, x12, x13
+        let mut next = 10;
+        let pieces = preprocessing::cut_xor(&(0..10).collect::<Vec<usize>>(), true, 4, &mut || { next += 1; next - 1 });
+        let pieces: Vec<(Vec<usize>, bool)> = pieces.into_iter().map(|p| (p.vars, p.rhs)).collect();
+        assert_eq!(pieces, vec![
+            (vec![0, 1, 2, 10], false), (vec![10, 3, 4, 11], false), (vec![11, 5, 6, 12], false), (vec![12, 7, 8, 9], true),
+        ]);
+        // Every assignment of odd parity extends to the auxiliary variables in exactly one way
+        let mut extensions = vec![0; 1 << 10];
+        for bits in 0..1usize << 13 {
+            if pieces.iter().all(|(vars, rhs)| vars.iter().fold(false, |acc, &v| acc ^ (bits >> v & 1 == 1)) == *rhs) {
+                extensions[bits & 0x3FF] += 1;
+            }
+        }
+        assert!((0..1usize << 10).all(|bits| extensions[bits] == (bits.count_ones() % 2) as usize));
+        let short = preprocessing::cut_xor(&[1, 2, 3, 4], false, 4, &mut || unreachable!());
+        assert_eq!((short.len(), &short[0].vars), (1, &vec![1, 2, 3, 4]));
+
+        // One block per component of XORs sharing variables, with columns local to the block
+        let xors = [vec![0, 1, 2], vec![7, 8, 9], vec![5, 2], vec![3], vec![]];
+        let xors: Vec<XorConstraint> = xors.into_iter().enumerate().map(|(i, vars)| XorConstraint { vars, rhs: i % 2 == 0 }).collect();
+        let blocks = preprocessing::xor_blocks(&xors, 10);
+        let columns: Vec<&Vec<usize>> = blocks.iter().map(|(columns, _)| columns).collect();
+        assert_eq!(columns, [&vec![0, 1, 2, 5], &vec![7, 8, 9], &vec![3]]);
+        let rows: Vec<Vec<(Vec<usize>, bool)>> = blocks.iter().map(|(_, rows)| rows.iter().map(|r| (r.ones().collect(), r.rhs)).collect()).collect();
+        assert_eq!(rows, vec![
+            vec![(vec![0, 1, 2], true), (vec![2, 3], true)],
+            vec![(vec![0, 1, 2], false)],
+            vec![(vec![0], false)],
+        ]);
+    }
+
+    #[test]
+    fn test_gate_extraction() {
+        // 4 = AND(1, 2), 5 = OR(1, -3), 6 = ITE(1, 2, 3), 7 = MAJ(1, 2, 3), 8 = XOR(-1, 2),
+        // 9 = AND(4, 5, -6, 7, 8)
+        let content = "p cnf 9 27\n-4 1 0\n-4 2 0\n4 -1 -2 0\n5 -1 0\n5 3 0\n-5 1 -3 0\n\
+            -6 -1 2 0\n-6 1 3 0\n6 -1 -2 0\n6 1 -3 0\n\
+            7 -1 -2 0\n7 -1 -3 0\n7 -2 -3 0\n-7 1 2 0\n-7 1 3 0\n-7 2 3 0\n\
+            8 1 2 0\n8 -1 -2 0\n-8 -1 2 0\n-8 1 -2 0\n\
+            -9 4 0\n-9 5 0\n-9 -6 0\n-9 7 0\n-9 8 0\n9 -4 -5 6 -7 -8 0\n9 0\n";
+        let formula = parse_formula(content);
+        let clauses: Vec<Clause> = formula.clauses.into_iter().map(|lits| Clause { lits, learned: false }).collect();
+        let circuit = Circuit::from_clauses(&clauses, formula.num_vars);
+
+        let expected = ["4 = AND(1, 2)", "5 = OR(1, -3)", "6 = ITE(1, 2, 3)", "7 = MAJ(1, 2, 3)", "8 = XOR(-1, 2)"];
+        for (var, text) in (3..8).zip(expected) {
+            assert_eq!(circuit.gate(var).map(|g| g.to_string()).as_deref(), Some(text));
+        }
+        assert_eq!(circuit.gates().last().unwrap().to_string(), "9 = AND(4, 5, -6, 7, 8)");
+        assert_eq!(circuit.inputs(), vec![0, 1, 2]);
+        assert_eq!(circuit.outputs(), vec![8]);
+    }
+
+    #[test]
+    fn test_preprocessing_pipeline() {
+        assert!(pipeline::Pipeline::parse("[sub,bve]*3,probe,gauss").is_ok());
+        assert!(pipeline::Pipeline::parse("").is_ok());
+        assert!(pipeline::Pipeline::parse("sub,foo").is_err());
+        assert!(pipeline::Pipeline::parse("[sub,bve*2").is_err());
+        let invalid = "sub,,bve".parse::<PipelineConfig>();
+        assert_eq!(invalid, Err("missing pass name at position 4".to_string()));
+        assert_eq!("[sub,bve]*3,probe".parse::<PipelineConfig>().unwrap().as_str(), "[sub,bve]*3,probe");
+
+        // Variables eliminated by a first solve come back when a later call assumes them
+        let lit = |code: usize| Lit::new(code >> 1, code & 1 == 1);
+        let mut solver = Solver::new(4);
+        solver.options.preprocessing = "sub,bve".parse().unwrap();
+        for clause in [&[0, 3, 6][..], &[0, 6, 5], &[1], &[4, 2]] {
+            solver.add_clause(clause.iter().map(|&code| lit(code)).collect());
+        }
+        assert!(solver.solve(&mut RandomStrategy::new(4), false));
+        assert!(!solver.solve_with_assumptions(&[lit(7)], &mut RandomStrategy::new(4), false));
+        assert_eq!(solver.core(), &[lit(7)]);
+
+        // ... or later clauses use them
+        let satisfies = |model: &[bool], clauses: &[Vec<Lit>]| clauses.iter().all(|c| c.iter().any(|l| model[l.var()] != l.is_neg()));
+        for seed in 1..100 {
+            let mut clauses = random_clauses(seed, 6, 10, 3);
+            let mut solver = Solver::new(6);
+            solver.options.preprocessing = "[sub,bve],bce".parse().unwrap();
+            for clause in &clauses {
+                solver.add_clause(clause.clone());
+            }
+            for (round, clause) in random_clauses(seed + 1000, 6, 4, 2).into_iter().enumerate() {
+                let assumptions = &clause[..round % 2];
+                let mut expected = clauses.clone();
+                expected.extend(assumptions.iter().map(|&l| vec![l]));
+                let sat = solver.solve_with_assumptions(assumptions, &mut RandomStrategy::new(6), false);
+                assert_eq!(sat, !all_models(6, &expected).is_empty(), "{:?} under {:?}", clauses, assumptions);
+                if sat { assert!(satisfies(solver.model(), &expected), "{:?} under {:?}", clauses, assumptions); }
+                solver.add_clause(clause.clone());
+                clauses.push(clause);
+            }
+        }
+
+        // Inprocessing after almost every conflict, across incremental calls, against a solver without it
+        for seed in 1..50 {
+            let mut clauses: Vec<Vec<Lit>> = random_clauses(seed, 30, 400, 3).into_iter().filter(|c| c.len() == 3).take(125).collect();
+            let mut solver = Solver::new(30);
+            solver.options.preprocessing = PipelineConfig::default();
+            solver.options.inprocessing = "probe,[sub,bve],els,bce".parse().unwrap();
+            solver.options.inprocess_interval = 1;
+            for clause in &clauses {
+                solver.add_clause(clause.clone());
+            }
+            for (round, clause) in random_clauses(seed + 1000, 30, 4, 3).into_iter().enumerate() {
+                let assumptions = &clause[..clause.len().min(round % 3)];
+                let mut expected = clauses.clone();
+                expected.extend(assumptions.iter().map(|&l| vec![l]));
+                let mut reference = Solver::new(30);
+                reference.options.inprocessing = PipelineConfig::default();
+                for clause in &expected {
+                    reference.add_clause(clause.clone());
+                }
+                let sat = solver.solve_with_assumptions(assumptions, &mut RandomStrategy::new(30), false);
+                assert_eq!(sat, reference.solve(&mut RandomStrategy::new(30), false), "{:?} under {:?}", clauses, assumptions);
+                if sat { assert!(satisfies(solver.model(), &expected), "{:?} under {:?}", clauses, assumptions); }
+                solver.add_clause(clause.clone());
+                clauses.push(clause);
+            }
+        }
+
+        let unsat_dir = PathBuf::from("cnf/unsat");
+        if !unsat_dir.exists() { return; }
+        for entry in fs::read_dir(unsat_dir).unwrap().take(4) {
+            let path = entry.unwrap().path();
+            let formula = parse_formula(&fs::read_to_string(&path).unwrap());
+            let mut solver = Solver::new(formula.num_vars);
+            solver.options.inprocessing = "probe,sub,els".parse().unwrap();
+            solver.options.inprocess_interval = 10;
+            for clause_lits in formula.clauses.iter() {
+                solver.add_clause(clause_lits.clone());
+            }
+            assert!(!solver.solve(&mut RandomStrategy::new(formula.num_vars), false), "Failed: {:?} should be UNSAT", path);
+        }
+
+        let sat_dir = PathBuf::from("cnf/sat");
+        if !sat_dir.exists() { return; }
+        // The small aim instances (test_satisfiable_instances solves them all)
+        for entry in fs::read_dir(sat_dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_name().and_then(|s| s.to_str()).is_some_and(|name| name.starts_with("aim") && name.ends_with(".cnf")) {
+                let content = fs::read_to_string(&path).unwrap();
+                let formula = parse_formula(&content);
+                let mut solver = Solver::new(formula.num_vars);
+                solver.options.preprocessing = "[sub,bve]*3,probe,els,[sub,bve],bce".parse().unwrap();
+                solver.options.inprocessing = "probe,sub,els".parse().unwrap();
+                solver.options.inprocess_interval = 10;
+                for clause_lits in formula.clauses.iter() {
+                    solver.add_clause(clause_lits.clone());
+                }
+                let mut strategy = RandomStrategy::new(formula.num_vars);
+                assert!(solver.solve(&mut strategy, false), "Failed: {:?} should be SAT", path);
+
+                let model = solver.model();
+                for clause in &formula.clauses {
+                    assert!(clause.iter().any(|l| model[l.var()] != l.is_neg()),
+                        "Model of {:?} violates clause {:?}", path, clause);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cardinality_detection() {
+        // Pairwise at-most-one over x1..x5, direct at-most-two over x6..x11
+        let mut clauses = Vec::new();
+        for i in 1..=5 {
+            for j in i + 1..=5 { clauses.push(vec![-i, -j]); }
+        }
+        for i in 6..=11 {
+            for j in i + 1..=11 {
+                for k in j + 1..=11 { clauses.push(vec![-i, -j, -k]); }
+            }
+        }
+        let to_lits = |clause: &Vec<i32>| clause.iter().map(|&l| Lit::new(l.unsigned_abs() as usize - 1, l < 0)).collect();
+        let mut solver = Solver::new(11);
+        for clause in &clauses { solver.add_clause(to_lits(clause)); }
+        let mut strategy = RandomStrategy::new(11);
+        let stats = solver.detect_cardinality_constraints(&mut strategy);
+        assert_eq!((stats.at_most_one, stats.at_most_k, stats.removed), (1, 1, 30));
+
+        // Pigeonhole with n pigeons and 4 holes, pairwise at-most-one per hole
+        for (pigeons, sat) in [(4, true), (5, false)] {
+            let var = |p: i32, h: i32| p * 4 + h + 1;
+            let mut clauses: Vec<Vec<i32>> = (0..pigeons).map(|p| (0..4).map(|h| var(p, h)).collect()).collect();
+            for h in 0..4 {
+                for p in 0..pigeons {
+                    for q in p + 1..pigeons { clauses.push(vec![-var(p, h), -var(q, h)]); }
+                }
+            }
+            let num_vars = (pigeons * 4) as usize;
+            let mut solver = Solver::new(num_vars);
+            solver.options.preprocessing = "card,probe,els".parse().unwrap();
+            for clause in &clauses { solver.add_clause(to_lits(clause)); }
+            let mut strategy = RandomStrategy::new(num_vars);
+            assert_eq!(solver.solve(&mut strategy, false), sat);
+            if sat {
+                let model = solver.model();
+                for clause in &clauses {
+                    assert!(clause.iter().any(|&l| model[l.unsigned_abs() as usize - 1] != (l < 0)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pseudo_boolean_constraints() {
+        assert!(parse_opb("+1 x1 +2 x2 >= 1 ;\n+1 x1 x2 >= 1 ;").is_err());
+        assert!(parse_opb("+1 x1 +2 y2 >= 1 ;").is_err());
+        assert!(parse_opb("+1 x1 +2 x2 ;").is_err());
+
+        // Knapsack with weights 3, 4, 5, 6 and values 4, 5, 6, 8 under capacity 10, x1 = x3:
+        // the best choice is x2, x4 with value 13
+        let content = "* #variable= 4 #constraint= 2\nmin: -4 x1 -5 x2 -6 x3 -8 x4 ;\n\
+            +3 x1 +4 x2 +5 x3 +6 x4 <= 10 ;\n+1 x1 +1 ~x3 = 1 ;\n";
+        let formula = parse_opb(content).unwrap();
+        assert_eq!((formula.num_vars, formula.pbs.len()), (4, 3));
+        for config in ["probe,els,gauss,bce", "card,probe,els"] {
+            let mut solver = Solver::new(formula.num_vars);
+            solver.options.preprocessing = config.parse().unwrap();
+            for pb in formula.pbs.iter() {
+                solver.add_pb(pb.terms.clone(), pb.bound);
+            }
+            let mut strategy = RandomStrategy::new(formula.num_vars);
+            assert_eq!(solver.minimize(formula.objective.as_ref().unwrap(), &mut strategy, false), Some(-13));
+
+            let model = solver.model();
+            assert_eq!(model, [false, true, false, true]);
+            for pb in &formula.pbs {
+                let sum: i64 = pb.terms.iter().filter(|(_, l)| model[l.var()] != l.is_neg()).map(|(a, _)| a).sum();
+                assert!(sum >= pb.bound, "Model violates {:?}", pb);
+            }
+        }
+
+        // 2 x1 + 3 ~x2 >= 4 forces x1, ~x2
+        let formula = parse_opb("+2 x1 +3 ~x2 >= 4 ;\n+1 x2 +1 ~x1 >= 1 ;\n").unwrap();
+        let mut solver = Solver::new(formula.num_vars);
+        for pb in formula.pbs {
+            solver.add_pb(pb.terms, pb.bound);
+        }
+        assert!(!solver.solve(&mut RandomStrategy::new(formula.num_vars), false));
+    }
+
+    #[test]
+    fn test_maxsat() {
+        // a -> b -> c: assuming a and ~c fails with both in the core, then both hold alone
+        let (a, b, c) = (Lit::new(0, false), Lit::new(1, false), Lit::new(2, false));
+        let mut solver = Solver::new(3);
+        solver.add_clause(vec![a.not(), b]);
+        solver.add_clause(vec![b.not(), c]);
+        let mut strategy = RandomStrategy::new(3);
+        assert!(!solver.solve_with_assumptions(&[a, c.not()], &mut strategy, false));
+        let mut core = solver.core().to_vec();
+        core.sort_by_key(|l| l.to_usize());
+        assert_eq!(core, [a, c.not()]);
+        assert!(solver.solve_with_assumptions(&[a], &mut strategy, false));
+        assert!(solver.model()[2]);
+        assert!(solver.solve_with_assumptions(&[c.not()], &mut strategy, false));
+        assert!(!solver.model()[0]);
+
+        // Hard x1 | x2: the optimum 3 falsifies ~x1, or ~x2 and x3
+        let new = "c new format\nh 1 2 0\n3 -1 0\n2 -2 0\n1 3 0\n4 -3 1 0\n";
+        let old = "p wcnf 3 5 10\n10 1 2 0\n3 -1 0\n2 -2 0\n1 3 0\n4 -3 1 0\n";
+        for content in [new, old] {
+            let formula = parse_wcnf(content).unwrap();
+            assert_eq!((formula.num_vars, formula.clauses.len(), formula.soft.len()), (3, 1, 4));
+            for algorithm in [MaxSatAlgorithm::CoreGuided, MaxSatAlgorithm::Linear] {
+                let solution = maxsat::solve_maxsat(&formula, algorithm, false).unwrap();
+                assert_eq!(solution.cost, 3, "{:?}", algorithm);
+                assert!(solution.model[0] || solution.model[1]);
+            }
+        }
+
+        let formula = parse_wcnf("h 1 0\nh -1 0\n5 1 0\n").unwrap();
+        assert!(maxsat::solve_maxsat(&formula, MaxSatAlgorithm::CoreGuided, false).is_none());
+    }
+
+    #[test]
+    fn test_model_enumeration() {
+        // x1 | x2, x2 -> x3, x4 = ~x3: 4 models, 3 projections onto x1, x2 and 2 onto x1
+        let content = "p cnf 4 2\nc ind 1 2 0\n1 2 0\n-2 3 0\nx3 4 0\n";
+        let formula = parse_formula(content);
+        assert_eq!(formula.projection, Some(vec![0, 1]));
+        for decision_blocking in [false, true] {
+            for (projection, expected) in [(None, 4), (formula.projection.clone(), 3), (Some(vec![0]), 2), (Some(vec![]), 1)] {
+                let mut solver = Solver::new(formula.num_vars);
+                for clause in &formula.clauses {
+                    solver.add_clause(clause.clone());
+                }
+                for xor in &formula.xors {
+                    solver.add_xor(xor.clone());
+                }
+                let mut strategy = RandomStrategy::new(formula.num_vars);
+                let models: Vec<Vec<bool>> = solver.models(projection.as_deref(), &mut strategy).decision_blocking(decision_blocking).collect();
+                let vars = projection.unwrap_or_else(|| (0..4).collect());
+                let mut projected: Vec<Vec<bool>> = models.iter().map(|m| vars.iter().map(|&v| m[v]).collect()).collect();
+                projected.sort();
+                projected.dedup();
+                assert_eq!((models.len(), projected.len()), (expected, expected));
+                for model in &models {
+                    assert!((model[0] || model[1]) && (!model[1] || model[2]) && model[2] != model[3]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_model_counting() {
+        let big: BigUint = "885443715538058477568".parse().unwrap();
+        assert_eq!(big.to_string(), "885443715538058477568");
+        assert_eq!((&big * &BigUint::from(u64::MAX)).to_string(), "16333553612205046245356537441186815672320");
+        assert_eq!("0.650".parse::<Decimal>().unwrap().to_string(), "0.65");
+
+        // x1 | x2 over 70 variables: 3 · 2^68 models
+        let mut solver = Solver::new(70);
+        solver.add_clause(vec![Lit::new(0, false), Lit::new(1, false)]);
+        assert_eq!(solver.count(None), big);
+
+        // With x1 weighing 0.3 (~x1 0.7) and x2 0.5, only ~x1 & ~x2 (0.35) is missing;
+        // projected onto x1 both values remain
+        let content = "p cnf 3 2\nc p weight 1 0.3 0\nc p weight 2 0.5 0\nc p weight -2 0.5 0\n1 2 0\nx1 2 3 0\n";
+        let formula = parse_formula(content);
+        let solve = |projection: Option<&[usize]>, weights: &[(Lit, Decimal)]| {
+            let mut solver = Solver::new(formula.num_vars);
+            solver.options.xor_propagation = false;
+            for clause in &formula.clauses {
+                solver.add_clause(clause.clone());
+            }
+            for xor in &formula.xors {
+                solver.add_xor(xor.clone());
+            }
+            solver.weighted_count(projection, weights).to_string()
+        };
+        assert_eq!(formula.weights.len(), 4);
+        assert_eq!(solve(None, &[]), "3");
+        assert_eq!(solve(None, &formula.weights), "0.65");
+        assert_eq!(solve(Some(&[0]), &[]), "2");
+        assert_eq!(solve(Some(&[0]), &formula.weights), "1");
+        assert_eq!(solve(Some(&[]), &[]), "1");
+    }
+
+    #[test]
+    fn test_approximate_counting() {
+        let options = approxmc::ApproxMcOptions::default();
+        // Fewer solutions than the threshold are counted exactly
+        let formula = parse_formula("p cnf 4 2\nc ind 1 2 3 0\n1 2 0\nx2 3 4 0\n");
+        assert_eq!(approxmc::approx_count(&formula, &options), BigUint::from(6));
+
+        // x1 | x2 over 20 variables: 3 · 2^18 models, estimated within a factor 1 + epsilon
+        let formula = parse_formula("p cnf 20 2\n1 2 0\n20 -20 0\n");
+        let exact = (3u64 << 18) as f64;
+        let estimate: f64 = approxmc::approx_count(&formula, &options).to_string().parse().unwrap();
+        assert!(estimate <= exact * (1.0 + options.epsilon) && estimate >= exact / (1.0 + options.epsilon), "{}", estimate);
+    }
+
+    #[test]
+    fn test_sampling() {
+        let satisfies = |formula: &Formula, model: &[bool]| formula.clauses.iter().all(|c| c.iter().any(|l| model[l.var()] != l.is_neg()));
+        // x1 | x2 projected onto x1, x2 (few solutions): each of the 3 drawn about equally often
+        let formula = parse_formula("p cnf 3 2\nc ind 1 2 0\n1 2 0\n-2 3 0\n");
+        let mut frequencies = std::collections::HashMap::new();
+        for model in sampling::samples(&formula, None, SamplingMode::Uniform, 1).take(300) {
+            assert!(satisfies(&formula, &model));
+            *frequencies.entry((model[0], model[1])).or_insert(0) += 1;
+        }
+        assert_eq!(frequencies.len(), 3);
+        assert!(frequencies.values().all(|&n| (70..=130).contains(&n)), "{:?}", frequencies);
+
+        // 576 solutions, sampled through hash cells
+        let formula = parse_formula("p cnf 10 3\n1 2 0\n3 -4 0\n10 -10 0\n");
+        let mut frequencies = std::collections::HashMap::new();
+        for model in sampling::samples(&formula, None, SamplingMode::Uniform, 1).take(300) {
+            assert!(satisfies(&formula, &model));
+            *frequencies.entry((model[0], model[1])).or_insert(0) += 1;
+        }
+        assert!(frequencies.values().all(|&n| (60..=140).contains(&n)), "{:?}", frequencies);
+
+        // Randomised phases: valid, diverse and reproducible from the seed
+        let sampled: Vec<Vec<bool>> = sampling::samples(&formula, None, SamplingMode::RandomPhase, 7).take(50).collect();
+        assert!(sampled.iter().all(|model| satisfies(&formula, model)));
+        assert!(sampled.iter().collect::<std::collections::HashSet<_>>().len() > 25);
+        assert_eq!(sampling::samples(&formula, None, SamplingMode::RandomPhase, 7).take(50).collect::<Vec<_>>(), sampled);
+
+        let formula = parse_formula("p cnf 1 2\n1 0\n-1 0\n");
+        assert_eq!(sampling::samples(&formula, None, SamplingMode::Uniform, 1).next(), None);
+    }
+
+    #[test]
+    fn test_mus_extraction() {
+        // Group 0 is hard; {1, 2} is the only minimal unsatisfiable subset
+        let content = "p gcnf 3 6 4\n{0} 1 2 0\n{1} -1 0\n{2} -2 0\n{3} 2 3 0\n{4} 3 0\n{2} -3 -2 0\n";
+        let formula = parse_gcnf(content).unwrap();
+        assert_eq!(formula.clauses.len(), 1);
+        assert_eq!(mus::extract_mus(&formula), Some(vec![1, 2]));
+        assert!(mus::is_mus(&formula, &[1, 2]));
+        assert!(!mus::is_mus(&formula, &[1, 2, 4]));
+        assert!(!mus::is_mus(&formula, &[1]));
+        let satisfiable = Formula { groups: formula.groups.iter().filter(|(id, _)| *id != 2).cloned().collect(), ..formula.clone() };
+        assert_eq!(mus::extract_mus(&satisfiable), None);
+        assert!(parse_gcnf("p gcnf 1 1 1\n1 0\n").is_err());
+
+        // Clause-level MUSes of the unsatisfiable instances
+        let unsat_dir = PathBuf::from("cnf/unsat");
+        if !unsat_dir.exists() { return; }
+        for entry in fs::read_dir(unsat_dir).unwrap().take(4) {
+            let path = entry.unwrap().path();
+            let mut formula = parse_formula(&fs::read_to_string(&path).unwrap());
+            formula.groups = std::mem::take(&mut formula.clauses).into_iter().enumerate().map(|(i, c)| (i + 1, c)).collect();
+            let ids = mus::extract_mus(&formula).unwrap();
+            assert!(mus::is_mus(&formula, &ids), "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn test_mcs_enumeration_and_marco() {
+        // MUSes {1, 2} and {1, 3, 4}; MCSes, their minimal hitting sets: {1}, {2, 3} and {2, 4}
+        let formula = parse_gcnf("p gcnf 2 4 4\n{1} 1 0\n{2} -1 0\n{3} 2 0\n{4} -1 -2 0\n").unwrap();
+        let mut mcses: Vec<Vec<usize>> = marco::mcses(&formula).collect();
+        mcses.sort();
+        assert_eq!(mcses, vec![vec![1], vec![2, 3], vec![2, 4]]);
+
+        let mut subsets: Vec<Subset> = marco::marco(&formula).collect();
+        subsets.sort_by_key(|s| format!("{:?}", s));
+        assert_eq!(subsets, vec![
+            Subset::Mcs(vec![1]), Subset::Mcs(vec![2, 3]), Subset::Mcs(vec![2, 4]),
+            Subset::Mus(vec![1, 2]), Subset::Mus(vec![1, 3, 4]),
+        ]);
+        for subset in &subsets {
+            if let Subset::Mus(ids) = subset { assert!(mus::is_mus(&formula, ids)); }
+        }
+
+        // A satisfiable formula needs no correction
+        let satisfiable = parse_gcnf("p gcnf 2 2 2\n{1} 1 0\n{2} 2 0\n").unwrap();
+        assert_eq!(marco::mcses(&satisfiable).collect::<Vec<_>>(), vec![Vec::<usize>::new()]);
+        assert_eq!(marco::marco(&satisfiable).collect::<Vec<_>>(), vec![Subset::Mcs(vec![])]);
+
+        let mut interrupted = marco::marco(&formula).time_limit(std::time::Duration::ZERO);
+        assert_eq!(interrupted.next(), None);
+        assert!(interrupted.interrupted());
+    }
+
+    #[test]
+    fn test_backbone() {
+        // x2 by resolution, x5 as a unit, -x1 through the XOR; x3 and x4 are not both true, no more
+        let formula = parse_formula("p cnf 5 4\n1 2 0\n-1 2 0\n-3 -4 0\n5 0\nx1 5 0\n");
+        let expected = vec![Lit::new(0, true), Lit::new(1, false), Lit::new(4, false)];
+        for algorithm in [BackboneAlgorithm::Iterative, BackboneAlgorithm::Chunked(1), BackboneAlgorithm::Chunked(2), BackboneAlgorithm::CoreBased] {
+            assert_eq!(backbone::backbone(&formula, algorithm), Some(expected.clone()), "{:?}", algorithm);
+        }
+        let unsatisfiable = parse_formula("p cnf 1 2\n1 0\n-1 0\n");
+        assert_eq!(backbone::backbone(&unsatisfiable, BackboneAlgorithm::default()), None);
+    }
+
+    #[test]
+    fn test_local_search() {
+        for name in ["aim-50-1_6-yes1-1", "aim-50-3_4-yes1-2", "sw100-11"] {
+            let path = PathBuf::from(format!("cnf/sat/{}.cnf", name));
+            if !path.exists() { continue; }
+            let formula = parse_formula(&fs::read_to_string(&path).unwrap());
+            let clauses: Vec<Clause> = formula.clauses.iter().map(|lits| Clause { lits: lits.clone(), learned: false }).collect();
+            for algorithm in [SlsAlgorithm::ProbSat, SlsAlgorithm::WalkSat] {
+                let options = SlsOptions { algorithm, ..SlsOptions::default() };
+                let result = sls::LocalSearch::new(formula.num_vars, &clauses).solve(None, &options);
+                assert_eq!(result.falsified, 0, "{} with {:?}", name, algorithm);
+                assert!(formula.clauses.iter().all(|c| c.iter().any(|l| result.assignment[l.var()] != l.is_neg())), "{} with {:?}", name, algorithm);
+            }
+        }
+
+        // All four clauses over x1, x2: every assignment falsifies exactly one
+        let formula = parse_formula("p cnf 2 4\n1 2 0\n1 -2 0\n-1 2 0\n-1 -2 0\n");
+        let clauses: Vec<Clause> = formula.clauses.into_iter().map(|lits| Clause { lits, learned: false }).collect();
+        let mut search = sls::LocalSearch::new(2, &clauses);
+        for algorithm in [SlsAlgorithm::ProbSat, SlsAlgorithm::WalkSat] {
+            let options = SlsOptions { algorithm, max_flips: 1000, ..SlsOptions::default() };
+            let result = search.solve(Some(&[true, true]), &options);
+            assert_eq!((result.falsified, result.flips), (1, 1000));
+        }
+    }
+
+    #[test]
+    fn test_local_search_rephasing() {
+        for name in ["aim-50-1_6-yes1-1", "aim-100-1_6-yes1-1", "sw100-10", "sw100-11"] {
+            let path = PathBuf::from(format!("cnf/sat/{}.cnf", name));
+            if !path.exists() { continue; }
+            let formula = parse_formula(&fs::read_to_string(&path).unwrap());
+            let mut solver = Solver::new(formula.num_vars);
+            solver.options.rephasing = true;
+            solver.options.rephase_interval = 5;
+            for clause_lits in formula.clauses.clone() {
+                solver.add_clause(clause_lits);
+            }
+            let mut strategy = RandomStrategy::new(formula.num_vars);
+            assert!(solver.solve(&mut strategy, false), "{} should be SAT", name);
+            let model = solver.model();
+            assert!(formula.clauses.iter().all(|c| c.iter().any(|l| model[l.var()] != l.is_neg())), "{}", name);
+            assert!(solver.target_phase(0).is_some(), "{} was never rephased", name);
+        }
+
+        // Rephasing after every conflict, also right after learning a unit: enumeration finds
+        // exactly the models of small formulas
+        let lit = |code: usize| Lit::new(code >> 1, code & 1 == 1);
+        let repro: Vec<Vec<Lit>> = [&[1, 5, 4][..], &[3, 1, 5], &[4, 5], &[1, 1, 5], &[5, 1, 7], &[6, 1], &[1, 5, 5], &[1, 3]]
+            .iter().map(|c| c.iter().map(|&code| lit(code)).collect()).collect();
+        let formulas = std::iter::once(repro).chain((1..300).map(|seed| random_clauses(seed, 5, 8, 3)));
+        for clauses in formulas {
+            let num_vars = clauses.iter().flatten().map(|l| l.var() + 1).max().unwrap();
+            let mut solver = Solver::new(num_vars);
+            solver.options.rephasing = true;
+            solver.options.rephase_interval = 1;
+            solver.options.rephase_flips = 50;
+            for clause in &clauses {
+                solver.add_clause(clause.clone());
+            }
+            let mut strategy = RandomStrategy::new(num_vars);
+            let mut models: Vec<Vec<bool>> = solver.models(None, &mut strategy).collect();
+            models.sort();
+            assert_eq!(models, all_models(num_vars, &clauses), "{:?}", clauses);
+        }
+    }
+
+    /// 'count' random clauses with 1 to 'max_len' literals over 'num_vars' variables.
+    fn random_clauses(seed: u64, num_vars: usize, count: usize, max_len: usize) -> Vec<Vec<Lit>> {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        (0..count).map(|_| {
+            let len = 1 + next(max_len);
+            (0..len).map(|_| Lit::new(next(num_vars), next(2) == 1)).collect()
+        }).collect()
+    }
+
+    /// The models of 'clauses' over 'num_vars' variables, in increasing order, by brute force.
+    fn all_models(num_vars: usize, clauses: &[Vec<Lit>]) -> Vec<Vec<bool>> {
+        (0..1usize << num_vars)
+            .map(|bits| (0..num_vars).rev().map(|v| (bits >> v) & 1 == 1).collect::<Vec<bool>>())
+            .filter(|model| clauses.iter().all(|c| c.iter().any(|l| model[l.var()] != l.is_neg())))
+            .collect()
+    }
+
+    #[test]
+    fn test_aig_builder() {
+        let mut aig = Aig::new();
+        let (a, b, c) = (aig.input(), aig.input(), aig.input());
+
+        // Hash-consing and constant propagation
+        let ab = aig.and(a, b);
+        assert_eq!(aig.and(b, a), ab);
+        assert_eq!(aig.and(a, a.not()), AigLit::FALSE);
+        assert_eq!(aig.or(ab, AigLit::TRUE), AigLit::TRUE);
+        assert_eq!(aig.ite(AigLit::TRUE, a, b), a);
+        assert_eq!(aig.xor(a, AigLit::FALSE), a);
+        assert_eq!(aig.num_ands(), 1);
+
+        // Majority built twice, checked by simulation and by a miter
+        let majority = aig.majority(a, b, c);
+        let b_or_c = aig.or(b, c);
+        let b_and_c = aig.and(b, c);
+        let by_ite = aig.ite(a, b_or_c, b_and_c);
+        for bits in 0..8 {
+            let inputs = [bits & 1 != 0, bits & 2 != 0, bits & 4 != 0];
+            let values = aig.simulate(&inputs);
+            assert_eq!(majority.value(&values), inputs.iter().filter(|&&x| x).count() >= 2);
+            assert_eq!(by_ite.value(&values), majority.value(&values));
+        }
+        let miter = aig.xor(majority, by_ite);
+        let parity = aig.xor(a, b);
+        let parity = aig.xor(parity, c);
+        let target = aig.and(majority, parity.not());
+        for encoding in [AigEncoding::Tseitin, AigEncoding::PlaistedGreenbaum] {
+            let mut solver = Solver::new(0);
+            let mut encoder = AigEncoder::new(encoding);
+            encoder.assert(&aig, &mut solver, miter);
+            assert!(!solver.solve(&mut RandomStrategy::new(0), false), "{:?}", encoding);
+
+            // Exactly two inputs true: the model evaluates the target to true on the circuit
+            let mut solver = Solver::new(0);
+            let mut encoder = AigEncoder::new(encoding);
+            assert!(encoder.assert(&aig, &mut solver, target));
+            let lit_a = encoder.lit(&aig, &mut solver, a);
+            assert!(solver.solve_with_assumptions(&[lit_a.not()], &mut RandomStrategy::new(0), false), "{:?}", encoding);
+            let values = encoder.evaluate(&aig, solver.model());
+            assert!(target.value(&values) && !a.value(&values) && b.value(&values) && c.value(&values));
+        }
+
+        // One encoder used between solves (with the default simplifications) as the graph grows
+        for encoding in [AigEncoding::Tseitin, AigEncoding::PlaistedGreenbaum] {
+            for seed in 1..40 {
+                let mut aig = Aig::new();
+                let mut edges: Vec<AigLit> = (0..4).map(|_| aig.input()).collect();
+                let mut solver = Solver::new(0);
+                let mut encoder = AigEncoder::new(encoding);
+                let mut asserted = Vec::new();
+                let picks: Vec<usize> = random_clauses(seed, 1 << 16, 60, 1).iter().map(|c| c[0].to_usize()).collect();
+                for (round, pick) in picks.chunks(3).enumerate() {
+                    let edge = |edges: &[AigLit], i: usize| if pick[i] & 1 == 1 { edges[pick[i] % edges.len()].not() } else { edges[pick[i] % edges.len()] };
+                    let (left, right) = (edge(&edges, 0), edge(&edges, 1));
+                    let node = if pick[2] % 3 == 0 { aig.xor(left, right) } else { aig.and(left, right) };
+                    edges.push(node);
+                    if round % 4 != 3 { continue; }
+                    let assumption = edge(&edges, 2);
+                    if round % 8 == 3 {
+                        asserted.push(node);
+                        encoder.assert(&aig, &mut solver, node);
+                    }
+                    let lit = encoder.lit(&aig, &mut solver, assumption);
+                    let holds = |values: &[bool]| asserted.iter().all(|e| e.value(values)) && assumption.value(values);
+                    let expected = (0..16).any(|bits| holds(&aig.simulate(&[bits & 1 != 0, bits & 2 != 0, bits & 4 != 0, bits & 8 != 0])));
+                    let sat = solver.solve_with_assumptions(&[lit], &mut RandomStrategy::new(0), false);
+                    assert_eq!(sat, expected, "{:?} seed {} round {}", encoding, seed, round);
+                    if sat { assert!(holds(&encoder.evaluate(&aig, solver.model())), "{:?} seed {} round {}", encoding, seed, round); }
+                }
+            }
+        }
+    }
+
+    /// Checks that the clauses of 'encode' over 'n' variables admit exactly the assignments of
+    /// those variables accepted by 'expected', both in a `Solver` and as a clause list.
+    fn check_encoding(n: usize, encode: &dyn Fn(&mut dyn encodings::ClauseSink, &[Lit]), expected: &dyn Fn(&[bool]) -> bool) {
+        let lits: Vec<Lit> = (0..n).map(|v| Lit::new(v, false)).collect();
+        let mut list = ClauseList::new(n);
+        encode(&mut list, &lits);
+        let mut solver = Solver::new(n);
+        encode(&mut solver, &lits);
+        let mut listed = Solver::new(list.num_vars);
+        for clause in list.clauses {
+            listed.add_clause(clause);
+        }
+        for bits in 0..1usize << n {
+            let assignment: Vec<bool> = (0..n).map(|v| bits >> v & 1 == 1).collect();
+            let assumptions: Vec<Lit> = (0..n).map(|v| Lit::new(v, !assignment[v])).collect();
+            let mut strategy = RandomStrategy::new(n);
+            assert_eq!(solver.solve_with_assumptions(&assumptions, &mut strategy, false), expected(&assignment), "{:?}", assignment);
+            assert_eq!(listed.solve_with_assumptions(&assumptions, &mut strategy, false), expected(&assignment), "{:?}", assignment);
+        }
+    }
+
+    #[test]
+    fn test_cnf_encodings() {
+        let count = |a: &[bool]| a.iter().filter(|&&x| x).count();
+        for n in 0..=5 {
+            for encoding in [AtMostOneEncoding::Pairwise, AtMostOneEncoding::Sequential, AtMostOneEncoding::Commander] {
+                check_encoding(n, &|sink, lits| encodings::at_most_one(sink, lits, encoding), &|a| count(a) <= 1);
+            }
+            for k in 0..=n + 1 {
+                for encoding in [CardinalityEncoding::Totalizer, CardinalityEncoding::ModuloTotalizer, CardinalityEncoding::CardinalityNetwork, CardinalityEncoding::SortingNetwork] {
+                    check_encoding(n, &|sink, lits| encodings::at_most_k(sink, lits, k, encoding), &|a| count(a) <= k);
+                    check_encoding(n, &|sink, lits| encodings::at_least_k(sink, lits, k, encoding), &|a| count(a) >= k);
+                }
+            }
+        }
+
+        let coefficients = [5u64, 3, 3, 2, 6];
+        let sum = |a: &[bool]| a.iter().zip(coefficients).filter(|&(&x, _)| x).map(|(_, c)| c).sum::<u64>();
+        let terms = |lits: &[Lit]| lits.iter().zip(coefficients).map(|(&l, c)| (c, l)).collect::<Vec<_>>();
+        for bound in 0..=20 {
+            for encoding in [PbEncoding::Bdd, PbEncoding::Adder] {
+                check_encoding(5, &|sink, lits| encodings::pb_at_most(sink, &terms(lits), bound, encoding), &|a| sum(a) <= bound);
+                check_encoding(5, &|sink, lits| encodings::pb_at_least(sink, &terms(lits), bound, encoding), &|a| sum(a) >= bound);
+            }
+            // The weighted totalizer of OLL, with the outputs above the bound forbidden
+            check_encoding(5, &|sink, lits| {
+                for (value, output) in encodings::weighted_totalizer(sink, &terms(lits), bound + 1) {
+                    if value > bound { sink.add_clause(vec![output.not()]); }
+                }
+            }, &|a| sum(a) <= bound);
+        }
+        assert!(encodings::weighted_totalizer(&mut ClauseList::new(0), &[], 3).is_empty());
+
+        // An incremental totalizer raises its bound and takes more inputs
+        let mut totalizer = Totalizer::new(&[Lit::new(0, false), Lit::new(1, false), Lit::new(2, false)]);
+        let mut list = ClauseList::new(5);
+        let two = totalizer.at_least(&mut list, 2).unwrap();
+        let three = totalizer.at_least(&mut list, 3).unwrap();
+        totalizer.add_inputs(&[Lit::new(3, false), Lit::new(4, false)]);
+        let four = totalizer.at_least(&mut list, 4).unwrap();
+        assert_eq!((totalizer.num_inputs(), totalizer.at_least(&mut list, 6)), (5, None));
+        let mut solver = Solver::new(list.num_vars);
+        for clause in list.clauses {
+            solver.add_clause(clause);
+        }
+        for (true_vars, bound, sat) in [(vec![0, 1], two, false), (vec![0, 3], two, true), (vec![0, 1], three, true), (vec![1, 3, 4], four, true), (vec![1, 2, 3, 4], four, false)] {
+            let mut assumptions: Vec<Lit> = true_vars.iter().map(|&v| Lit::new(v, false)).collect();
+            assumptions.push(bound.not());
+            assert_eq!(solver.solve_with_assumptions(&assumptions, &mut RandomStrategy::new(5), false), sat, "{:?}", true_vars);
+        }
+    }
+
+    /// Forward DRAT check by naive unit propagation (RUP, else RAT on the first literal).
+    fn check_drat(mut formula: Vec<Vec<i32>>, proof: &str) -> bool {
+        fn rup(formula: &[Vec<i32>], clause: &[i32]) -> bool {
+            if clause.iter().any(|l| clause.contains(&-l)) { return true; }
+            let mut assigned: Vec<i32> = clause.iter().map(|l| -l).collect();
+            loop {
+                let mut changed = false;
+                for c in formula {
+                    if c.iter().any(|l| assigned.contains(l)) { continue; }
+                    let open: Vec<i32> = c.iter().copied().filter(|l| !assigned.contains(&-l)).collect();
+                    match open.len() {
+                        0 => return true,
+                        1 => { assigned.push(open[0]); changed = true; }
+                        _ => {}
+                    }
+                }
+                if !changed { return false; }
+            }
+        }
+        for line in proof.lines() {
+            let deletion = line.starts_with("d ");
+            let lits: Vec<i32> = line.trim_start_matches("d ").split_whitespace()
+                .map(|t| t.parse().unwrap()).filter(|&l| l != 0).collect();
+            let same = |c: &Vec<i32>| c.len() == lits.len() && c.iter().all(|l| lits.contains(l));
+            if deletion {
+                if let Some(i) = formula.iter().position(same) { formula.swap_remove(i); }
+                continue;
+            }
+            let rat = || formula.iter().filter(|c| c.contains(&-lits[0])).all(|c| {
+                let resolvent: Vec<i32> = lits.iter().chain(c.iter().filter(|&&l| l != -lits[0])).copied().collect();
+                rup(&formula, &resolvent)
+            });
+            if !rup(&formula, &lits) && (lits.is_empty() || !rat()) { return false; }
+            if lits.is_empty() { return true; }
+            formula.push(lits);
+        }
+        false
+    }
+
+    #[test]
+    fn test_drat_proofs() {
+        // x1 ^ x2 ^ x3 = 1, x3 ^ x4 ^ x5 = 1, x1 ^ x2 ^ x4 ^ x5 = 1 sums to 0 = 1, in CNF,
+        // plus an at-most-one over x6..x8 with (x6 v x7) and (x7 v x8) next to a few Tseitin ANDs
+        let content = "p cnf 11 0\n1 2 3 0\n1 -2 -3 0\n-1 2 -3 0\n-1 -2 3 0\n\
+            3 4 5 0\n3 -4 -5 0\n-3 4 -5 0\n-3 -4 5 0\n\
+            1 2 4 5 0\n1 2 -4 -5 0\n1 -2 4 -5 0\n1 -2 -4 5 0\n-1 2 4 -5 0\n-1 2 -4 5 0\n-1 -2 4 5 0\n-1 -2 -4 -5 0\n\
+            -6 -7 0\n-6 -8 0\n-7 -8 0\n6 7 0\n7 8 0\n-9 6 0\n-9 1 0\n9 -6 -1 0\n-10 9 0\n-10 11 0\n10 -9 -11 0\n";
+        let formula = parse_formula(content);
+        let dimacs: Vec<Vec<i32>> = formula.clauses.iter()
+            .map(|c| c.iter().map(|l| (l.var() as i32 + 1) * if l.is_neg() { -1 } else { 1 }).collect())
+            .collect();
+
+        for config in ["probe,bca,els,gauss,bce", "gauss", "[sub,bve]*2,probe,els", ""] {
+            let buffer = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+            struct Shared(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+            impl std::io::Write for Shared {
+                fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { self.0.borrow_mut().write(buf) }
+                fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+            }
+
+            let mut solver = Solver::new(formula.num_vars);
+            solver.options.preprocessing = config.parse().unwrap();
+            solver.set_proof(proof::Proof::new(Box::new(Shared(buffer.clone())), false));
+            for clause_lits in formula.clauses.iter() {
+                solver.add_clause(clause_lits.clone());
+            }
+            let mut strategy = RandomStrategy::new(formula.num_vars);
+            assert!(!solver.solve(&mut strategy, false));
+            assert!(solver.flush_proof().is_ok());
+
+            let proof = String::from_utf8(buffer.borrow().clone()).unwrap();
+            assert!(check_drat(dimacs.clone(), &proof), "Invalid proof with \"{}\":\n{}", config, proof);
+        }
+    }
+
     #[test]
     fn test_unsatisfiable_instances() {
         let unsat_dir = PathBuf::from("cnf/unsat");
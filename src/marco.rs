@@ -0,0 +1,148 @@
+use std::time::{Duration, Instant};
+
+use crate::mus::Selection;
+use crate::{BranchingStrategy, Formula, Lit, Solver, VarValue};
+
+// =========================================================================
+// MCS Enumeration and MUS/MCS Duality (MARCO)
+// =========================================================================
+
+/// A subset of the groups of a formula found by `marco`, as ascending group ids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Subset {
+    /// Minimal unsatisfiable subset: unsatisfiable, satisfiable without any one of its groups.
+    Mus(Vec<usize>),
+    /// Minimal correction set: the formula is satisfiable without these groups, but not without
+    /// any smaller subset of them. The complement of a maximal satisfiable subset.
+    Mcs(Vec<usize>),
+}
+
+/// Iterator over all minimal correction sets of a formula (see `mcses`).
+pub struct Mcses {
+    selection: Selection,
+    interrupted: bool,
+}
+
+/// Iterator over all minimal unsatisfiable subsets and minimal correction sets of a formula
+/// (see `marco`).
+pub struct Marco {
+    selection: Selection,
+    map: Solver,            // One variable per group: its models are the unexplored subsets
+    strategy: EnableFirst,
+    interrupted: bool,
+}
+
+/// Enumerates the minimal correction sets of the groups of 'formula' (`groups`; its `clauses`
+/// and `xors` are hard): the minimal sets of groups whose removal makes it satisfiable.
+/// A satisfiable formula has the single empty one, hard constraints that are unsatisfiable on
+/// their own have none.
+///
+/// Every step grows the groups satisfied by a model into a maximal satisfiable subset, whose
+/// complement is the next MCS; a clause over the selectors of the MCS then demands that later
+/// subsets keep one of its groups, so they can only lead to other MCSes.
+pub fn mcses(formula: &Formula) -> Mcses {
+    Mcses { selection: Selection::new(formula), interrupted: false }
+}
+
+/// Enumerates the minimal unsatisfiable subsets and the minimal correction sets of the groups of
+/// 'formula' together, as in MARCO: a map solver over one variable per group proposes a large
+/// subset not explored yet. A satisfiable one is grown into a maximal satisfiable subset, and
+/// its complement, an MCS, is blocked by demanding one of its groups; an unsatisfiable one is
+/// shrunk to an MUS, which is blocked by demanding the absence of one of its groups. The map
+/// runs out once all MUSes and MCSes are found, as they are hitting-set duals.
+pub fn marco(formula: &Formula) -> Marco {
+    let selection = Selection::new(formula);
+    let map = Solver::new(selection.ids.len());
+    Marco { selection, map, strategy: EnableFirst, interrupted: false }
+}
+
+impl Mcses {
+    /// Stops the enumeration at the first step after 'limit' (see `interrupted`).
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.selection.deadline = Instant::now().checked_add(limit); // None: too far to matter
+        self
+    }
+
+    /// Whether the enumeration ended at the time limit rather than being complete.
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+}
+
+impl Marco {
+    /// Stops the enumeration at the first step after 'limit' (see `interrupted`).
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.selection.deadline = Instant::now().checked_add(limit); // None: too far to matter
+        self
+    }
+
+    /// Whether the enumeration ended at the time limit rather than being complete.
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+}
+
+impl Iterator for Mcses {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.interrupted { return None; }
+        if self.selection.expired() {
+            self.interrupted = true;
+            return None;
+        }
+        if self.selection.core_of(&[]).is_some() { return None; }
+        let model = self.selection.model();
+        let seed: Vec<usize> = (0..self.selection.ids.len()).filter(|&g| self.selection.satisfies(&model, g)).collect();
+        let Some(mss) = self.selection.grow(&seed) else {
+            self.interrupted = true;
+            return None;
+        };
+        let mcs: Vec<usize> = (0..self.selection.ids.len()).filter(|g| !mss.contains(g)).collect();
+        self.selection.require_one_of(&mcs);
+        Some(mcs.iter().map(|&g| self.selection.ids[g]).collect())
+    }
+}
+
+impl Iterator for Marco {
+    type Item = Subset;
+
+    fn next(&mut self) -> Option<Subset> {
+        if self.interrupted { return None; }
+        if self.selection.expired() {
+            self.interrupted = true;
+            return None;
+        }
+        if !self.map.solve(&mut self.strategy, false) { return None; }
+        let seed: Vec<usize> = (0..self.selection.ids.len()).filter(|&g| self.map.model()[g]).collect();
+
+        let subset = match self.selection.core_of(&seed) {
+            Some(core) => self.selection.shrink(core).map(|mus| {
+                self.map.add_clause(mus.iter().map(|&g| Lit::new(g, true)).collect());
+                Subset::Mus(mus.iter().map(|&g| self.selection.ids[g]).collect())
+            }),
+            None => self.selection.grow(&seed).map(|mss| {
+                let mcs: Vec<usize> = (0..self.selection.ids.len()).filter(|g| !mss.contains(g)).collect();
+                self.map.add_clause(mcs.iter().map(|&g| Lit::new(g, false)).collect());
+                Subset::Mcs(mcs.iter().map(|&g| self.selection.ids[g]).collect())
+            }),
+        };
+        self.interrupted = subset.is_none();
+        subset
+    }
+}
+
+/// Branching strategy of the map: enables groups first, so that the seeds are large and
+/// satisfiable ones need little growing.
+struct EnableFirst;
+
+impl BranchingStrategy for EnableFirst {
+    fn pick_branch(&mut self, solver: &Solver) -> Option<Lit> {
+        let var = solver.assignments.iter().position(|&v| v == VarValue::Unassigned)?;
+        Some(Lit::new(var, false))
+    }
+
+    fn on_conflict(&mut self, _involved_vars: &[usize]) {}
+    fn on_assign(&mut self, _var: usize) {}
+    fn on_unassign(&mut self, _var: usize, _old_value: bool) {}
+}
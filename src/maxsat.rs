@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use crate::encodings::{weighted_totalizer, Totalizer};
+use crate::{Formula, Lit, RandomStrategy, Solver};
+
+// =========================================================================
+// MaxSAT: Core-Guided (OLL) and Linear SAT-UNSAT Search
+// =========================================================================
+
+/// Number of times a core is shrunk by solving under it again.
+const CORE_TRIMMING_ROUNDS: usize = 3;
+
+/// How `solve_maxsat` searches for the optimum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaxSatAlgorithm {
+    /// OLL, as in RC2: raises a lower bound with the unsatisfiable cores found under assumptions,
+    /// relaxing every core by a totalizer whose next output becomes a new assumption.
+    #[default]
+    CoreGuided,
+    /// SAT-UNSAT search: bounds a weighted totalizer over the soft clauses below the cost of
+    /// every model found, until no model is left.
+    Linear,
+}
+
+/// An optimal assignment: the total weight of the soft clauses it falsifies, and the model.
+#[derive(Debug, Clone)]
+pub struct MaxSatSolution {
+    pub cost: u64,
+    pub model: Vec<bool>,
+}
+
+/// The hard clauses in a `Solver`, with every soft clause relaxed by a literal that may only be
+/// true if the clause is falsified.
+struct Relaxation {
+    solver: Solver,
+    strategy: RandomStrategy,
+    soft: Vec<(u64, Lit)>, // Weight and relaxation literal, one entry per literal
+    fixed: u64,            // Weight of the empty soft clauses
+}
+
+/// Solves the weighted partial MaxSAT instance of 'formula': its clauses are hard, `soft` may be
+/// falsified at the cost of their weights. Returns None if the hard clauses are unsatisfiable.
+/// In verbose mode, the cost of every better model found is printed as an "o" line.
+pub fn solve_maxsat(formula: &Formula, algorithm: MaxSatAlgorithm, verbose: bool) -> Option<MaxSatSolution> {
+    let mut relaxation = Relaxation::new(formula);
+    let model = match algorithm {
+        MaxSatAlgorithm::CoreGuided => relaxation.oll(formula, verbose)?,
+        MaxSatAlgorithm::Linear => relaxation.linear(formula, verbose)?,
+    };
+    let model = model[..formula.num_vars].to_vec();
+    Some(MaxSatSolution { cost: cost(formula, &model), model })
+}
+
+/// Total weight of the soft clauses of 'formula' falsified by 'model'.
+fn cost(formula: &Formula, model: &[bool]) -> u64 {
+    formula.soft.iter()
+        .filter(|(_, clause)| !clause.iter().any(|l| model[l.var()] != l.is_neg()))
+        .map(|&(w, _)| w)
+        .sum()
+}
+
+impl Relaxation {
+    fn new(formula: &Formula) -> Self {
+        let mut solver = Solver::new(formula.num_vars);
+        for clause in &formula.clauses {
+            solver.add_clause(clause.clone());
+        }
+
+        // Unit soft clauses are relaxed by their negation, longer ones by a fresh variable
+        let mut soft = Vec::with_capacity(formula.soft.len());
+        let mut fixed = 0;
+        for (w, clause) in &formula.soft {
+            match clause.len() {
+                _ if *w == 0 => {}
+                0 => fixed += w,
+                1 => soft.push((*w, clause[0].not())),
+                _ => {
+                    let relax = Lit::new(solver.new_var(), false);
+                    let mut lits = clause.clone();
+                    lits.push(relax);
+                    solver.add_clause(lits);
+                    soft.push((*w, relax));
+                }
+            }
+        }
+        soft.sort_by_key(|&(_, l)| l.to_usize());
+        let mut merged: Vec<(u64, Lit)> = Vec::with_capacity(soft.len());
+        for (w, l) in soft {
+            match merged.last_mut() {
+                Some(last) if last.1 == l => last.0 += w,
+                _ => merged.push((w, l)),
+            }
+        }
+
+        let strategy = RandomStrategy::new(solver.num_vars);
+        Relaxation { solver, strategy, soft: merged, fixed }
+    }
+
+    /// OLL: assumes every relaxation literal false, weighted by its remaining weight.
+    /// A core raises the lower bound by its smallest weight, which is taken from all its members;
+    /// the core is then relaxed by a totalizer whose output "at least 2 true" is assumed false
+    /// with that weight, and a totalizer output in a core makes way for the next one (which the
+    /// incremental totalizer only encodes then).
+    /// Assumptions are stratified: lighter ones only join once the heavier ones are satisfiable.
+    /// The first model satisfying all assumptions is optimal.
+    fn oll(&mut self, formula: &Formula, verbose: bool) -> Option<Vec<bool>> {
+        let mut weights: HashMap<Lit, u64> = self.soft.iter().map(|&(w, l)| (l.not(), w)).collect();
+        let mut totalizers: Vec<Totalizer> = Vec::new();
+        let mut outputs: HashMap<Lit, (usize, usize)> = HashMap::new(); // Assumption -> (totalizer, output)
+        let mut stratum = weights.values().copied().max().unwrap_or(0);
+        let mut upper = u64::MAX;
+
+        loop {
+            let mut assumptions: Vec<Lit> = weights.iter().filter(|&(_, &w)| w >= stratum).map(|(&l, _)| l).collect();
+            assumptions.sort_by_key(|l| l.to_usize());
+            if self.solve_under(&assumptions) {
+                let model = self.solver.model().to_vec();
+                let cost = cost(formula, &model);
+                if cost < upper {
+                    upper = cost;
+                    if verbose { println!("o {}", cost); }
+                }
+                match weights.values().copied().filter(|&w| w < stratum).max() {
+                    Some(next) => stratum = next,
+                    None => return Some(model),
+                }
+                continue;
+            }
+            let mut core = self.solver.core().to_vec();
+            if core.is_empty() { return None; }
+            // Solving under the core again often finds a smaller one
+            for _ in 0..CORE_TRIMMING_ROUNDS {
+                if core.len() <= 2 || self.solve_under(&core) || self.solver.core().len() >= core.len() { break; }
+                core = self.solver.core().to_vec();
+            }
+
+            let min = core.iter().map(|l| weights[l]).min().unwrap();
+            for l in &core {
+                let weight = weights.get_mut(l).unwrap();
+                *weight -= min;
+                if *weight == 0 { weights.remove(l); }
+                if let Some(&(t, k)) = outputs.get(l) && let Some(next) = totalizers[t].at_least(&mut self.solver, k + 2) {
+                    *weights.entry(next.not()).or_insert(0) += min;
+                    outputs.insert(next.not(), (t, k + 1));
+                }
+            }
+            if core.len() == 1 {
+                self.solver.add_clause(vec![core[0].not()]);
+                continue;
+            }
+            let relaxed: Vec<Lit> = core.iter().map(|l| l.not()).collect();
+            let mut sum = Totalizer::new(&relaxed);
+            let at_least_two = sum.at_least(&mut self.solver, 2).unwrap();
+            *weights.entry(at_least_two.not()).or_insert(0) += min;
+            outputs.insert(at_least_two.not(), (totalizers.len(), 1));
+            totalizers.push(sum);
+        }
+    }
+
+    /// Solves under 'assumptions'. Only the first call simplifies the formula: later ones
+    /// mostly add a few clauses to it.
+    fn solve_under(&mut self, assumptions: &[Lit]) -> bool {
+        self.solver.solve_with_assumptions(assumptions, &mut self.strategy, false)
+    }
+
+    /// SAT-UNSAT search: after every model, forbids the weighted totalizer outputs of its cost
+    /// and more. The last model is optimal.
+    fn linear(&mut self, formula: &Formula, verbose: bool) -> Option<Vec<bool>> {
+        if !self.solve_under(&[]) { return None; }
+        let mut best = self.solver.model().to_vec();
+        let mut upper = cost(formula, &best);
+        if verbose { println!("o {}", upper); }
+        if upper == self.fixed { return Some(best); }
+
+        let sums = weighted_totalizer(&mut self.solver, &self.soft, upper - self.fixed);
+        loop {
+            for &(value, o) in &sums {
+                if value >= upper - self.fixed { self.solver.add_clause(vec![o.not()]); }
+            }
+            if !self.solve_under(&[]) { return Some(best); }
+            best = self.solver.model().to_vec();
+            upper = cost(formula, &best);
+            if verbose { println!("o {}", upper); }
+            if upper == self.fixed { return Some(best); }
+        }
+    }
+}
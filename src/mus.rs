@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use crate::{Formula, Lit, RandomStrategy, Solver};
+
+// =========================================================================
+// Minimal Unsatisfiable Subsets: Deletion with Refinement and Model Rotation
+// =========================================================================
+
+/// The clauses of a formula in a `Solver`: the hard ones as they are, those of every group
+/// guarded by a selector literal, so that a group is only enabled while its selector is assumed.
+/// Groups are numbered by index, in the ascending order of their ids.
+pub(crate) struct Selection {
+    solver: Solver,
+    strategy: RandomStrategy,
+    pub(crate) ids: Vec<usize>,              // Group index -> group id
+    selectors: Vec<Lit>,                     // Group index -> selector
+    clauses: Vec<(Option<usize>, Vec<Lit>)>, // Hard (None) and group clauses
+    members: Vec<Vec<usize>>,                // Group index -> its clauses
+    occurrences: Vec<Vec<usize>>,            // Var -> clauses containing it
+    in_xor: Vec<bool>,                       // Var -> occurs in a hard XOR
+    pub(crate) deadline: Option<Instant>,    // Shrinking and growing give up after it
+}
+
+/// Extracts a minimal unsatisfiable subset of the groups of 'formula' (`groups`; its `clauses`
+/// and `xors` are hard and always included): an unsatisfiable set of groups that becomes
+/// satisfiable without any one of them. Returns the ids of its groups in ascending order
+/// (empty if the hard constraints are unsatisfiable on their own), or None if the formula
+/// is satisfiable.
+pub fn extract_mus(formula: &Formula) -> Option<Vec<usize>> {
+    let mut selection = Selection::new(formula);
+    let all: Vec<usize> = (0..selection.ids.len()).collect();
+    let core = selection.core_of(&all)?;
+    let mus = selection.shrink(core)?; // Never interrupted without a deadline
+    Some(mus.iter().map(|&g| selection.ids[g]).collect())
+}
+
+/// Checks that the groups 'ids' of 'formula', with its hard constraints, are a minimal
+/// unsatisfiable subset: unsatisfiable together, satisfiable without any one of them.
+pub fn is_mus(formula: &Formula, ids: &[usize]) -> bool {
+    let mut selection = Selection::new(formula);
+    let groups: Vec<usize> = (0..selection.ids.len()).filter(|&g| ids.contains(&selection.ids[g])).collect();
+    if groups.len() != ids.len() { return false; }
+    if selection.core_of(&groups).is_none() { return false; }
+    groups.iter().all(|&g| {
+        let rest: Vec<usize> = groups.iter().copied().filter(|&c| c != g).collect();
+        selection.core_of(&rest).is_none()
+    })
+}
+
+impl Selection {
+    pub(crate) fn new(formula: &Formula) -> Self {
+        let mut solver = Solver::new(formula.num_vars);
+        for clause in &formula.clauses {
+            solver.add_clause(clause.clone());
+        }
+        let mut in_xor = vec![false; formula.num_vars];
+        for xor in &formula.xors {
+            for &var in &xor.vars { in_xor[var] = true; }
+            solver.add_xor(xor.clone());
+        }
+
+        let mut groups: BTreeMap<usize, Vec<&Vec<Lit>>> = BTreeMap::new();
+        for (id, clause) in &formula.groups {
+            groups.entry(*id).or_default().push(clause);
+        }
+        let ids: Vec<usize> = groups.keys().copied().collect();
+        let mut clauses: Vec<(Option<usize>, Vec<Lit>)> = formula.clauses.iter().map(|c| (None, c.clone())).collect();
+        let mut members = Vec::with_capacity(ids.len());
+        let mut selectors = Vec::with_capacity(ids.len());
+        for (g, group) in groups.into_values().enumerate() {
+            let selector = Lit::new(solver.new_var(), false);
+            let mut member = Vec::with_capacity(group.len());
+            for clause in group {
+                let mut lits = clause.clone();
+                lits.push(selector.not());
+                solver.add_clause(lits);
+                member.push(clauses.len());
+                clauses.push((Some(g), clause.clone()));
+            }
+            members.push(member);
+            selectors.push(selector);
+        }
+        let mut occurrences = vec![Vec::new(); formula.num_vars];
+        for (c, (_, clause)) in clauses.iter().enumerate() {
+            for lit in clause { occurrences[lit.var()].push(c); }
+        }
+
+        let strategy = RandomStrategy::new(solver.num_vars);
+        Selection { solver, strategy, ids, selectors, clauses, members, occurrences, in_xor, deadline: None }
+    }
+
+    pub(crate) fn expired(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Solves with the 'groups' enabled. If they are unsatisfiable, returns the groups of the
+    /// core, ascending; None if they are satisfiable (see `model`). Only the first call
+    /// simplifies the formula.
+    pub(crate) fn core_of(&mut self, groups: &[usize]) -> Option<Vec<usize>> {
+        let assumptions: Vec<Lit> = groups.iter().map(|&g| self.selectors[g]).collect();
+        let sat = self.solver.solve_with_assumptions(&assumptions, &mut self.strategy, false);
+        if sat { return None; }
+        let core = self.solver.core();
+        Some(groups.iter().copied().filter(|&g| core.contains(&self.selectors[g])).collect())
+    }
+
+    /// The last model found, over the variables of the formula.
+    pub(crate) fn model(&self) -> Vec<bool> {
+        self.solver.model()[..self.occurrences.len()].to_vec()
+    }
+
+    /// Whether 'model' satisfies all clauses of group 'g'.
+    pub(crate) fn satisfies(&self, model: &[bool], g: usize) -> bool {
+        self.members[g].iter().all(|&c| satisfied(model, &self.clauses[c].1))
+    }
+
+    /// Adds a clause over the selectors: one of the 'groups' at least is enabled from now on.
+    pub(crate) fn require_one_of(&mut self, groups: &[usize]) {
+        self.solver.add_clause(groups.iter().map(|&g| self.selectors[g]).collect());
+    }
+
+    /// Shrinks the unsatisfiable 'candidates' to a minimal unsatisfiable subset, or None if the
+    /// deadline passes first.
+    ///
+    /// Deletion-based: every group left is tentatively removed. If the rest is unsatisfiable, the
+    /// candidates are refined to the groups of the core; otherwise the group is critical, and the
+    /// model is rotated: flipping a variable of the falsified group to falsify exactly one other
+    /// candidate group proves that group critical too, without another search.
+    pub(crate) fn shrink(&mut self, mut candidates: Vec<usize>) -> Option<Vec<usize>> {
+        let mut critical = vec![false; self.ids.len()];
+        let mut enabled = vec![false; self.ids.len()];
+        for &g in &candidates { enabled[g] = true; }
+
+        while let Some(&g) = candidates.iter().find(|&&g| !critical[g]) {
+            if self.expired() { return None; }
+            let rest: Vec<usize> = candidates.iter().copied().filter(|&c| c != g).collect();
+            match self.core_of(&rest) {
+                Some(core) => {
+                    // Critical groups belong to every unsatisfiable subset, so the core keeps them
+                    for &removed in candidates.iter().filter(|c| !core.contains(c)) { enabled[removed] = false; }
+                    candidates = core;
+                }
+                None => {
+                    let mut pending = vec![(g, self.model())];
+                    while let Some((g, model)) = pending.pop() {
+                        if critical[g] { continue; }
+                        critical[g] = true;
+                        pending.extend(self.rotations(g, &model, &enabled).filter(|(other, _)| !critical[*other]));
+                    }
+                }
+            }
+        }
+        Some(candidates)
+    }
+
+    /// Grows the satisfiable 'seed' to a maximal satisfiable subset, or None if the deadline
+    /// passes first. Every model found adds all the groups it satisfies.
+    pub(crate) fn grow(&mut self, seed: &[usize]) -> Option<Vec<usize>> {
+        assert!(self.core_of(seed).is_none(), "growing an unsatisfiable seed");
+        let mut included = vec![false; self.ids.len()];
+        for &g in seed { included[g] = true; }
+        self.include_satisfied(&mut included);
+        for g in 0..self.ids.len() {
+            if included[g] { continue; }
+            if self.expired() { return None; }
+            included[g] = true;
+            let subset: Vec<usize> = (0..self.ids.len()).filter(|&h| included[h]).collect();
+            match self.core_of(&subset) {
+                Some(_) => included[g] = false,
+                None => self.include_satisfied(&mut included),
+            }
+        }
+        Some((0..self.ids.len()).filter(|&g| included[g]).collect())
+    }
+
+    /// Adds the groups satisfied by the last model to 'included'.
+    fn include_satisfied(&self, included: &mut [bool]) {
+        let model = self.model();
+        for (g, included) in included.iter_mut().enumerate() {
+            if !*included && self.satisfies(&model, g) { *included = true; }
+        }
+    }
+
+    /// The models next to 'model' (which among the 'enabled' groups only falsifies group 'g')
+    /// obtained by flipping a variable of a falsified clause of 'g', and the single enabled group
+    /// each one falsifies instead. Flips that break a hard clause or an XOR, or falsify several
+    /// groups, are skipped.
+    fn rotations<'a>(&'a self, g: usize, model: &'a [bool], enabled: &'a [bool]) -> impl Iterator<Item = (usize, Vec<bool>)> + 'a {
+        let mut vars: Vec<usize> = self.members[g].iter()
+            .filter(|&&c| !satisfied(model, &self.clauses[c].1))
+            .flat_map(|&c| self.clauses[c].1.iter().map(|l| l.var()))
+            .filter(|&var| !self.in_xor[var])
+            .collect();
+        vars.sort_unstable();
+        vars.dedup();
+        vars.into_iter().filter_map(move |var| {
+            let mut flipped = model.to_vec();
+            flipped[var] = !flipped[var];
+            if !self.satisfies(&flipped, g) { return None; }
+            // Only the clauses of 'var' can have become falsified
+            let mut owner = None;
+            for &c in &self.occurrences[var] {
+                let (group, clause) = &self.clauses[c];
+                if group.is_some_and(|h| !enabled[h]) || satisfied(&flipped, clause) { continue; }
+                match (*group, owner) {
+                    (None, _) => return None,
+                    (Some(h), None) => owner = Some(h),
+                    (Some(h), Some(o)) if h != o => return None,
+                    _ => {}
+                }
+            }
+            owner.map(|h| (h, flipped))
+        })
+    }
+}
+
+fn satisfied(model: &[bool], clause: &[Lit]) -> bool {
+    clause.iter().any(|l| model[l.var()] != l.is_neg())
+}
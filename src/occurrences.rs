@@ -0,0 +1,95 @@
+use crate::{Clause, Lit, Solver};
+
+// =========================================================================
+// Clause Database with Occurrence Lists
+// =========================================================================
+
+/// The clauses under the root assignment (None = removed) with literal occurrence lists,
+/// shared by the simplification passes that resolve or compare clauses.
+/// The lists are cleaned lazily: 'live' counts the entries that still point to existing clauses.
+pub(crate) struct ClauseDatabase {
+    pub(crate) clauses: Vec<Option<Vec<Lit>>>,
+    pub(crate) learned: Vec<bool>,
+    pub(crate) occurs: Vec<Vec<usize>>,
+    pub(crate) live: Vec<usize>,
+}
+
+impl ClauseDatabase {
+    pub(crate) fn new(clauses: Vec<Clause>, num_vars: usize) -> Self {
+        let mut db = ClauseDatabase {
+            clauses: Vec::with_capacity(clauses.len()),
+            learned: Vec::with_capacity(clauses.len()),
+            occurs: vec![Vec::new(); num_vars * 2],
+            live: vec![0; num_vars * 2],
+        };
+        for clause in clauses {
+            db.add(clause.lits, clause.learned);
+        }
+        db
+    }
+
+    pub(crate) fn add(&mut self, lits: Vec<Lit>, learned: bool) -> usize {
+        let idx = self.clauses.len();
+        for &l in &lits {
+            self.occurs[l.to_usize()].push(idx);
+            self.live[l.to_usize()] += 1;
+        }
+        self.clauses.push(Some(lits));
+        self.learned.push(learned);
+        idx
+    }
+
+    pub(crate) fn remove(&mut self, idx: usize) -> Vec<Lit> {
+        let removed = self.clauses[idx].take().unwrap();
+        for &l in &removed {
+            self.live[l.to_usize()] -= 1;
+            if self.occurs[l.to_usize()].len() > 2 * self.live[l.to_usize()] {
+                let clauses = &self.clauses;
+                self.occurs[l.to_usize()].retain(|&i| clauses[i].is_some());
+            }
+        }
+        removed
+    }
+
+    /// Removes 'lit' from clause 'idx' (and the clause from the occurrences of 'lit').
+    pub(crate) fn strengthen(&mut self, idx: usize, lit: Lit) {
+        let lits = self.clauses[idx].as_mut().unwrap();
+        lits.retain(|&l| l != lit);
+        let list = &mut self.occurs[lit.to_usize()];
+        if let Some(pos) = list.iter().position(|&i| i == idx) { list.swap_remove(pos); }
+        self.live[lit.to_usize()] -= 1;
+    }
+
+    /// The existing clauses containing 'lit'.
+    pub(crate) fn occurrences(&self, lit: Lit) -> impl Iterator<Item = usize> + '_ {
+        self.occurs[lit.to_usize()].iter().copied().filter(|&i| self.clauses[i].is_some())
+    }
+
+    pub(crate) fn into_clauses(self) -> Vec<Clause> {
+        self.clauses.into_iter().zip(self.learned)
+            .filter_map(|(lits, learned)| lits.map(|lits| Clause { lits, learned }))
+            .collect()
+    }
+}
+
+impl Solver {
+    /// The clauses under the root assignment as a database for simplification passes.
+    pub(crate) fn clause_database(&self) -> ClauseDatabase {
+        ClauseDatabase::new(self.root_clauses(), self.num_vars)
+    }
+
+    /// Runs 'pass' on a database of the clauses under the root assignment and makes it the
+    /// clause database if the pass reports a change. Does nothing above decision level 0 or
+    /// if the formula is (or propagation proves it) UNSAT.
+    pub(crate) fn with_clause_database(&mut self, pass: impl FnOnce(&mut Solver, &mut ClauseDatabase) -> bool) {
+        if !self.ok || self.decision_level() != 0 { return; }
+        if self.propagate().is_some() {
+            self.ok = false;
+            return;
+        }
+        let mut db = self.clause_database();
+        if pass(self, &mut db) && self.ok {
+            self.replace_clause_database(db.into_clauses());
+        }
+    }
+}
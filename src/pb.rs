@@ -0,0 +1,264 @@
+use crate::preprocessing::apply_replacements;
+use crate::{BranchingStrategy, Lit, Reason, Solver, VarValue};
+
+// =========================================================================
+// Pseudo-Boolean Constraints: Watched Slack Propagation and Minimisation
+// =========================================================================
+
+/// The linear constraint "sum of coefficient · literal >= bound", where a true literal counts 1.
+/// Coefficients and bound may be negative (see `Solver::add_pb`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PbConstraint {
+    pub terms: Vec<(i64, Lit)>,
+    pub bound: i64,
+}
+
+/// A normalized constraint during search: one term per variable, positive coefficients
+/// capped at the (positive) bound and sorted in decreasing order.
+#[derive(Debug)]
+struct WatchedConstraint {
+    terms: Vec<(u64, Lit)>,
+    bound: u64,
+    total: u64,         // Sum of the coefficients
+    watched: Vec<bool>, // Term -> watched
+}
+
+/// The native PB constraints during search.
+///
+/// A constraint watches some of its terms, so that the coefficients of the watched terms that
+/// are not false exceed the bound by at least the largest coefficient (the watch slack).
+/// Then no term can be implied. When a watched term becomes false, the constraint watches more
+/// terms. If there are not enough, all non-false terms are watched, the watch slack is the real
+/// slack, and every unassigned term with a larger coefficient is implied (a negative slack is
+/// a conflict). Backtracking only increases the slack, so the watches never need an update.
+#[derive(Debug, Default)]
+pub(crate) struct PbPropagator {
+    constraints: Vec<WatchedConstraint>,
+    watches: Vec<Vec<(u32, u32)>>, // Literal -> (constraint, term) of the watched terms it falsifies
+}
+
+impl PbPropagator {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.constraints.len()
+    }
+}
+
+impl Solver {
+    /// Adds the constraint "sum of coefficient · literal >= bound" to the formula.
+    /// It is kept as a native constraint propagated with watched slack, or encoded into one clause
+    /// per minimal set of terms whose falsification violates it while a proof is written.
+    /// Must be called at decision level 0. Returns false if the formula became UNSAT.
+    pub fn add_pb(&mut self, terms: Vec<(i64, Lit)>, bound: i64) -> bool {
+        let lits: Vec<Lit> = terms.iter().map(|&(_, l)| l).collect();
+        if !self.restore_clauses(&lits) { return false; }
+        let constraint = PbConstraint { terms, bound };
+        if self.proof.is_none() { self.pbs.push(constraint.clone()); }
+        self.attach_pb(&constraint)
+    }
+
+    /// Rebuilds the propagator from the PB constraints of the formula for the current
+    /// substitution and root assignment. Must be called at decision level 0.
+    pub(crate) fn attach_pbs(&mut self) {
+        self.pb = PbPropagator::default();
+        for constraint in self.pbs.clone() {
+            if !self.attach_pb(&constraint) { return; }
+        }
+    }
+
+    /// Normalizes 'constraint' and attaches it, propagating the terms it implies at the root.
+    /// Sets `ok = false` if it cannot be satisfied.
+    fn attach_pb(&mut self, constraint: &PbConstraint) -> bool {
+        let (terms, bound) = self.normalize_pb(constraint);
+        if bound <= 0 { return true; }
+        let bound = bound as u64;
+        let total: u64 = terms.iter().map(|&(a, _)| a).sum();
+        if total < bound {
+            self.ok = false;
+            return false;
+        }
+        if self.proof.is_some() { return self.encode_pb(&terms, total - bound); }
+
+        let c = self.pb.constraints.len();
+        if self.pb.watches.len() < self.num_vars * 2 { self.pb.watches.resize(self.num_vars * 2, Vec::new()); }
+        let watched = vec![false; terms.len()];
+        self.pb.constraints.push(WatchedConstraint { terms, bound, total, watched });
+        let start = self.trail.len();
+        self.update_pb(c, None);
+        if self.trail.len() > start && self.propagate().is_some() { self.ok = false; }
+        self.ok
+    }
+
+    /// Maps 'constraint' onto representatives and the root assignment: one term per unassigned
+    /// variable with a positive coefficient (a·¬x = a - a·x), capped at the bound if that is positive.
+    /// The constraint is satisfied if the bound is not positive, unsatisfiable if the coefficients
+    /// sum to less.
+    fn normalize_pb(&self, constraint: &PbConstraint) -> (Vec<(u64, Lit)>, i64) {
+        let lits: Vec<Lit> = constraint.terms.iter().map(|&(_, l)| l).collect();
+        let mut bound = constraint.bound;
+        let mut by_var: Vec<(usize, i64)> = Vec::with_capacity(lits.len());
+        for (&(a, _), lit) in constraint.terms.iter().zip(apply_replacements(&lits, &self.replacements)) {
+            if lit.is_neg() {
+                by_var.push((lit.var(), -a));
+                bound -= a;
+            } else {
+                by_var.push((lit.var(), a));
+            }
+        }
+        by_var.sort_by_key(|&(var, _)| var);
+
+        let mut terms = Vec::with_capacity(by_var.len());
+        for group in by_var.chunk_by(|x, y| x.0 == y.0) {
+            let var = group[0].0;
+            let a: i64 = group.iter().map(|&(_, a)| a).sum();
+            match self.assignments[var] {
+                VarValue::True => bound -= a,
+                VarValue::False => {}
+                VarValue::Unassigned if a > 0 => terms.push((a as u64, Lit::new(var, false))),
+                VarValue::Unassigned if a < 0 => {
+                    bound -= a;
+                    terms.push((a.unsigned_abs(), Lit::new(var, true)));
+                }
+                VarValue::Unassigned => {}
+            }
+        }
+        if bound > 0 {
+            for term in &mut terms { term.0 = term.0.min(bound as u64); }
+        }
+        terms.sort_by_key(|&(a, _)| std::cmp::Reverse(a));
+        (terms, bound)
+    }
+
+    /// Adds the clauses of a normalized constraint: one per minimal set of terms whose
+    /// coefficients exceed 'slack' (they cannot all be false).
+    fn encode_pb(&mut self, terms: &[(u64, Lit)], slack: u64) -> bool {
+        fn recurse(terms: &[(u64, Lit)], slack: u64, from: usize, sum: u64, chosen: &mut Vec<Lit>, out: &mut Vec<Vec<Lit>>) {
+            for i in from..terms.len() {
+                let (a, lit) = terms[i];
+                chosen.push(lit);
+                if sum + a > slack {
+                    out.push(chosen.clone());
+                } else {
+                    recurse(terms, slack, i + 1, sum + a, chosen, out);
+                }
+                chosen.pop();
+            }
+        }
+        let mut clauses = Vec::new();
+        recurse(terms, slack, 0, 0, &mut Vec::new(), &mut clauses);
+        clauses.into_iter().all(|clause| self.add_clause_internal(clause, false))
+    }
+
+    /// Processes the PB constraints watching the literal falsified by the true literal 'p'.
+    /// Returns a violated constraint.
+    #[inline]
+    pub(crate) fn propagate_pbs(&mut self, p: Lit) -> Option<Reason> {
+        let mut watchers = match self.pb.watches.get_mut(p.to_usize()) {
+            Some(list) if !list.is_empty() => std::mem::take(list),
+            _ => return None,
+        };
+
+        let mut conflict = None;
+        let mut j = 0;
+        for i in 0..watchers.len() {
+            let (c, term) = watchers[i];
+            if conflict.is_none() {
+                let (keep, found) = self.update_pb(c as usize, Some(term as usize));
+                conflict = found;
+                if !keep { continue; }
+            }
+            watchers[j] = watchers[i];
+            j += 1;
+        }
+        watchers.truncate(j);
+        watchers.append(&mut self.pb.watches[p.to_usize()]);
+        self.pb.watches[p.to_usize()] = watchers;
+        conflict
+    }
+
+    /// Restores the watch slack of constraint 'c' after its watched term 'falsified' became
+    /// false (None when it is attached), or propagates the constraint.
+    /// Returns whether 'falsified' stays watched, and a conflict.
+    fn update_pb(&mut self, c: usize, falsified: Option<usize>) -> (bool, Option<Reason>) {
+        let pb = &mut self.pb;
+        let constraint = &mut pb.constraints[c];
+        let max = constraint.terms[0].0 as i64;
+        let is_false = |l: Lit| Self::value_lit(&self.assignments, l) == VarValue::False;
+
+        let mut slack = -(constraint.bound as i64);
+        for (i, &(a, l)) in constraint.terms.iter().enumerate() {
+            if constraint.watched[i] && !is_false(l) { slack += a as i64; }
+        }
+        // Watch further terms, largest first
+        for (i, &(a, l)) in constraint.terms.iter().enumerate() {
+            if slack >= max { break; }
+            if constraint.watched[i] || is_false(l) { continue; }
+            constraint.watched[i] = true;
+            pb.watches[l.not().to_usize()].push((c as u32, i as u32));
+            slack += a as i64;
+        }
+        if slack >= max {
+            if let Some(i) = falsified { constraint.watched[i] = false; }
+            return (false, None);
+        }
+
+        // Every non-false term is watched: 'slack' is exact
+        if slack < 0 {
+            let reason = self.pb_reason(c, None);
+            return (true, Some(self.explain(reason)));
+        }
+        for i in 0..self.pb.constraints[c].terms.len() {
+            let (a, lit) = self.pb.constraints[c].terms[i];
+            if a as i64 <= slack { break; }
+            if self.assignments[lit.var()] == VarValue::Unassigned {
+                let reason = self.pb_reason(c, Some((a, lit)));
+                let reason = self.explain(reason);
+                self.unchecked_enqueue(lit, Some(reason));
+            }
+        }
+        (true, None)
+    }
+
+    /// The reason clause of constraint 'c' for the implied term (first), or its conflict clause:
+    /// the false terms, largest first, until the others cannot reach the bound without the implied one.
+    fn pb_reason(&self, c: usize, implied: Option<(u64, Lit)>) -> Vec<Lit> {
+        let constraint = &self.pb.constraints[c];
+        let needed = constraint.bound + implied.map_or(0, |(a, _)| a);
+        let mut lits: Vec<Lit> = implied.map(|(_, l)| l).into_iter().collect();
+        let mut rest = constraint.total;
+        for &(a, l) in &constraint.terms {
+            if rest < needed { break; }
+            if Self::value_lit(&self.assignments, l) == VarValue::False {
+                lits.push(l);
+                rest -= a;
+            }
+        }
+        lits
+    }
+
+    /// Variables of the native PB constraints (after substitution).
+    pub(crate) fn pb_variables(&self) -> impl Iterator<Item = usize> + '_ {
+        self.pb.constraints.iter().flat_map(|c| c.terms.iter().map(|&(_, l)| l.var()))
+    }
+
+    /// Minimizes 'objective' (sum of coefficient · literal) by solving repeatedly, each time
+    /// requiring a smaller value than the last model. Returns the optimum, whose model `model`
+    /// holds, or None if the formula is unsatisfiable.
+    pub fn minimize(&mut self, objective: &[(i64, Lit)], strategy: &mut dyn BranchingStrategy, verbose: bool) -> Option<i64> {
+        let mut best = None;
+        while self.solve(strategy, verbose) {
+            let value: i64 = objective.iter()
+                .filter(|&&(_, l)| self.model[l.var()] != l.is_neg())
+                .map(|&(a, _)| a)
+                .sum();
+            if verbose { println!("Objective: {}", value); }
+            best = Some(value);
+            // objective <= value - 1
+            if !self.add_pb(objective.iter().map(|&(a, l)| (-a, l)).collect(), 1 - value) { break; }
+        }
+        best
+    }
+}
@@ -0,0 +1,387 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::occurrences::ClauseDatabase;
+use crate::preprocessing::apply_replacements;
+use crate::{BranchingStrategy, Solver, SolverOptions};
+
+// =========================================================================
+// Preprocessing Pipeline
+// =========================================================================
+
+/// Rounds of a repeated group (`[..]`) without an explicit count.
+const DEFAULT_ROUNDS: usize = 8;
+
+/// What one run of a pass did.
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    /// Whether the formula changed (repeated groups stop after a round without changes).
+    pub changed: bool,
+    /// One line of statistics, printed in verbose mode.
+    pub summary: String,
+}
+
+impl PassReport {
+    /// The report of a pass that did nothing, e.g. because the formula is already UNSAT.
+    pub fn unchanged() -> Self {
+        PassReport { changed: false, summary: String::new() }
+    }
+}
+
+/// A simplification pass on the clauses of a solver at decision level 0.
+///
+/// Passes work on the clauses under the root assignment, push the clauses they remove in a
+/// model-changing way onto the solver's reconstruction stack and clear the solver's `ok` flag
+/// when they derive a conflict.
+pub trait Preprocessor {
+    /// The name used in pipeline configurations.
+    fn name(&self) -> &'static str;
+    /// Whether the pass runs under the given options (e.g. disabled by its switch).
+    fn enabled(&self, _options: &SolverOptions) -> bool { true }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport;
+}
+
+/// What the passes of a pipeline run work on: the solver and its branching strategy, and a
+/// clause database with occurrence lists. The passes that resolve or compare clauses (`sub`,
+/// `bve`, `bce`) share the database, so a group of them builds it only once; it is written back
+/// into the solver before any other pass and at the end of the run.
+pub struct PassContext<'a> {
+    solver: &'a mut Solver,
+    strategy: &'a mut dyn BranchingStrategy,
+    database: Option<ClauseDatabase>,
+    changed: bool, // The database has changes the solver's clauses lack
+}
+
+impl<'a> PassContext<'a> {
+    /// The solver, with the changes made on the shared database written back.
+    pub fn solver(&mut self) -> &mut Solver {
+        self.write_back();
+        self.solver
+    }
+
+    /// Like `solver`, together with the branching strategy (e.g. for passes that propagate).
+    pub fn solver_and_strategy(&mut self) -> (&mut Solver, &mut dyn BranchingStrategy) {
+        self.write_back();
+        (self.solver, self.strategy)
+    }
+
+    pub fn options(&self) -> &SolverOptions {
+        &self.solver.options
+    }
+
+    /// The shared database, built from the clauses under the root assignment on first use, and
+    /// the solver, whose clauses must not be used until it is written back.
+    /// None if propagation at the root fails (the solver's `ok` flag is cleared).
+    pub(crate) fn database(&mut self) -> Option<(&mut Solver, &mut ClauseDatabase)> {
+        if self.database.is_none() {
+            if !self.solver.ok { return None; }
+            if self.solver.propagate().is_some() {
+                self.solver.ok = false;
+                return None;
+            }
+            self.database = Some(self.solver.clause_database());
+        }
+        Some((&mut *self.solver, self.database.as_mut().unwrap()))
+    }
+
+    fn write_back(&mut self) {
+        if let Some(db) = self.database.take() && self.changed && self.solver.ok {
+            self.solver.replace_clause_database(db.into_clauses());
+        }
+        self.changed = false;
+    }
+}
+
+/// Failed literal probing (`probe`).
+pub struct Probing;
+/// Blocked binary clause addition (`bca`).
+pub struct BlockedClauseAddition;
+/// Equivalent literal substitution (`els`).
+pub struct EquivalentLiterals;
+/// XOR extraction with Gaussian elimination (`gauss`).
+pub struct GaussianElimination;
+/// Blocked (and covered) clause elimination (`bce`).
+pub struct BlockedClauseElimination;
+/// Subsumption and self-subsuming resolution (`sub`).
+pub struct Subsumption;
+/// Bounded variable elimination (`bve`).
+pub struct VariableElimination;
+/// Cardinality constraint detection (`card`).
+pub struct CardinalityDetection;
+
+impl Preprocessor for Probing {
+    fn name(&self) -> &'static str { "probe" }
+    fn enabled(&self, options: &SolverOptions) -> bool { options.probing }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport {
+        let (solver, strategy) = context.solver_and_strategy();
+        let stats = solver.probe(strategy);
+        PassReport {
+            changed: stats.failed + stats.lifted + stats.hyper_binaries > 0,
+            summary: format!("Probing: {} probes, {} failed literals, {} lifted units, {} hyper-binary resolvents",
+                stats.probed, stats.failed, stats.lifted, stats.hyper_binaries),
+        }
+    }
+}
+
+impl Preprocessor for BlockedClauseAddition {
+    fn name(&self) -> &'static str { "bca" }
+    fn enabled(&self, options: &SolverOptions) -> bool { options.blocked_clauses && options.equivalences }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport {
+        // The added binaries are not implied, so they must not meet learned clauses, later
+        // clauses or assumptions: after the preprocessing of the first `solve` the pass does nothing
+        let solver = context.solver();
+        if solver.preprocessed { return PassReport::unchanged(); }
+        let added = solver.add_blocked_binaries();
+        PassReport { changed: added > 0, summary: format!("Blocked clause addition: {} binary clauses added", added) }
+    }
+}
+
+impl Preprocessor for EquivalentLiterals {
+    fn name(&self) -> &'static str { "els" }
+    fn enabled(&self, options: &SolverOptions) -> bool { options.equivalences }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport {
+        let substituted = context.solver().substitute_equivalent_literals();
+        PassReport { changed: substituted > 0, summary: format!("Equivalent literals: {} variables substituted", substituted) }
+    }
+}
+
+impl Preprocessor for GaussianElimination {
+    fn name(&self) -> &'static str { "gauss" }
+    fn enabled(&self, options: &SolverOptions) -> bool { options.gauss }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport {
+        let stats = context.solver().gaussian_elimination();
+        PassReport {
+            changed: stats.units + stats.substituted + stats.removed > 0,
+            summary: format!("Gaussian elimination: {} XORs, {} units, {} variables substituted, {} XOR clauses replaced, {} auxiliary variables",
+                stats.xors, stats.units, stats.substituted, stats.removed, stats.aux_vars),
+        }
+    }
+}
+
+impl Preprocessor for BlockedClauseElimination {
+    fn name(&self) -> &'static str { "bce" }
+    fn enabled(&self, options: &SolverOptions) -> bool { options.blocked_clauses }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport {
+        let Some((solver, db)) = context.database() else { return PassReport::unchanged() };
+        let stats = solver.eliminate_blocked_clauses_in(db, solver.options.covered_clauses);
+        PassReport {
+            changed: stats.blocked + stats.covered > 0,
+            summary: format!("Blocked clause elimination: {} blocked, {} covered clauses removed", stats.blocked, stats.covered),
+        }
+    }
+}
+
+impl Preprocessor for Subsumption {
+    fn name(&self) -> &'static str { "sub" }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport {
+        let Some((solver, db)) = context.database() else { return PassReport::unchanged() };
+        let stats = solver.subsume_in(db);
+        PassReport {
+            changed: stats.subsumed + stats.strengthened > 0,
+            summary: format!("Subsumption: {} clauses subsumed, {} literals strengthened", stats.subsumed, stats.strengthened),
+        }
+    }
+}
+
+impl Preprocessor for VariableElimination {
+    fn name(&self) -> &'static str { "bve" }
+    fn enabled(&self, options: &SolverOptions) -> bool { options.elimination }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport {
+        let Some((solver, db)) = context.database() else { return PassReport::unchanged() };
+        let stats = solver.eliminate_variables_in(db);
+        PassReport {
+            changed: stats.eliminated > 0,
+            summary: format!("Variable elimination: {} variables eliminated, {} resolvents added", stats.eliminated, stats.resolvents),
+        }
+    }
+}
+
+impl Preprocessor for CardinalityDetection {
+    fn name(&self) -> &'static str { "card" }
+    fn enabled(&self, options: &SolverOptions) -> bool { options.cardinality }
+    fn run(&mut self, context: &mut PassContext<'_>) -> PassReport {
+        let (solver, strategy) = context.solver_and_strategy();
+        let stats = solver.detect_cardinality_constraints(strategy);
+        PassReport {
+            changed: stats.at_most_one + stats.at_most_k > 0,
+            summary: format!("Cardinality detection: {} at-most-one, {} at-most-k constraints, {} clauses replaced",
+                stats.at_most_one, stats.at_most_k, stats.removed),
+        }
+    }
+}
+
+/// The built-in pass with the given configuration name.
+pub fn builtin_pass(name: &str) -> Option<Box<dyn Preprocessor>> {
+    let pass: Box<dyn Preprocessor> = match name {
+        "probe" => Box::new(Probing),
+        "bca" => Box::new(BlockedClauseAddition),
+        "els" => Box::new(EquivalentLiterals),
+        "gauss" => Box::new(GaussianElimination),
+        "bce" => Box::new(BlockedClauseElimination),
+        "sub" => Box::new(Subsumption),
+        "bve" => Box::new(VariableElimination),
+        "card" => Box::new(CardinalityDetection),
+        _ => return None,
+    };
+    Some(pass)
+}
+
+enum Step {
+    Pass(Box<dyn Preprocessor>),
+    Repeat(Vec<Step>, usize), // Steps and maximum number of rounds
+}
+
+/// An ordered list of passes, some of them grouped and repeated until nothing changes.
+///
+/// Configurations are comma-separated pass names, e.g. "sub,bve,probe,gauss".
+/// A group in brackets is repeated until a round changes nothing: "[sub,bve]*4" runs at most
+/// four rounds (`DEFAULT_ROUNDS` without a count). Groups may be nested.
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Step>,
+}
+
+/// A pipeline configuration that is known to parse, e.g. `"[sub,bve]*3,probe".parse()`, so an
+/// invalid one is rejected where it is set instead of when `solve` runs it.
+/// The default configuration has no passes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineConfig(String);
+
+impl PipelineConfig {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for PipelineConfig {
+    type Err = String;
+
+    fn from_str(config: &str) -> Result<Self, String> {
+        Pipeline::parse(config)?;
+        Ok(PipelineConfig(config.to_string()))
+    }
+}
+
+impl fmt::Display for PipelineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self { Pipeline::default() }
+
+    /// The pipeline of a configuration that was validated when it was parsed.
+    pub fn from_config(config: &PipelineConfig) -> Self {
+        Self::parse(config.as_str()).expect("pipeline configurations are validated by parsing")
+    }
+
+    /// Parses a configuration string with the built-in pass names.
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let chars: Vec<char> = config.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut pos = 0;
+        let steps = parse_steps(&chars, &mut pos)?;
+        if pos < chars.len() {
+            return Err(format!("unexpected '{}' at position {}", chars[pos], pos));
+        }
+        Ok(Pipeline { steps })
+    }
+
+    /// Appends a pass (e.g. a custom one) at the end of the pipeline.
+    pub fn push(&mut self, pass: Box<dyn Preprocessor>) {
+        self.steps.push(Step::Pass(pass));
+    }
+
+    /// Runs all passes in order. Returns false if the formula was found unsatisfiable.
+    /// Drops the native XOR matrix since passes may substitute its variables
+    /// (`solve` attaches it again). Must be called at decision level 0.
+    pub fn run(&mut self, solver: &mut Solver, strategy: &mut dyn BranchingStrategy, verbose: bool) -> bool {
+        if !solver.ok || solver.decision_level() != 0 { return solver.ok; }
+        solver.gauss = None;
+        let mut context = PassContext { solver, strategy, database: None, changed: false };
+        run_steps(&mut self.steps, &mut context, verbose);
+        context.solver().ok
+    }
+}
+
+/// Runs the steps once, returns whether any pass changed the formula.
+fn run_steps(steps: &mut [Step], context: &mut PassContext<'_>, verbose: bool) -> bool {
+    let mut changed = false;
+    for step in steps {
+        if !context.solver.ok { break; }
+        match step {
+            Step::Pass(pass) => {
+                if !pass.enabled(context.options()) { continue; }
+                let report = pass.run(context);
+                if verbose && !report.summary.is_empty() { println!("{}", report.summary); }
+                changed |= report.changed;
+                context.changed |= report.changed && context.database.is_some();
+            }
+            Step::Repeat(group, rounds) => {
+                for _ in 0..*rounds {
+                    if !run_steps(group, context, verbose) { break; }
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+impl Solver {
+    /// Whether the conflicts since the last inprocessing reached the schedule of
+    /// `options.inprocess_interval` (the same as the one of rephasing).
+    pub(crate) fn inprocessing_due(&self) -> bool {
+        let k = self.inprocessings + 1;
+        !self.options.inprocessing.as_str().is_empty()
+            && self.conflicts >= self.options.inprocess_interval.saturating_mul(k * (k + 1) / 2)
+    }
+
+    /// Restarts and runs the `options.inprocessing` pipeline at level 0, then attaches the
+    /// native XORs again and maps the assumptions onto new representatives.
+    /// Called only once propagation is complete. Returns false if the formula became UNSAT.
+    pub(crate) fn inprocess(&mut self, strategy: &mut dyn BranchingStrategy, verbose: bool) -> bool {
+        self.backtrack(0, strategy);
+        self.inprocessings += 1;
+        if verbose { println!("Inprocessing after {} conflicts", self.conflicts); }
+        let mut pipeline = Pipeline::from_config(&self.options.inprocessing);
+        if !pipeline.run(self, strategy, verbose) { return false; }
+        self.attach_xors();
+        self.assumptions = apply_replacements(&self.assumptions, &self.replacements);
+        self.ok
+    }
+}
+
+/// Parses a comma-separated list of pass names and groups up to a closing bracket or the end.
+fn parse_steps(chars: &[char], pos: &mut usize) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    loop {
+        if chars.get(*pos) == Some(&'[') {
+            *pos += 1;
+            let group = parse_steps(chars, pos)?;
+            if chars.get(*pos) != Some(&']') { return Err(format!("missing ']' at position {}", pos)); }
+            *pos += 1;
+            let mut rounds = DEFAULT_ROUNDS;
+            if chars.get(*pos) == Some(&'*') {
+                *pos += 1;
+                let start = *pos;
+                while chars.get(*pos).is_some_and(|c| c.is_ascii_digit()) { *pos += 1; }
+                let digits: String = chars[start..*pos].iter().collect();
+                rounds = digits.parse().map_err(|_| format!("missing repeat count at position {}", start))?;
+            }
+            steps.push(Step::Repeat(group, rounds));
+        } else {
+            let start = *pos;
+            while chars.get(*pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_') { *pos += 1; }
+            let name: String = chars[start..*pos].iter().collect();
+            if name.is_empty() {
+                // An empty configuration means no passes
+                if steps.is_empty() && start == chars.len() { break; }
+                return Err(format!("missing pass name at position {}", start));
+            }
+            steps.push(Step::Pass(builtin_pass(&name).ok_or_else(|| format!("unknown pass '{}'", name))?));
+        }
+        if chars.get(*pos) != Some(&',') { break; }
+        *pos += 1;
+    }
+    Ok(steps)
+}
@@ -1,5 +1,7 @@
 use std::collections::{HashMap, HashSet};
-use crate::{Clause, Lit};
+use crate::gates::{find_gates, GateKind};
+use crate::xor_finder::find_xors;
+use crate::{Clause, Lit, Solver};
 
 // =========================================================================
 // XOR Extraction and Gaussian Elimination
@@ -12,27 +14,57 @@ pub struct XorConstraint {
     pub rhs: bool, // true means parity is 1 (odd number of variables are true)
 }
 
+/// The reduced rows replace the defining clauses only if they have at most this many times
+/// as many variable occurrences as the extracted XORs.
+const MAX_FILL_IN: usize = 2;
+
 pub struct PreprocessResult {
     pub clauses: Vec<Clause>,
     pub units: Vec<Lit>,
+    /// Variables fixed by 2-variable rows: var -> (var, invert), already applied to 'clauses'.
+    pub replacements: HashMap<usize, (usize, bool)>,
+    /// The XOR defining clauses that were replaced by the reduced rows.
+    pub removed: Vec<Clause>,
+    /// The extracted XOR constraints (over the variables before substitution).
+    pub xors: Vec<XorConstraint>,
+    /// Number of fresh variables taken from 'new_var' to cut long rows.
+    pub aux_vars: usize,
+    /// The long rows and the pieces they were cut into (for proofs).
+    pub cuts: Vec<(XorConstraint, Vec<XorConstraint>)>,
+}
+
+impl PreprocessResult {
+    /// A result containing only the empty clause, derived from 'xors'.
+    fn unsat(xors: Vec<XorConstraint>) -> Self {
+        PreprocessResult {
+            clauses: vec![Clause { lits: vec![], learned: false }],
+            units: vec![],
+            replacements: HashMap::new(),
+            removed: Vec::new(),
+            xors,
+            aux_vars: 0,
+            cuts: vec![],
+        }
+    }
 }
 
+/// A row of a GF(2) matrix: one bit per column plus the right-hand side.
 #[derive(Clone, Debug)]
-struct BitRow {
+pub struct BitRow {
     chunks: Vec<u64>,
-    rhs: bool,
+    pub rhs: bool,
 }
 
 impl BitRow {
-    fn new(size: usize, rhs: bool) -> Self {
-        let num_chunks = (size + 63) / 64;
+    pub fn new(size: usize, rhs: bool) -> Self {
+        let num_chunks = size.div_ceil(64);
         BitRow {
             chunks: vec![0; num_chunks],
             rhs,
         }
     }
 
-    fn set_bit(&mut self, idx: usize) {
+    pub fn set_bit(&mut self, idx: usize) {
         let chunk = idx / 64;
         let offset = idx % 64;
         if chunk < self.chunks.len() {
@@ -40,7 +72,7 @@ impl BitRow {
         }
     }
 
-    fn get_bit(&self, idx: usize) -> bool {
+    pub fn get_bit(&self, idx: usize) -> bool {
         let chunk = idx / 64;
         let offset = idx % 64;
         if chunk < self.chunks.len() {
@@ -50,308 +82,270 @@ impl BitRow {
         }
     }
 
-    fn xor_with(&mut self, other: &BitRow) {
+    pub fn xor_with(&mut self, other: &BitRow) {
         for (c1, c2) in self.chunks.iter_mut().zip(&other.chunks) {
             *c1 ^= *c2;
         }
         self.rhs ^= other.rhs;
     }
 
-    fn is_zero_row(&self) -> bool {
-        self.chunks.iter().all(|&c| c == 0)
+    /// Indices of the set bits in increasing order.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.chunks.iter().enumerate().flat_map(|(i, &chunk)| {
+            let mut bits = chunk;
+            std::iter::from_fn(move || {
+                if bits == 0 { return None; }
+                let bit = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(i * 64 + bit)
+            })
+        })
     }
 }
 
-pub fn preprocess(clauses: &Vec<Clause>, num_vars: usize) -> Option<PreprocessResult> {
-    // 1. Identify XORs (Semantic Extraction)
-    // We switched from syntactic counting to semantic truth-table checks
-    // to handle hidden XORs (e.g. formed by AND/NAND gates).
-    let (xors, indices_to_remove) = extract_semantic_xors(clauses, num_vars);
+/// Splits the XORs into connected components (XORs sharing a variable) and builds one dense
+/// matrix per component: the variable of each column and the rows over those columns.
+/// Independent parity systems thereby never pay for each other's columns.
+pub fn xor_blocks(xors: &[XorConstraint], num_vars: usize) -> Vec<(Vec<usize>, Vec<BitRow>)> {
+    fn find(parent: &mut [usize], mut v: usize) -> usize {
+        while parent[v] != v {
+            parent[v] = parent[parent[v]];
+            v = parent[v];
+        }
+        v
+    }
+    let mut parent: Vec<usize> = (0..num_vars).collect();
+    for xor in xors {
+        let Some((&first, rest)) = xor.vars.split_first() else { continue };
+        let root = find(&mut parent, first);
+        for &v in rest {
+            let other = find(&mut parent, v);
+            parent[other] = root;
+        }
+    }
 
-    if xors.is_empty() {
-        return None;
+    let mut block_of: HashMap<usize, usize> = HashMap::new();
+    let mut members: Vec<Vec<&XorConstraint>> = Vec::new();
+    for xor in xors.iter().filter(|x| !x.vars.is_empty()) {
+        let root = find(&mut parent, xor.vars[0]);
+        let b = *block_of.entry(root).or_insert_with(|| {
+            members.push(Vec::new());
+            members.len() - 1
+        });
+        members[b].push(xor);
     }
 
-    // 2. Build Matrix
-    let mut matrix: Vec<BitRow> = Vec::with_capacity(xors.len());
-    for xor in xors.iter() {
-        let mut row = BitRow::new(num_vars, xor.rhs);
-        for &v in &xor.vars {
-            row.set_bit(v);
+    members.into_iter().map(|block| {
+        let mut columns: Vec<usize> = block.iter().flat_map(|x| x.vars.iter().copied()).collect();
+        columns.sort_unstable();
+        columns.dedup();
+        let rows = block.iter().map(|x| {
+            let mut row = BitRow::new(columns.len(), x.rhs);
+            for v in &x.vars { row.set_bit(columns.binary_search(v).unwrap()); }
+            row
+        }).collect();
+        (columns, rows)
+    }).collect()
+}
+
+/// Gauss-Jordan elimination. Returns the pivot columns in increasing order: afterwards row i
+/// has pivot column i of the result, which occurs in no other row, and the remaining rows are zero.
+pub(crate) fn gauss_jordan(rows: &mut [BitRow], num_columns: usize) -> Vec<usize> {
+    let mut pivots = Vec::new();
+    for col in 0..num_columns {
+        if pivots.len() >= rows.len() { break; }
+        let pivot_row = pivots.len();
+        let Some(r) = (pivot_row..rows.len()).find(|&r| rows[r].get_bit(col)) else { continue };
+        rows.swap(pivot_row, r);
+        let pivot = rows[pivot_row].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i != pivot_row && row.get_bit(col) { row.xor_with(&pivot); }
         }
-        matrix.push(row);
+        pivots.push(col);
     }
+    pivots
+}
 
-    // 3. Gaussian Elimination
-    let mut pivot_row_idx = 0;
-    for col in 0..num_vars {
-        if pivot_row_idx >= matrix.len() { break; }
+/// Extracts XOR constraints, reduces them with Gauss-Jordan elimination and rewrites the formula:
+/// 1-variable rows become units, 2-variable rows substitutions, and the other rows replace the
+/// clauses that defined the XORs. Rows with more than 'cut_size' variables are cut into pieces
+/// linked by auxiliary variables taken from 'new_var'. Returns None if no XORs were found, or if
+/// they all come from exactly-one style constraints and reduce to no units or equivalences.
+pub fn preprocess(
+    clauses: &[Clause],
+    num_vars: usize,
+    max_xor_size: usize,
+    cut_size: usize,
+    new_var: &mut dyn FnMut() -> usize,
+) -> Option<PreprocessResult> {
+    // 1. Identify XORs: syntactically (full sign patterns, up to 'max_xor_size' variables)
+    // and as XOR gates recognized semantically, which also covers non-standard encodings.
+    let mut xors = Vec::new();
+    let mut indices_to_remove = Vec::new();
+    let mut seen: HashSet<(Vec<usize>, bool)> = HashSet::new();
+    // An XOR with a single clause over all its variables is implied by the shorter ones, as in
+    // exactly-one constraints: eliminating such XORs alone only adds clauses.
+    let mut parity = false;
+    for found in find_xors(clauses, num_vars, max_xor_size) {
+        seen.insert((found.xor.vars.clone(), found.xor.rhs));
+        parity |= found.defining.len() > 1;
+        indices_to_remove.extend(found.defining);
+        xors.push(found.xor);
+    }
+    for gate in find_gates(clauses, num_vars).into_iter().filter(|g| g.kind == GateKind::Xor) {
+        // output = l1 ^ .. ^ ln, where a negated input flips the parity
+        let mut vars: Vec<usize> = gate.inputs.iter().map(|l| l.var()).chain([gate.output]).collect();
+        vars.sort_unstable();
+        let rhs = gate.inputs.iter().filter(|l| l.is_neg()).count() % 2 == 1;
+        if seen.insert((vars.clone(), rhs)) { xors.push(XorConstraint { vars, rhs }); }
+        indices_to_remove.extend(gate.clauses);
+        parity = true;
+    }
 
-        let mut pivot_found = None;
-        for r in pivot_row_idx..matrix.len() {
-            if matrix[r].get_bit(col) {
-                pivot_found = Some(r);
-                break;
-            }
-        }
+    if xors.is_empty() {
+        return None;
+    }
 
-        if let Some(r) = pivot_found {
-            matrix.swap(pivot_row_idx, r);
-            for i in 0..matrix.len() {
-                if i != pivot_row_idx && matrix[i].get_bit(col) {
-                    let (left, right) = matrix.split_at_mut(if i < pivot_row_idx { pivot_row_idx } else { i });
-                    if i < pivot_row_idx {
-                        left[i].xor_with(&right[0]);
-                    } else {
-                        right[0].xor_with(&left[pivot_row_idx]);
-                    }
-                }
-            }
-            pivot_row_idx += 1;
-        }
+    // 2. Build one dense matrix per connected component of the XORs
+    let mut reduced: Vec<(Vec<usize>, bool)> = Vec::new();
+    for (columns, mut rows) in xor_blocks(&xors, num_vars) {
+        // 3. Gaussian Elimination
+        let rank = gauss_jordan(&mut rows, columns.len()).len();
+        if rows[rank..].iter().any(|r| r.rhs) { return Some(PreprocessResult::unsat(xors)); } // 0 = 1
+        reduced.extend(rows[..rank].iter().map(|row| (row.ones().map(|c| columns[c]).collect(), row.rhs)));
+    }
+    // Without parity structure, only units and equivalences are worth changing the clauses
+    if !parity && reduced.iter().all(|(vars, _)| vars.len() > 2) {
+        return None;
     }
 
     // 4. Extract results
-    let mut new_xor_clauses = Vec::new();
+    let mut row_clauses = Vec::new();
     let mut units = Vec::new();
     let mut replacements: HashMap<usize, (usize, bool)> = HashMap::new();
-    let mut found_conflict = false;
+    let mut aux_vars = 0;
+    let mut cuts = Vec::new();
 
-    for row in matrix {
-        if row.is_zero_row() {
-            if row.rhs { found_conflict = true; break; }
-            continue;
-        }
-
-        let mut vars = Vec::new();
-        for (i, &chunk) in row.chunks.iter().enumerate() {
-            if chunk == 0 { continue; }
-            for bit in 0..64 {
-                if (chunk & (1 << bit)) != 0 {
-                    vars.push(i * 64 + bit);
-                }
-            }
-        }
-        
-        if vars.is_empty() { continue; }
+    // Fill-in can make the reduced rows much longer than the XORs they came from.
+    // Their units and equivalences are still valid, but the defining clauses stay.
+    let extracted_size: usize = xors.iter().map(|x| x.vars.len()).sum();
+    let reduced_size: usize = reduced.iter().map(|(vars, _)| vars.len()).sum();
+    let keep_definitions = reduced_size > MAX_FILL_IN * extracted_size;
 
+    for (vars, rhs) in reduced {
         if vars.len() == 1 {
-            units.push(Lit::new(vars[0], !row.rhs));
+            units.push(Lit::new(vars[0], !rhs));
         } else if vars.len() == 2 {
-            let v1 = vars[0];
-            let v2 = vars[1];
-            let (target, source) = if v1 > v2 { (v1, v2) } else { (v2, v1) };
-            replacements.insert(target, (source, row.rhs));
-        } else if vars.len() > 12 { 
-             // Avoid huge fill-in clauses, abort simplification if too messy
-             return None; 
-        } else {
-            new_xor_clauses.extend(xor_to_cnf(&vars, row.rhs));
+            if !add_equivalence(&mut replacements, vars[0], vars[1], rhs) {
+                return Some(PreprocessResult::unsat(xors));
+            }
+        } else if !keep_definitions {
+            // Long rows are cut into a chain of short XORs linked by fresh variables
+            let mut counted_new_var = || { aux_vars += 1; new_var() };
+            let pieces = cut_xor(&vars, rhs, cut_size, &mut counted_new_var);
+            for piece in &pieces {
+                row_clauses.extend(xor_to_cnf(&piece.vars, piece.rhs));
+            }
+            if pieces.len() > 1 { cuts.push((XorConstraint { vars, rhs }, pieces)); }
         }
     }
 
-    if found_conflict {
-        return Some(PreprocessResult { 
-            clauses: vec![Clause { lits: vec![], learned: false }], 
-            units: vec![] 
-        }); 
-    }
-
     // 5. Rebuild Clauses
-    // Remove clauses that were part of the identified semantic XORs
-    let indices_set: HashSet<usize> = indices_to_remove.into_iter().collect();
+    // Remove clauses that were part of the identified semantic XORs (they are implied by the rows)
+    let indices_set: HashSet<usize> = if keep_definitions { HashSet::new() } else { indices_to_remove.into_iter().collect() };
+    let removed: Vec<Clause> = clauses.iter().enumerate().filter(|(i, _)| indices_set.contains(i)).map(|(_, c)| c.clone()).collect();
     let mut final_clauses = Vec::with_capacity(clauses.len());
 
-    for (i, c) in clauses.iter().enumerate() {
-        if !indices_set.contains(&i) {
-            let mut new_lits = apply_replacements(&c.lits, &replacements);
-            if simplify_clause(&mut new_lits) { continue; }
-            if new_lits.is_empty() { 
-                return Some(PreprocessResult { clauses: vec![Clause { lits: vec![], learned: false }], units: vec![] }); 
-            }
-            final_clauses.push(Clause { lits: new_lits, learned: c.learned });
-        }
-    }
-
-    for c in new_xor_clauses {
+    let kept = clauses.iter().enumerate()
+        .filter(|(i, _)| !indices_set.contains(i))
+        .map(|(_, c)| c.clone());
+    for c in kept.chain(row_clauses) {
         let mut new_lits = apply_replacements(&c.lits, &replacements);
         if simplify_clause(&mut new_lits) { continue; }
-        if new_lits.is_empty() { 
-             return Some(PreprocessResult { clauses: vec![Clause { lits: vec![], learned: false }], units: vec![] }); 
-        }
-        final_clauses.push(Clause { lits: new_lits, learned: false });
+        if new_lits.is_empty() { return Some(PreprocessResult::unsat(xors)); }
+        final_clauses.push(Clause { lits: new_lits, learned: c.learned });
     }
 
-    // Always return result if we found replacements, even if clause count is similar
-    if final_clauses.len() >= clauses.len() && units.is_empty() && replacements.is_empty() {
-        return None;
-    }
-
-    Some(PreprocessResult { clauses: final_clauses, units })
+    Some(PreprocessResult { clauses: final_clauses, units, replacements, removed, xors, aux_vars, cuts })
 }
 
-/// Extracts XOR gates by checking variable relationships semantically via truth tables.
-/// This detects XORs even if they are hidden in AND/NAND structures.
-fn extract_semantic_xors(clauses: &Vec<Clause>, num_vars: usize) -> (Vec<XorConstraint>, Vec<usize>) {
-    let mut xors = Vec::new();
-    let mut used_clauses = HashSet::new();
-
-    // Build an adjacency list: Variable -> List of Clause Indices
-    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); num_vars];
-    for (i, c) in clauses.iter().enumerate() {
-        // Skip huge clauses for performance, likely not part of small local gates
-        if c.lits.len() > 6 { continue; } 
-        for lit in &c.lits {
-            adj[lit.var()].push(i);
-        }
-    }
-
-    // Iterate over every variable, treating it as a potential "output" of a gate
-    for var in 0..num_vars {
-        let clauses_indices = &adj[var];
-        if clauses_indices.is_empty() { continue; }
-
-        // Collect neighbors (other variables in the same clauses)
-        let mut neighbors = HashSet::new();
-        for &c_idx in clauses_indices {
-            for lit in &clauses[c_idx].lits {
-                if lit.var() != var {
-                    neighbors.insert(lit.var());
-                }
-            }
-        }
-
-        // Filter: We only look for small gates (e.g. 2 to 4 inputs)
-        // A standard 2-bit XOR has 2 inputs. 
-        if neighbors.len() < 2 || neighbors.len() > 4 { continue; }
-
-        let neighbor_vec: Vec<usize> = neighbors.into_iter().collect();
-
-        // Perform semantic check: Does 'var' behave like XOR(neighbors)?
-        if let Some(rhs) = check_semantic_xor_logic(var, &neighbor_vec, clauses_indices, clauses) {
-            
-            // Found a valid XOR constraint
-            let mut xor_vars = neighbor_vec.clone();
-            xor_vars.push(var); // The full constraint is inputs ^ output = const
-            xors.push(XorConstraint { vars: xor_vars, rhs });
-
-            // Mark involved clauses for removal.
-            // We assume if the clauses fully define the XOR, they are redundant 
-            // once we have the algebraic definition.
-            for &idx in clauses_indices {
-                let c = &clauses[idx];
-                // Only remove clauses that are STRICTLY composed of these vars.
-                // If a clause has other vars, it constrains something else too.
-                let all_in_xor = c.lits.iter().all(|l| l.var() == var || neighbor_vec.contains(&l.var()));
-                if all_in_xor {
-                    used_clauses.insert(idx);
-                }
-            }
-        }
-    }
+/// Records the row 'a ^ b = parity' as a substitution of the larger variable.
+/// Both sides are resolved through the existing entries first: two rows can share
+/// a variable, and a plain insert would silently drop one of the equivalences.
+/// Returns false if the row contradicts the equivalences found so far.
+fn add_equivalence(replacements: &mut HashMap<usize, (usize, bool)>, a: usize, b: usize, parity: bool) -> bool {
+    let ra = apply_replacements(&[Lit::new(a, false)], replacements)[0];
+    let rb = apply_replacements(&[Lit::new(b, false)], replacements)[0];
+    // a = ra ^ neg(ra), b = rb ^ neg(rb), so ra ^ rb = parity ^ neg(ra) ^ neg(rb)
+    let invert = parity ^ ra.is_neg() ^ rb.is_neg();
+    if ra.var() == rb.var() { return !invert; }
+    let (target, source) = if ra.var() > rb.var() { (ra.var(), rb.var()) } else { (rb.var(), ra.var()) };
+    replacements.insert(target, (source, invert));
+    true
+}
 
-    (xors, used_clauses.into_iter().collect())
+/// Counters of a Gaussian elimination pass (reported in verbose mode).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GaussStats {
+    pub xors: usize,
+    pub units: usize,
+    pub substituted: usize,
+    pub removed: usize, // XOR defining clauses replaced by the reduced rows
+    pub aux_vars: usize, // Fresh variables linking the pieces of cut rows
 }
 
-/// Helper function to check truth table logic.
-/// Verifies if 'target' is functionally determined by 'inputs' as an XOR relationship.
-fn check_semantic_xor_logic(
-    target: usize, 
-    inputs: &[usize], 
-    relevant_clause_indices: &[usize], 
-    clauses: &Vec<Clause>
-) -> Option<bool> {
-    let num_inputs = inputs.len();
-    let limit = 1 << num_inputs;
-    
-    // We want to determine if: target == parity(inputs) ^ C
-    // 'relation' will store (target_val ^ input_parity). This must be constant.
-    let mut expected_relation: Option<bool> = None; 
-
-    for i in 0..limit {
-        // 1. Build Input Assignment and calculate input parity
-        let mut input_parity = false;
-        let mut current_assignment = HashMap::with_capacity(num_inputs);
-        
-        for (bit, &inp_var) in inputs.iter().enumerate() {
-            let val = (i >> bit) & 1 == 1;
-            current_assignment.insert(inp_var, val);
-            if val { input_parity = !input_parity; }
+impl Solver {
+    /// Runs `preprocess` on the clauses restricted to the root assignment and installs the result.
+    /// Substituted variables go through `apply_substitution`, so they are recorded for model
+    /// reconstruction and blocked from coming back in later clauses.
+    /// The removed defining clauses are kept to validate models found afterwards in debug builds.
+    /// Must be called at decision level 0; sets `ok = false` if the formula is UNSAT.
+    pub fn gaussian_elimination(&mut self) -> GaussStats {
+        let mut stats = GaussStats::default();
+        if !self.ok || self.decision_level() != 0 { return stats; }
+        if self.propagate().is_some() {
+            self.ok = false;
+            return stats;
         }
 
-        // 2. Check what the clauses force 'target' to be
-        let mut forced_true = false; 
-        let mut forced_false = false;
-
-        for &c_idx in relevant_clause_indices {
-            let clause = &clauses[c_idx];
-            
-            // Check if clause is already satisfied by the inputs alone
-            let mut satisfied_by_inputs = false;
-            let mut target_lit: Option<Lit> = None;
-
-            for lit in &clause.lits {
-                if lit.var() == target {
-                    target_lit = Some(*lit);
-                } else if let Some(&val) = current_assignment.get(&lit.var()) {
-                    // Lit is satisfied if (val=True & !neg) or (val=False & neg)
-                    if (val && !lit.is_neg()) || (!val && lit.is_neg()) {
-                        satisfied_by_inputs = true;
-                        break; 
-                    }
-                }
-            }
-
-            if satisfied_by_inputs {
-                continue; // Clause provides no info about target for this assignment
-            }
-
-            // If not satisfied by inputs, 'target' MUST satisfy this clause.
-            if let Some(t_lit) = target_lit {
-                if t_lit.is_neg() {
-                    // Literal is '-target'. To satisfy clause, '-target' must be True => target must be False.
-                    forced_false = true;
-                } else {
-                    // Literal is '+target'. target must be True.
-                    forced_true = true;
-                }
-            } else {
-                // Clause has no target lit, and is not satisfied by inputs.
-                // This means this input combination is strictly forbidden.
-                // An XOR gate must be total (defined for all inputs), so this isn't an XOR.
-                return None; 
-            }
+        let clauses = self.root_clauses();
+        let (num_vars, max_size, cut_size) = (self.num_vars, self.options.xor_max_size, self.options.xor_cut_size);
+        let Some(result) = preprocess(&clauses, num_vars, max_size, cut_size, &mut || self.new_var()) else { return stats };
+        stats = GaussStats {
+            xors: result.xors.len(),
+            units: result.units.len(),
+            substituted: result.replacements.len(),
+            removed: result.removed.len(),
+            aux_vars: result.aux_vars,
+        };
+        if result.removed.is_empty() && result.units.is_empty() && result.replacements.is_empty() {
+            if self.options.xor_propagation && self.proof.is_none() { self.keep_xors(result.xors); }
+            return stats;
         }
 
-        // 3. Analyze forcing results
-        if forced_true && forced_false {
-            return None; // Conflict: Logic is UNSAT for this input combo.
-        }
-        if !forced_true && !forced_false {
-            return None; // Under-constrained: Not a functional definition (target can be anything).
+        // With a proof, the result is only used if the reduction can be derived in it
+        if self.proof.is_some() && !self.log_xor_elimination(&result.xors, &result.cuts) {
+            self.log_sync();
+            return GaussStats { xors: stats.xors, ..GaussStats::default() };
         }
+        if self.options.xor_propagation && self.proof.is_none() { self.keep_xors(result.xors); }
 
-        let target_val = forced_true;
-        
-        // Calculate the relation: target_val ^ input_parity
-        // If this is constant for all rows, we have an XOR (or XNOR).
-        let relation = target_val ^ input_parity; 
+        // The removed clauses are implied by the reduced rows
+        self.validation_clauses.extend(result.removed.into_iter().filter(|c| !c.learned).map(|c| c.lits));
 
-        if let Some(existing_rel) = expected_relation {
-            if existing_rel != relation {
-                return None; // Inconsistent logic (not an XOR).
-            }
-        } else {
-            expected_relation = Some(relation);
+        self.replace_clause_database(result.clauses);
+        for unit in result.units {
+            if !self.add_clause_internal(vec![unit], false) { return stats; }
         }
+        if !result.replacements.is_empty() {
+            self.apply_substitution(result.replacements);
+        }
+        stats
     }
-
-    // If relation is true: target != parity => target ^ parity = 1 => Odd Parity (rhs=1)
-    // If relation is false: target == parity => target ^ parity = 0 => Even Parity (rhs=0)
-    expected_relation
 }
 
-fn xor_to_cnf(vars: &[usize], rhs: bool) -> Vec<Clause> {
+/// Direct CNF encoding of an XOR: one clause per falsifying assignment (2^(n-1) clauses).
+pub(crate) fn xor_to_cnf(vars: &[usize], rhs: bool) -> Vec<Clause> {
     let mut result = Vec::new();
     let n = vars.len();
     let total_combs: usize = 1 << n;
@@ -370,7 +364,35 @@ fn xor_to_cnf(vars: &[usize], rhs: bool) -> Vec<Clause> {
     result
 }
 
-fn apply_replacements(lits: &[Lit], map: &HashMap<usize, (usize, bool)>) -> Vec<Lit> {
+/// Cuts a long XOR into a chain of XORs with at most 'cut_size' (>= 3) variables each:
+/// x1 ^ .. ^ xn = rhs becomes x1 ^ .. ^ x(k-1) ^ a1 = 0, a1 ^ xk ^ .. ^ a2 = 0, ..., a(m) ^ .. ^ xn = rhs
+/// where the auxiliary variables a1.. are taken from 'new_var'.
+pub fn cut_xor(vars: &[usize], rhs: bool, cut_size: usize, new_var: &mut dyn FnMut() -> usize) -> Vec<XorConstraint> {
+    let cut_size = cut_size.max(3);
+    let mut pieces = Vec::new();
+    let mut rest = vars;
+    let mut carry: Option<usize> = None;
+    loop {
+        let room = cut_size - carry.is_some() as usize;
+        if rest.len() <= room {
+            let mut piece: Vec<usize> = carry.into_iter().collect();
+            piece.extend_from_slice(rest);
+            pieces.push(XorConstraint { vars: piece, rhs });
+            return pieces;
+        }
+        // Leave room for the auxiliary variable that carries the partial sum
+        let (head, tail) = rest.split_at(room - 1);
+        let aux = new_var();
+        let mut piece: Vec<usize> = carry.into_iter().collect();
+        piece.extend_from_slice(head);
+        piece.push(aux);
+        pieces.push(XorConstraint { vars: piece, rhs: false });
+        carry = Some(aux);
+        rest = tail;
+    }
+}
+
+pub(crate) fn apply_replacements(lits: &[Lit], map: &HashMap<usize, (usize, bool)>) -> Vec<Lit> {
     let mut new_lits = Vec::with_capacity(lits.len());
     for &lit in lits {
         let mut var = lit.var();
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use crate::{BranchingStrategy, Lit, Solver, VarValue};
+
+// =========================================================================
+// Failed Literal Probing with Hyper-Binary Resolution
+// =========================================================================
+
+/// Counters of a single probing round (reported in verbose mode).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProbeStats {
+    pub probed: usize,
+    pub failed: usize,         // Probes that ran into a conflict -> negation is a unit
+    pub lifted: usize,         // Units implied by both polarities of a probe
+    pub hyper_binaries: usize, // Binary resolvents added during probing
+}
+
+/// Result of assigning a single probe literal at a fresh decision level.
+enum ProbeOutcome {
+    Failed,
+    Implied(Vec<Lit>),
+}
+
+impl Solver {
+    /// Failed literal probing on the roots of the binary implication graph.
+    ///
+    /// Every candidate is assigned at decision level 1 and propagated:
+    /// - a conflict makes its negation a root-level unit,
+    /// - literals implied by both polarities of a candidate are learned as units,
+    /// - implications through long clauses are shortcut by hyper-binary resolvents.
+    ///
+    /// The number of propagated literals is bounded by `options.probe_budget`.
+    /// Must be called at decision level 0; sets `ok = false` if the formula is UNSAT.
+    pub fn probe(&mut self, strategy: &mut dyn BranchingStrategy) -> ProbeStats {
+        let mut stats = ProbeStats::default();
+        if !self.ok || self.decision_level() != 0 { return stats; }
+        if self.propagate().is_some() {
+            self.ok = false;
+            return stats;
+        }
+
+        let mut budget = self.options.probe_budget;
+        let mut marked = vec![false; self.num_vars * 2];
+
+        for root in self.probe_candidates() {
+            if budget == 0 || !self.ok { break; }
+            if Self::value_lit(&self.assignments, root) != VarValue::Unassigned { continue; }
+            stats.probed += 1;
+
+            // 1. Probe the root literal itself
+            let first = match self.probe_lit(root, strategy, &mut budget, &mut stats) {
+                ProbeOutcome::Failed => {
+                    stats.failed += 1;
+                    self.add_clause_internal(vec![root.not()], false);
+                    continue;
+                }
+                ProbeOutcome::Implied(lits) => lits,
+            };
+
+            // 2. Probe the opposite polarity and intersect the implied literals
+            if Self::value_lit(&self.assignments, root) != VarValue::Unassigned { continue; }
+            match self.probe_lit(root.not(), strategy, &mut budget, &mut stats) {
+                ProbeOutcome::Failed => {
+                    stats.failed += 1;
+                    self.add_clause_internal(vec![root], false);
+                }
+                ProbeOutcome::Implied(second) => {
+                    for &l in &first { marked[l.to_usize()] = true; }
+                    for &l in &second {
+                        if marked[l.to_usize()] && Self::value_lit(&self.assignments, l) == VarValue::Unassigned {
+                            stats.lifted += 1;
+                            // Both cases of the probe in the proof, resolved into the unit
+                            let cases = [[root.not(), l], [root, l]];
+                            for case in &cases { self.log_add(case); }
+                            let ok = self.add_clause_internal(vec![l], false);
+                            for case in &cases { self.log_delete(case); }
+                            if !ok { break; }
+                        }
+                    }
+                    for &l in &first { marked[l.to_usize()] = false; }
+                }
+            }
+        }
+        stats
+    }
+
+    /// Candidate probes: unassigned literals without incoming edges in the
+    /// binary implication graph that still have outgoing edges.
+    fn probe_candidates(&self) -> Vec<Lit> {
+        let mut occurs_in_binary = vec![false; self.num_vars * 2];
+        for [a, b] in self.root_binary_clauses() {
+            occurs_in_binary[a.to_usize()] = true;
+            occurs_in_binary[b.to_usize()] = true;
+        }
+
+        // Clause (a v b) yields the edges -a -> b and -b -> a.
+        // A literal 'l' is a root if it never occurs in a binary clause (no incoming edge)
+        // while '-l' does (at least one outgoing edge).
+        (0..self.num_vars * 2)
+            .map(|code| Lit::new(code >> 1, code & 1 == 1))
+            .filter(|&l| self.assignments[l.var()] == VarValue::Unassigned)
+            .filter(|&l| !occurs_in_binary[l.to_usize()] && occurs_in_binary[l.not().to_usize()])
+            .collect()
+    }
+
+    /// Assigns 'probe' at decision level 1, propagates and returns to level 0.
+    /// Hyper-binary resolvents found on the way are added after backtracking.
+    fn probe_lit(
+        &mut self,
+        probe: Lit,
+        strategy: &mut dyn BranchingStrategy,
+        budget: &mut usize,
+        stats: &mut ProbeStats,
+    ) -> ProbeOutcome {
+        let start = self.trail.len();
+        self.trail_lim.push(start);
+        self.unchecked_enqueue(probe, None);
+        let conflict = self.propagate();
+        *budget = budget.saturating_sub(self.trail.len() - start);
+
+        if conflict.is_some() {
+            self.backtrack(0, strategy);
+            return ProbeOutcome::Failed;
+        }
+
+        let implied: Vec<Lit> = self.trail[start + 1..].to_vec();
+        let resolvents = self.hyper_binary_resolvents(start);
+        self.backtrack(0, strategy);
+
+        for (dominator, lit) in resolvents {
+            stats.hyper_binaries += 1;
+            if !self.add_clause_internal(vec![dominator.not(), lit], false) { break; }
+        }
+        ProbeOutcome::Implied(implied)
+    }
+
+    /// Walks the trail of the current probe and builds the implication tree rooted at the probe.
+    /// A literal forced by a long clause is attached to the dominator (lowest common ancestor)
+    /// of the literals that falsified the rest of the clause. The binary clause
+    /// (-dominator v lit) is a hyper-binary resolvent; we only keep it if the reason clause
+    /// had at least two literals falsified at the probe level, i.e. it shortcuts a real step.
+    /// This includes resolvents dominated by the probe itself: the probe propagates its binary
+    /// clauses first, so a literal it implies by a binary clause never has a long reason.
+    fn hyper_binary_resolvents(&self, start: usize) -> Vec<(Lit, Lit)> {
+        // parent/depth in the implication tree, keyed by variable
+        let mut tree: HashMap<usize, (Lit, usize)> = HashMap::new();
+        let root = self.trail[start];
+        tree.insert(root.var(), (root, 0));
+
+        let mut resolvents = Vec::new();
+        for &lit in &self.trail[start + 1..] {
+            let Some(reason) = self.reason[lit.var()] else { continue };
+
+            // The antecedents are the negations of the other (falsified) literals in the reason
+            let antecedents: Vec<Lit> = self.reason_lits(reason, Some(lit)).iter()
+                .filter(|l| l.var() != lit.var() && self.level[l.var()] > 0)
+                .map(|l| l.not())
+                .collect();
+
+            let mut dominator = match antecedents.first() {
+                Some(&a) => a,
+                None => root,
+            };
+            for &a in antecedents.iter().skip(1) {
+                dominator = Self::lowest_common_ancestor(&tree, dominator, a);
+            }
+
+            let depth = tree.get(&dominator.var()).map_or(0, |&(_, d)| d) + 1;
+            tree.insert(lit.var(), (dominator, depth));
+
+            if antecedents.len() >= 2 {
+                resolvents.push((dominator, lit));
+            }
+        }
+        resolvents
+    }
+
+    fn lowest_common_ancestor(tree: &HashMap<usize, (Lit, usize)>, mut a: Lit, mut b: Lit) -> Lit {
+        let depth = |l: Lit| tree.get(&l.var()).map_or(0, |&(_, d)| d);
+        let parent = |l: Lit| tree.get(&l.var()).map_or(l, |&(p, _)| p);
+        while a != b {
+            let (da, db) = (depth(a), depth(b));
+            if da == 0 && db == 0 { break; } // Both at the root of the tree
+            if da >= db { a = parent(a); }
+            if db >= da { b = parent(b); }
+        }
+        a
+    }
+}
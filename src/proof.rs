@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use crate::preprocessing::{xor_blocks, xor_to_cnf, XorConstraint};
+use crate::{Lit, Solver};
+
+// =========================================================================
+// DRAT Proofs
+// =========================================================================
+
+/// Largest number of variables in a pair of XORs whose sum is derived clause by clause.
+/// One summation takes about 2^n proof lines.
+const MAX_PROOF_XOR_SIZE: usize = 12;
+
+/// A DRAT proof being written: added clauses, which a checker verifies by unit propagation
+/// (RUP) or as resolution asymmetric tautologies on their first literal (RAT), and deletions.
+///
+/// Besides units, the proof keeps track of the clauses it currently contains, so that the solver
+/// can delete everything its clause database no longer has after a simplification.
+pub struct Proof {
+    out: Box<dyn Write>,
+    binary: bool,
+    clauses: HashMap<Vec<Lit>, usize>, // Sorted literals -> copies in the proof
+    error: Option<io::Error>,          // First write error, reported by `flush`
+    finished: bool,                    // The empty clause was written
+}
+
+impl Proof {
+    /// A proof written to 'out', in the binary DRAT format if 'binary' is set.
+    pub fn new(out: Box<dyn Write>, binary: bool) -> Self {
+        Proof { out, binary, clauses: HashMap::new(), error: None, finished: false }
+    }
+
+    /// Flushes the output. Returns the first error that occurred while writing.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() { return Err(e); }
+        self.out.flush()
+    }
+
+    /// Flushes the output, keeping an error for `flush`.
+    pub(crate) fn flush_output(&mut self) {
+        if self.error.is_none() && let Err(e) = self.out.flush() { self.error = Some(e); }
+    }
+
+    fn key(lits: &[Lit]) -> Vec<Lit> {
+        let mut key = lits.to_vec();
+        key.sort_by_key(|l| l.to_usize());
+        key.dedup();
+        key
+    }
+
+    fn write(&mut self, tag: u8, lits: &[Lit]) {
+        if self.error.is_some() { return; }
+        let mut buf = Vec::new();
+        if self.binary {
+            // Literals as variable-length integers 2 * (var + 1) + sign
+            buf.push(tag);
+            for &l in lits {
+                let mut code = 2 * (l.var() + 1) + l.is_neg() as usize;
+                while code >= 0x80 {
+                    buf.push((code & 0x7f) as u8 | 0x80);
+                    code >>= 7;
+                }
+                buf.push(code as u8);
+            }
+            buf.push(0);
+        } else {
+            if tag == b'd' { buf.extend_from_slice(b"d "); }
+            for &l in lits {
+                let dimacs = (l.var() + 1) as i64 * if l.is_neg() { -1 } else { 1 };
+                buf.extend_from_slice(format!("{} ", dimacs).as_bytes());
+            }
+            buf.extend_from_slice(b"0\n");
+        }
+        if let Err(e) = self.out.write_all(&buf) { self.error = Some(e); }
+    }
+
+    /// Adds a clause (a RAT clause must have its pivot first).
+    fn add(&mut self, lits: &[Lit]) {
+        if self.finished { return; }
+        self.write(b'a', lits);
+        match lits.len() {
+            0 => self.finished = true,
+            1 => {} // Units are never deleted
+            _ => *self.clauses.entry(Self::key(lits)).or_insert(0) += 1,
+        }
+    }
+
+    /// Deletes one copy of a clause, if the proof has it.
+    fn delete(&mut self, lits: &[Lit]) {
+        if self.finished { return; }
+        let key = Self::key(lits);
+        let Some(count) = self.clauses.get_mut(&key) else { return };
+        *count -= 1;
+        if *count == 0 { self.clauses.remove(&key); }
+        self.write(b'd', lits);
+    }
+
+    fn contains(&self, lits: &[Lit]) -> bool {
+        self.clauses.contains_key(&Self::key(lits))
+    }
+
+    /// Records a clause of the input formula, which the checker reads from the formula file.
+    fn record_input(&mut self, lits: &[Lit]) {
+        if lits.len() > 1 { *self.clauses.entry(Self::key(lits)).or_insert(0) += 1; }
+    }
+}
+
+/// The XOR of the two constraints (variables in both cancel out) and their shared variables.
+fn xor_sum(a: &XorConstraint, b: &XorConstraint) -> (XorConstraint, Vec<usize>) {
+    let mut vars: Vec<usize> = a.vars.iter().chain(&b.vars).copied().collect();
+    vars.sort_unstable();
+    let mut sum = Vec::with_capacity(vars.len());
+    let mut shared = Vec::new();
+    for v in vars {
+        if sum.last() == Some(&v) {
+            sum.pop();
+            shared.push(v);
+        } else {
+            sum.push(v);
+        }
+    }
+    (XorConstraint { vars: sum, rhs: a.rhs ^ b.rhs }, shared)
+}
+
+/// The row operations of Gauss-Jordan elimination on 'rows' (same pivots as `gauss_jordan`,
+/// without reordering the rows), as (row, pivot row) pairs in the order they are applied.
+/// Updates 'rows' in place. Returns None if an operation involves more than
+/// `MAX_PROOF_XOR_SIZE` variables.
+fn row_operations(rows: &mut [XorConstraint], columns: &[usize]) -> Option<Vec<(usize, XorConstraint)>> {
+    let mut operations = Vec::new();
+    let mut is_pivot = vec![false; rows.len()];
+    for &col in columns {
+        let Some(r) = (0..rows.len()).find(|&r| !is_pivot[r] && rows[r].vars.binary_search(&col).is_ok()) else { continue };
+        is_pivot[r] = true;
+        let pivot = rows[r].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i == r || row.vars.binary_search(&col).is_err() { continue; }
+            let (sum, shared) = xor_sum(row, &pivot);
+            if sum.vars.len() + shared.len() > MAX_PROOF_XOR_SIZE { return None; }
+            operations.push((i, pivot.clone()));
+            *row = sum;
+        }
+    }
+    Some(operations)
+}
+
+impl Solver {
+    /// Writes a DRAT proof to 'proof' whenever `solve` finds the formula unsatisfiable.
+    ///
+    /// The proof refers to the clauses added with `add_clause` (and XORs added with `add_xor`
+    /// in their direct CNF encoding) before the first call to `solve`. While a proof is written,
+    /// XOR constraints are not propagated natively, since the Gauss-Jordan matrix cannot explain
+    /// its propagations in DRAT. Must be called before any clause is added.
+    pub fn set_proof(&mut self, proof: Proof) {
+        self.proof = Some(proof);
+    }
+
+    /// Flushes the proof. Returns the first error that occurred while writing it.
+    pub fn flush_proof(&mut self) -> io::Result<()> {
+        match &mut self.proof {
+            Some(proof) => proof.flush(),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn log_add(&mut self, lits: &[Lit]) {
+        if let Some(proof) = &mut self.proof { proof.add(lits); }
+    }
+
+    pub(crate) fn log_delete(&mut self, lits: &[Lit]) {
+        if let Some(proof) = &mut self.proof { proof.delete(lits); }
+    }
+
+    pub(crate) fn log_input(&mut self, lits: &[Lit]) {
+        if let Some(proof) = &mut self.proof { proof.record_input(lits); }
+    }
+
+    /// Logs a clause entering the clause database unless the proof already contains it.
+    /// It must follow from the proof by unit propagation.
+    pub(crate) fn log_stored(&mut self, lits: &[Lit]) {
+        if let Some(proof) = &mut self.proof && (lits.len() < 2 || !proof.contains(lits)) {
+            proof.add(lits);
+        }
+    }
+
+    /// Brings the proof in line with the clause database: logs the new root units (their
+    /// reasons may be deleted next), then deletes every clause the database no longer contains.
+    /// Must be called at decision level 0.
+    pub(crate) fn log_sync(&mut self) {
+        let Some(proof) = &mut self.proof else { return };
+        for &lit in &self.trail[self.proof_units..] { proof.add(&[lit]); }
+        self.proof_units = self.trail.len();
+
+        let mut kept: HashMap<Vec<Lit>, usize> = HashMap::new();
+        for clause in &self.clauses { *kept.entry(Proof::key(&clause.lits)).or_insert(0) += 1; }
+        let stale: Vec<(Vec<Lit>, usize)> = proof.clauses.iter()
+            .filter_map(|(lits, &count)| {
+                let keep = kept.get(lits).copied().unwrap_or(0);
+                (count > keep).then(|| (lits.clone(), count - keep))
+            })
+            .collect();
+        for (lits, count) in stale {
+            for _ in 0..count { proof.delete(&lits); }
+        }
+    }
+
+    /// Derives the direct encodings of the rows that Gaussian elimination reduces 'xors' to, and
+    /// the pieces 'cuts' split long rows into: (row, pieces linked by fresh auxiliary variables).
+    /// The clauses of every XOR in 'xors' must follow from the formula by unit propagation.
+    ///
+    /// Row additions are derived by resolution on the shared variables: every clause of the sum,
+    /// extended by each sign pattern of all shared variables but one, follows by unit propagation,
+    /// and resolving the extensions away yields the clause itself. The pieces defining auxiliary
+    /// variables are RAT on them, the last piece is the sum of the row and the other pieces.
+    /// Returns false, before logging anything, if a summation involves more than
+    /// `MAX_PROOF_XOR_SIZE` variables.
+    pub(crate) fn log_xor_elimination(&mut self, xors: &[XorConstraint], cuts: &[(XorConstraint, Vec<XorConstraint>)]) -> bool {
+        // Plan all summations first, so that a derivation is either complete or not started
+        let mut blocks = Vec::new();
+        for (columns, bit_rows) in xor_blocks(xors, self.num_vars) {
+            let rows: Vec<XorConstraint> = bit_rows.iter()
+                .map(|row| XorConstraint { vars: row.ones().map(|c| columns[c]).collect(), rhs: row.rhs })
+                .collect();
+            let mut reduced = rows.clone();
+            let Some(operations) = row_operations(&mut reduced, &columns) else { return false };
+            blocks.push((rows, operations));
+        }
+        for (row, pieces) in cuts {
+            let mut partial = row.clone();
+            for piece in &pieces[..pieces.len() - 1] {
+                let (sum, shared) = xor_sum(&partial, piece);
+                if sum.vars.len() + shared.len() > MAX_PROOF_XOR_SIZE { return false; }
+                partial = sum;
+            }
+        }
+
+        for (mut rows, operations) in blocks {
+            for row in &rows {
+                for clause in xor_to_cnf(&row.vars, row.rhs) { self.log_add(&clause.lits); }
+            }
+            for (i, pivot) in operations {
+                let sum = self.log_xor_sum(&rows[i], &pivot);
+                self.log_xor_deletion(&rows[i]);
+                rows[i] = sum;
+            }
+        }
+
+        for (row, pieces) in cuts {
+            let (last, linked) = pieces.split_last().unwrap();
+            for piece in linked {
+                // The auxiliary variable is the last one of its defining piece
+                let aux = *piece.vars.last().unwrap();
+                for mut clause in xor_to_cnf(&piece.vars, piece.rhs) {
+                    let pivot = clause.lits.iter().position(|l| l.var() == aux).unwrap();
+                    clause.lits.swap(0, pivot);
+                    self.log_add(&clause.lits);
+                }
+            }
+            let mut partial = row.clone();
+            for piece in linked {
+                let sum = self.log_xor_sum(&partial, piece);
+                self.log_xor_deletion(&partial);
+                partial = sum;
+            }
+            debug_assert!(partial.vars == last.vars && partial.rhs == last.rhs);
+        }
+        true
+    }
+
+    /// Derives the direct encoding of the sum of two XORs with at least one shared variable,
+    /// whose encodings are in the proof. Returns the sum.
+    fn log_xor_sum(&mut self, a: &XorConstraint, b: &XorConstraint) -> XorConstraint {
+        let (sum, shared) = xor_sum(a, b);
+        for clause in xor_to_cnf(&sum.vars, sum.rhs) {
+            let mut lits = clause.lits;
+            self.log_extended(&mut lits, &shared[1..]);
+        }
+        sum
+    }
+
+    /// Adds 'clause' via the clauses extending it by every sign pattern of 'extra',
+    /// deleting the extensions afterwards.
+    fn log_extended(&mut self, clause: &mut Vec<Lit>, extra: &[usize]) {
+        if let Some((&var, rest)) = extra.split_first() {
+            for lit in [Lit::new(var, false), Lit::new(var, true)] {
+                clause.push(lit);
+                self.log_extended(clause, rest);
+                clause.pop();
+            }
+            self.log_add(clause);
+            for lit in [Lit::new(var, false), Lit::new(var, true)] {
+                clause.push(lit);
+                self.log_delete(clause);
+                clause.pop();
+            }
+        } else {
+            self.log_add(clause);
+        }
+    }
+
+    fn log_xor_deletion(&mut self, xor: &XorConstraint) {
+        for clause in xor_to_cnf(&xor.vars, xor.rhs) { self.log_delete(&clause.lits); }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::preprocessing::apply_replacements;
+use crate::{Lit, Solver};
+
+// =========================================================================
+// Model Reconstruction
+// =========================================================================
+
+/// Clauses removed by simplifications that do not preserve models,
+/// each stored together with a "witness" literal.
+///
+/// To extend a model of the simplified formula to the original one we walk
+/// the stack backwards: whenever a stored clause is falsified, its witness is flipped to True.
+#[derive(Debug, Clone, Default)]
+pub struct ReconstructionStack {
+    entries: Vec<Entry>,
+    witnesses: Vec<usize>, // Var -> number of entries witnessed on it
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    witness: Lit,
+    clause: Vec<Lit>,
+    first: bool, // First entry of a removal (see `push_step`)
+}
+
+impl ReconstructionStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a removed clause. The witness must be one of the clause's literals.
+    pub fn push(&mut self, witness: Lit, clause: Vec<Lit>) {
+        self.push_entry(witness, clause, true);
+    }
+
+    /// Records one more entry of the last removal, e.g. the clause extended by covered literal
+    /// addition. Restoring the removal brings back only its first clause.
+    pub fn push_step(&mut self, witness: Lit, clause: Vec<Lit>) {
+        self.push_entry(witness, clause, false);
+    }
+
+    fn push_entry(&mut self, witness: Lit, clause: Vec<Lit>, first: bool) {
+        debug_assert!(clause.contains(&witness));
+        if self.witnesses.len() <= witness.var() { self.witnesses.resize(witness.var() + 1, 0); }
+        self.witnesses[witness.var()] += 1;
+        self.entries.push(Entry { witness, clause, first });
+    }
+
+    /// Records that 'lit' was replaced by 'repr' (lit <-> repr).
+    /// Encoded as the two binary clauses of the equivalence, witnessed on 'lit'.
+    pub fn push_equivalence(&mut self, lit: Lit, repr: Lit) {
+        self.push(lit, vec![lit, repr.not()]);
+        self.push(lit.not(), vec![lit.not(), repr]);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether reconstruction may flip 'var'.
+    pub fn is_witness(&self, var: usize) -> bool {
+        self.witnesses.get(var).is_some_and(|&n| n > 0)
+    }
+
+    /// Takes every removal with an entry witnessed on a 'touched' variable off the stack.
+    /// Returns their first clauses, oldest first.
+    pub fn restore(&mut self, touched: &[bool]) -> Vec<Vec<Lit>> {
+        let mut restored = Vec::new();
+        let mut kept = Vec::with_capacity(self.entries.len());
+        let mut entries = std::mem::take(&mut self.entries).into_iter().peekable();
+        while let Some(first) = entries.next() {
+            let mut removal = vec![first];
+            while let Some(step) = entries.next_if(|e| !e.first) { removal.push(step); }
+            if !removal.iter().any(|e| touched.get(e.witness.var()).copied().unwrap_or(false)) {
+                kept.extend(removal);
+                continue;
+            }
+            for e in &removal { self.witnesses[e.witness.var()] -= 1; }
+            restored.push(removal.swap_remove(0).clause);
+        }
+        self.entries = kept;
+        restored
+    }
+
+    /// Fixes 'model' (indexed by variable) so that all removed clauses are satisfied.
+    pub fn extend_model(&self, model: &mut [bool]) {
+        let is_true = |model: &[bool], l: Lit| model[l.var()] != l.is_neg();
+        for Entry { witness, clause, .. } in self.entries.iter().rev() {
+            if !clause.iter().any(|&l| is_true(model, l)) {
+                model[witness.var()] = !witness.is_neg();
+            }
+        }
+    }
+}
+
+impl Solver {
+    /// Brings back the clauses that blocked clause and variable elimination removed if a new
+    /// constraint or assumption over 'lits' uses a variable that reconstruction may flip, which
+    /// could falsify it. Restored clauses can themselves contain such variables, so this repeats
+    /// until no witness is touched. Must be called at decision level 0.
+    /// Returns false if the formula became UNSAT.
+    pub(crate) fn restore_clauses(&mut self, lits: &[Lit]) -> bool {
+        if self.reconstruction.is_empty() || !self.ok { return self.ok; }
+        let lits = apply_replacements(lits, &self.replacements);
+        if !lits.iter().any(|l| self.reconstruction.is_witness(l.var())) { return true; }
+
+        let mut touched = vec![false; self.num_vars];
+        for l in &lits { touched[l.var()] = true; }
+        loop {
+            let restored = self.reconstruction.restore(&touched);
+            if restored.is_empty() { return true; }
+            for clause in restored {
+                // Variables substituted after the removal are replaced like in new clauses
+                let clause = apply_replacements(&clause, &self.replacements);
+                for l in &clause { touched[l.var()] = true; }
+                if !self.add_clause_internal(clause, false) { return false; }
+            }
+        }
+    }
+}
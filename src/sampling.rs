@@ -0,0 +1,114 @@
+use crate::approxmc::{self, ApproxMcOptions};
+use crate::{Formula, RandomStrategy, Rng, Solver};
+
+// =========================================================================
+// Near-Uniform Solution Sampling: UniGen Cells and Randomised Phases
+// =========================================================================
+
+/// Tolerance of the UniGen cells: a cell is accepted if its size lies between `LO_THRESHOLD`
+/// and `HI_THRESHOLD`, around the pivot 4.03 (1 + 1/kappa)^2 for kappa = 0.638.
+const PIVOT: f64 = 27.0;
+const KAPPA: f64 = 0.638;
+const HI_THRESHOLD: usize = (1.0 + std::f64::consts::SQRT_2 * (1.0 + KAPPA) * PIVOT) as usize;
+const LO_THRESHOLD: usize = (PIVOT / (std::f64::consts::SQRT_2 * (1.0 + KAPPA))) as usize + 1;
+
+/// How `samples` draws solutions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SamplingMode {
+    /// UniGen: a random cell of the solution space cut by random XORs over the sampling set,
+    /// then a uniformly random solution of the cell. Near-uniform over the projections onto the
+    /// sampling set, at the cost of an approximate count and cell enumerations.
+    #[default]
+    Uniform,
+    /// Repeated solving with randomised decision order and polarities: cheap and diverse,
+    /// but without guarantees on the distribution.
+    RandomPhase,
+}
+
+/// Iterator over sampled models of a formula (see `samples`); ends only if it is unsatisfiable.
+pub struct Samples<'a> {
+    formula: &'a Formula,
+    sampling: Vec<usize>,
+    mode: SamplingMode,
+    rng: Rng,
+    state: State,
+}
+
+enum State {
+    Start,
+    Phase(Box<Solver>, RandomStrategy), // RandomPhase: the solver re-solved for every sample
+    Few(Vec<Vec<bool>>),                // Uniform: all solutions, one per projection, no more than the threshold
+    Hashed(usize),                      // Uniform: the number of XORs expected to give cells of pivot size
+    Unsat,
+}
+
+/// Samples models of 'formula' (clauses and XORs), as many as taken from the iterator.
+/// Solutions are distinguished by their projections onto 'sampling' (by default the projection
+/// variables of the formula, or all variables), and the same 'seed' gives the same samples.
+pub fn samples<'a>(formula: &'a Formula, sampling: Option<&[usize]>, mode: SamplingMode, seed: u64) -> Samples<'a> {
+    let sampling = sampling.map(<[usize]>::to_vec)
+        .or_else(|| formula.projection.clone())
+        .unwrap_or_else(|| (0..formula.num_vars).collect());
+    Samples { formula, sampling, mode, rng: Rng::new(seed), state: State::Start }
+}
+
+impl Samples<'_> {
+    fn start(&mut self) -> State {
+        if self.mode == SamplingMode::RandomPhase {
+            let mut solver = Solver::new(self.formula.num_vars);
+            for clause in &self.formula.clauses {
+                solver.add_clause(clause.clone());
+            }
+            for xor in &self.formula.xors {
+                solver.add_xor(xor.clone());
+            }
+            return State::Phase(Box::new(solver), RandomStrategy::with_seed(self.rng.next_u64()));
+        }
+
+        let models = approxmc::cell_models(self.formula, &self.sampling, &[], HI_THRESHOLD + 1);
+        if models.is_empty() { return State::Unsat; }
+        if models.len() <= HI_THRESHOLD { return State::Few(models); }
+
+        // q XORs leave about 1.8 · pivot solutions per cell; cells of q - 4 ..= q XORs are tried
+        let formula = Formula { projection: Some(self.sampling.clone()), ..self.formula.clone() };
+        let options = ApproxMcOptions { seed: self.rng.next_u64(), ..ApproxMcOptions::default() };
+        let estimate = approxmc::approx_count(&formula, &options);
+        let q = (estimate.log2() + 1.8f64.log2() - PIVOT.log2()).ceil().max(0.0) as usize;
+        State::Hashed(q.min(self.sampling.len()))
+    }
+}
+
+/// A uniformly random element of 'models' (not empty).
+fn pick(rng: &mut Rng, models: &[Vec<bool>]) -> Vec<bool> {
+    models[(rng.next_u64() % models.len() as u64) as usize].clone()
+}
+
+impl Iterator for Samples<'_> {
+    type Item = Vec<bool>;
+
+    fn next(&mut self) -> Option<Vec<bool>> {
+        if let State::Start = self.state { self.state = self.start(); }
+        match &mut self.state {
+            State::Start | State::Unsat => None,
+            State::Phase(solver, strategy) => {
+                if !solver.solve(strategy, false) {
+                    self.state = State::Unsat;
+                    return None;
+                }
+                Some(solver.model()[..self.formula.num_vars].to_vec())
+            }
+            State::Few(models) => Some(pick(&mut self.rng, models)),
+            &mut State::Hashed(q) => loop {
+                // Cells shrink as XORs of the hash are added: the first one small enough is
+                // accepted if it is not too small, else a new hash is drawn
+                let hash = approxmc::random_hash(&self.sampling, &mut self.rng);
+                for m in q.saturating_sub(4)..=q {
+                    let cell = approxmc::cell_models(self.formula, &self.sampling, &hash[..m], HI_THRESHOLD + 1);
+                    if cell.len() > HI_THRESHOLD { continue; }
+                    if cell.len() >= LO_THRESHOLD { return Some(pick(&mut self.rng, &cell)); }
+                    break;
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::time::{Duration, Instant};
+
+use crate::{BranchingStrategy, Clause, Lit, Rng, Solver};
+
+// =========================================================================
+// Stochastic Local Search: ProbSAT and WalkSAT with Clause Weighting
+// =========================================================================
+
+/// Flips between two looks at the clock.
+const CLOCK_PERIOD: u64 = 1024;
+
+/// How `LocalSearch` picks the variable to flip in a random falsified clause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SlsAlgorithm {
+    /// ProbSAT: a variable with probability decreasing in its break count, polynomially for
+    /// clauses of up to 3 literals and exponentially for longer ones.
+    #[default]
+    ProbSat,
+    /// WalkSAT (SKC): a variable breaking nothing if there is one, else a random one with
+    /// probability `noise`, else one with the largest make minus break.
+    WalkSat,
+}
+
+impl SlsAlgorithm {
+    /// Weight increases between two smoothing steps of the clause weights. WalkSAT reaches a
+    /// local minimum at almost every greedy step, so its weights must fade much more slowly.
+    fn smoothing_period(self) -> u64 {
+        match self {
+            SlsAlgorithm::ProbSat => 64,
+            SlsAlgorithm::WalkSat => 1024,
+        }
+    }
+}
+
+/// Settings of `LocalSearch::solve`.
+#[derive(Debug, Clone)]
+pub struct SlsOptions {
+    pub algorithm: SlsAlgorithm,
+    /// Seed of the random choices (and of the initial assignment if none is given).
+    pub seed: u64,
+    /// The search gives up after this many flips ...
+    pub max_flips: u64,
+    /// ... or after this time.
+    pub time_limit: Option<Duration>,
+    /// Probability of a random walk step in WalkSAT.
+    pub noise: f64,
+    /// Raise the weights of the falsified clauses at local minima (and smooth them back now and
+    /// then): the make and break counts are sums of weights, steering the search away from
+    /// clauses that stay falsified.
+    pub clause_weighting: bool,
+}
+
+impl Default for SlsOptions {
+    fn default() -> Self {
+        SlsOptions {
+            algorithm: SlsAlgorithm::ProbSat,
+            seed: 1,
+            max_flips: u64::MAX,
+            time_limit: None,
+            noise: 0.2,
+            clause_weighting: true,
+        }
+    }
+}
+
+/// Outcome of `LocalSearch::solve`: a model if 'falsified' is 0, otherwise the best assignment
+/// found, the one falsifying the fewest clauses.
+#[derive(Debug, Clone)]
+pub struct SlsResult {
+    pub assignment: Vec<bool>,
+    pub falsified: usize,
+    pub flips: u64,
+}
+
+/// Local search over a set of clauses. Every clause counts its true literals and keeps the XOR
+/// of their variables, which is the critical variable when only one is true, so that the make
+/// count (weight of the falsified clauses a flip satisfies) and the break count (weight of the
+/// clauses it falsifies) of every variable are maintained incrementally.
+pub struct LocalSearch {
+    clauses: Vec<Vec<Lit>>,       // Without tautologies and duplicate literals
+    empty: usize,                 // Empty clauses: always falsified
+    occurrences: Vec<Vec<usize>>, // Literal -> clauses containing it
+    assignment: Vec<bool>,
+    num_true: Vec<u32>,
+    critical: Vec<usize>, // XOR of the variables of the true literals
+    weights: Vec<u64>,
+    make: Vec<u64>,
+    breaks: Vec<u64>,
+    falsified: Vec<usize>,
+    position: Vec<usize>, // Clause -> index in 'falsified'
+    increases: u64,
+}
+
+impl LocalSearch {
+    /// Local search over 'clauses' (learned ones included) on 'num_vars' variables.
+    pub fn new(num_vars: usize, clauses: &[Clause]) -> Self {
+        let mut kept = Vec::with_capacity(clauses.len());
+        let mut empty = 0;
+        for clause in clauses {
+            let mut lits = clause.lits.clone();
+            lits.sort_by_key(|l| l.to_usize());
+            lits.dedup();
+            if lits.windows(2).any(|w| w[0].var() == w[1].var()) { continue; }
+            if lits.is_empty() { empty += 1; } else { kept.push(lits); }
+        }
+        let mut occurrences = vec![Vec::new(); num_vars * 2];
+        for (c, lits) in kept.iter().enumerate() {
+            for lit in lits { occurrences[lit.to_usize()].push(c); }
+        }
+        let num_clauses = kept.len();
+        LocalSearch {
+            clauses: kept,
+            empty,
+            occurrences,
+            assignment: vec![false; num_vars],
+            num_true: vec![0; num_clauses],
+            critical: vec![0; num_clauses],
+            weights: vec![1; num_clauses],
+            make: vec![0; num_vars],
+            breaks: vec![0; num_vars],
+            falsified: Vec::new(),
+            position: vec![usize::MAX; num_clauses],
+            increases: 0,
+        }
+    }
+
+    /// Searches a model, starting from 'initial' or a random assignment, until one is found or
+    /// a limit of 'options' is reached.
+    pub fn solve(&mut self, initial: Option<&[bool]>, options: &SlsOptions) -> SlsResult {
+        let start = Instant::now();
+        let mut rng = Rng::new(options.seed);
+        let num_vars = self.assignment.len();
+        self.assignment = match initial {
+            Some(initial) => initial[..num_vars].to_vec(),
+            None => (0..num_vars).map(|_| rng.coin()).collect(),
+        };
+        self.weights.fill(1);
+        self.increases = 0;
+        self.initialize();
+
+        let max_length = self.clauses.iter().map(Vec::len).max().unwrap_or(0);
+        let mut best = SlsResult { assignment: self.assignment.clone(), falsified: self.falsified.len() + self.empty, flips: 0 };
+        let mut flips = 0;
+        let mut probabilities = Vec::new();
+        while !self.falsified.is_empty() && flips < options.max_flips {
+            if flips % CLOCK_PERIOD == 0 && options.time_limit.is_some_and(|limit| start.elapsed() >= limit) { break; }
+            let clause = self.falsified[(rng.next_u64() % self.falsified.len() as u64) as usize];
+            let lits = &self.clauses[clause];
+            let var = match options.algorithm {
+                SlsAlgorithm::ProbSat => {
+                    // (1 + break)^-2.38 for 3-SAT, 3^-break for longer clauses (cb of the ProbSAT paper)
+                    probabilities.clear();
+                    probabilities.extend(lits.iter().map(|l| {
+                        let breaks = self.breaks[l.var()] as f64;
+                        if max_length <= 3 { (1.0 + breaks).powf(-2.38) } else { 3f64.powf(-breaks) }
+                    }));
+                    let mut threshold = uniform(&mut rng) * probabilities.iter().sum::<f64>();
+                    let mut chosen = lits.len() - 1;
+                    for (i, p) in probabilities.iter().enumerate() {
+                        if threshold < *p {
+                            chosen = i;
+                            break;
+                        }
+                        threshold -= p;
+                    }
+                    lits[chosen].var()
+                }
+                SlsAlgorithm::WalkSat => {
+                    let vars = lits.iter().map(|l| l.var());
+                    if let Some(freebie) = vars.clone().find(|&v| self.breaks[v] == 0) {
+                        freebie
+                    } else if uniform(&mut rng) < options.noise {
+                        lits[(rng.next_u64() % lits.len() as u64) as usize].var()
+                    } else {
+                        vars.max_by_key(|&v| self.make[v] as i64 - self.breaks[v] as i64).unwrap()
+                    }
+                }
+            };
+            // A local minimum if no variable of the clause gains more than it breaks
+            if options.clause_weighting && self.clauses[clause].iter().all(|l| self.make[l.var()] <= self.breaks[l.var()]) {
+                self.increase_weights(options.algorithm.smoothing_period());
+            }
+            self.flip(var);
+            flips += 1;
+
+            if self.falsified.len() + self.empty < best.falsified {
+                best.assignment.copy_from_slice(&self.assignment);
+                best.falsified = self.falsified.len() + self.empty;
+            }
+        }
+        best.flips = flips;
+        best
+    }
+
+    /// Computes the counts, critical variables and falsified clauses of the assignment.
+    fn initialize(&mut self) {
+        self.make.fill(0);
+        self.breaks.fill(0);
+        self.falsified.clear();
+        self.position.fill(usize::MAX);
+        for c in 0..self.clauses.len() {
+            let (mut num_true, mut critical) = (0, 0);
+            for lit in &self.clauses[c] {
+                if self.assignment[lit.var()] != lit.is_neg() {
+                    num_true += 1;
+                    critical ^= lit.var();
+                }
+            }
+            self.num_true[c] = num_true;
+            self.critical[c] = critical;
+            match num_true {
+                0 => {
+                    self.position[c] = self.falsified.len();
+                    self.falsified.push(c);
+                    for lit in &self.clauses[c] { self.make[lit.var()] += self.weights[c]; }
+                }
+                1 => self.breaks[critical] += self.weights[c],
+                _ => {}
+            }
+        }
+    }
+
+    fn flip(&mut self, var: usize) {
+        let old = self.assignment[var];
+        self.assignment[var] = !old;
+        let made_true = Lit::new(var, old);
+        for i in 0..self.occurrences[made_true.to_usize()].len() {
+            let c = self.occurrences[made_true.to_usize()][i];
+            let weight = self.weights[c];
+            self.num_true[c] += 1;
+            self.critical[c] ^= var;
+            match self.num_true[c] {
+                1 => {
+                    self.remove_falsified(c);
+                    for lit in &self.clauses[c] { self.make[lit.var()] -= weight; }
+                    self.breaks[var] += weight;
+                }
+                2 => self.breaks[self.critical[c] ^ var] -= weight,
+                _ => {}
+            }
+        }
+        let made_false = made_true.not();
+        for i in 0..self.occurrences[made_false.to_usize()].len() {
+            let c = self.occurrences[made_false.to_usize()][i];
+            let weight = self.weights[c];
+            self.num_true[c] -= 1;
+            self.critical[c] ^= var;
+            match self.num_true[c] {
+                0 => {
+                    self.position[c] = self.falsified.len();
+                    self.falsified.push(c);
+                    for lit in &self.clauses[c] { self.make[lit.var()] += weight; }
+                    self.breaks[var] -= weight;
+                }
+                1 => self.breaks[self.critical[c]] += weight,
+                _ => {}
+            }
+        }
+    }
+
+    fn remove_falsified(&mut self, c: usize) {
+        let i = self.position[c];
+        let last = self.falsified.pop().unwrap();
+        if last != c {
+            self.falsified[i] = last;
+            self.position[last] = i;
+        }
+        self.position[c] = usize::MAX;
+    }
+
+    /// Raises the weight of every falsified clause by one. Periodically, every heavier clause
+    /// loses one instead, so that old weights fade.
+    fn increase_weights(&mut self, smoothing_period: u64) {
+        for &c in &self.falsified {
+            self.weights[c] += 1;
+            for lit in &self.clauses[c] { self.make[lit.var()] += 1; }
+        }
+        self.increases += 1;
+        if !self.increases.is_multiple_of(smoothing_period) { return; }
+        for c in 0..self.clauses.len() {
+            if self.weights[c] == 1 { continue; }
+            self.weights[c] -= 1;
+            match self.num_true[c] {
+                0 => for lit in &self.clauses[c] { self.make[lit.var()] -= 1; },
+                1 => self.breaks[self.critical[c]] -= 1,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A random number in [0, 1).
+fn uniform(rng: &mut Rng) -> f64 {
+    (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl Solver {
+    /// Whether the conflicts since the last rephasing reached the schedule of `options.rephase_interval`.
+    pub(crate) fn rephase_due(&self) -> bool {
+        let k = self.rephases + 1;
+        self.conflicts >= self.options.rephase_interval.saturating_mul(k * (k + 1) / 2)
+    }
+
+    /// Restarts and runs local search over the clauses left at the root, learned ones included,
+    /// from the saved phases. Its best assignment becomes the target phases of later decisions.
+    /// Called only once propagation is complete, since backtracking drops the pending propagations.
+    pub(crate) fn rephase(&mut self, strategy: &mut dyn BranchingStrategy, verbose: bool) {
+        self.backtrack(0, strategy);
+        self.rephases += 1;
+        let options = SlsOptions { seed: self.rephases, max_flips: self.options.rephase_flips, ..SlsOptions::default() };
+        let result = LocalSearch::new(self.num_vars, &self.root_clauses()).solve(Some(&self.saved_phases), &options);
+        if verbose {
+            println!("Rephasing {} after {} conflicts: local search leaves {} clauses falsified", self.rephases, self.conflicts, result.falsified);
+        }
+        self.target_phases = result.assignment;
+    }
+}
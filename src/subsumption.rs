@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use crate::occurrences::ClauseDatabase;
+use crate::Solver;
+
+// =========================================================================
+// Subsumption and Self-Subsuming Resolution
+// =========================================================================
+
+/// Number of literals visited in candidate clauses before the pass stops.
+const SUBSUMPTION_BUDGET: usize = 20_000_000;
+
+/// Counters of a subsumption round (reported in verbose mode).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SubsumptionStats {
+    pub subsumed: usize,     // Clauses removed because they contain another clause
+    pub strengthened: usize, // Literals removed by self-subsuming resolution
+}
+
+impl Solver {
+    /// Backward subsumption and self-subsuming resolution with the irredundant clauses.
+    ///
+    /// A clause C subsumes every clause D containing it, which is removed. If D contains C with
+    /// exactly one literal negated, resolving them yields D without that literal, which replaces D.
+    /// Both keep the set of models, so nothing is pushed onto the reconstruction stack.
+    /// Strengthened clauses are tried again as subsumers. Must be called at decision level 0.
+    pub fn subsume(&mut self) -> SubsumptionStats {
+        let mut stats = SubsumptionStats::default();
+        self.with_clause_database(|solver, db| {
+            stats = solver.subsume_in(db);
+            stats.subsumed + stats.strengthened > 0
+        });
+        stats
+    }
+
+    /// `subsume` on a clause database shared with other passes (see `PassContext`).
+    pub(crate) fn subsume_in(&mut self, db: &mut ClauseDatabase) -> SubsumptionStats {
+        let mut stats = SubsumptionStats::default();
+        let mut order: Vec<usize> = (0..db.clauses.len()).filter(|&i| !db.learned[i]).collect();
+        order.sort_by_key(|&i| db.clauses[i].as_ref().map_or(0, |c| c.len()));
+        let mut queued = vec![false; db.clauses.len()];
+        for &i in &order { queued[i] = true; }
+        let mut queue: VecDeque<usize> = order.into();
+        let mut marks = vec![false; self.num_vars * 2];
+        let mut budget = SUBSUMPTION_BUDGET;
+
+        while let Some(idx) = queue.pop_front() {
+            queued[idx] = false;
+            if budget == 0 { break; }
+            let Some(clause) = db.clauses[idx].clone() else { continue };
+
+            // Every candidate contains the variable of C with the fewest occurrences
+            let pivot = *clause.iter().min_by_key(|l| db.live[l.to_usize()] + db.live[l.not().to_usize()]).unwrap();
+            let candidates: Vec<usize> = db.occurrences(pivot).chain(db.occurrences(pivot.not()))
+                .filter(|&d| d != idx)
+                .collect();
+            for &l in &clause { marks[l.to_usize()] = true; }
+
+            for d in candidates {
+                let Some(other) = &db.clauses[d] else { continue };
+                if other.len() < clause.len() { continue; }
+                budget = budget.saturating_sub(other.len());
+
+                let matched = other.iter().filter(|l| marks[l.to_usize()]).count();
+                let mut negated = other.iter().filter(|l| marks[l.not().to_usize()]);
+                match (negated.next(), negated.next()) {
+                    (None, _) if matched == clause.len() => {
+                        db.remove(d);
+                        stats.subsumed += 1;
+                    }
+                    (Some(&lit), None) if matched + 1 == clause.len() => {
+                        db.strengthen(d, lit);
+                        stats.strengthened += 1;
+                        let strengthened = db.clauses[d].as_ref().unwrap();
+                        self.log_add(strengthened);
+                        if strengthened.is_empty() {
+                            self.ok = false;
+                            return stats;
+                        }
+                        if !db.learned[d] && !queued[d] {
+                            queued[d] = true;
+                            queue.push_back(d);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            for &l in &clause { marks[l.to_usize()] = false; }
+        }
+        stats
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use crate::preprocessing::XorConstraint;
+use crate::Clause;
+
+// =========================================================================
+// Syntactic XOR Extraction
+// =========================================================================
+
+/// The sign patterns are enumerated explicitly, so the size has to stay small.
+const MAX_SUPPORTED_SIZE: usize = 16;
+
+/// An XOR constraint recovered from the clause database.
+#[derive(Debug, Clone)]
+pub struct ExtractedXor {
+    pub xor: XorConstraint,
+    /// Indices of the clauses that together encode the XOR.
+    pub clauses: Vec<usize>,
+    /// The subset of 'clauses' over exactly the XOR's variables. They are implied by the XOR,
+    /// while the other (shorter) clauses are stronger and must stay in the formula.
+    pub defining: Vec<usize>,
+}
+
+/// Finds XORs over 3 to 'max_size' variables.
+///
+/// An XOR over n variables is encoded by the 2^(n-1) clauses over its variables whose number of
+/// negated literals has the right parity. Every clause is tried as the base of such an XOR: the
+/// clauses over subsets of its variables are collected as bit patterns, and each of the 2^(n-1)
+/// sign patterns must be covered by one of them. A pattern may also be covered by a shorter clause
+/// whose literals all occur in it, so XORs whose encoding was partly strengthened are found too.
+pub fn find_xors(clauses: &[Clause], num_vars: usize, max_size: usize) -> Vec<ExtractedXor> {
+    let max_size = max_size.min(MAX_SUPPORTED_SIZE);
+    let mut occurs: Vec<Vec<usize>> = vec![Vec::new(); num_vars];
+    let mut lit_count = vec![0usize; num_vars * 2];
+    for (i, c) in clauses.iter().enumerate() {
+        if c.lits.len() > max_size { continue; }
+        for lit in &c.lits {
+            occurs[lit.var()].push(i);
+            lit_count[lit.to_usize()] += 1;
+        }
+    }
+
+    let mut found = Vec::new();
+    let mut done: HashSet<(Vec<usize>, bool)> = HashSet::new();
+    let mut stamp = vec![usize::MAX; clauses.len()];
+
+    for (base, clause) in clauses.iter().enumerate() {
+        let n = clause.lits.len();
+        if n < 3 || n > max_size { continue; }
+        // Every variable of an XOR occurs in both polarities
+        if clause.lits.iter().any(|l| lit_count[l.not().to_usize()] == 0) { continue; }
+
+        let mut vars: Vec<usize> = clause.lits.iter().map(|l| l.var()).collect();
+        vars.sort_unstable();
+        if vars.windows(2).any(|w| w[0] == w[1]) { continue; }
+
+        // Clause with sign pattern s forbids the assignment s, the XOR forbids one parity class
+        let base_pattern = sign_pattern(clause, &vars).1;
+        let rhs = base_pattern.count_ones().is_multiple_of(2);
+        let key = (vars, rhs);
+        if done.contains(&key) { continue; }
+        let (vars, rhs) = key;
+
+        // Clauses over subsets of 'vars' as (variable mask, sign mask). Most bases are not XORs,
+        // so we first only look at the shortest occurrence list and scan the others
+        // if at least half of the patterns are covered already.
+        let mut candidates: Vec<(usize, u64, u64)> = Vec::new();
+        let mut collect = |v: usize, candidates: &mut Vec<(usize, u64, u64)>| {
+            for &idx in &occurs[v] {
+                if stamp[idx] == base { continue; }
+                stamp[idx] = base;
+                let other = &clauses[idx];
+                if other.lits.len() > n || !other.lits.iter().all(|l| vars.binary_search(&l.var()).is_ok()) { continue; }
+                let (mask, signs) = sign_pattern(other, &vars);
+                candidates.push((idx, mask, signs));
+            }
+        };
+        let shortest = *vars.iter().min_by_key(|&&v| occurs[v].len()).unwrap();
+        collect(shortest, &mut candidates);
+        let required = 1usize << (n - 1);
+        let mut used = cover(&candidates, n, rhs);
+        if used.len() < required && 2 * used.len() >= required {
+            for &v in &vars {
+                if v != shortest { collect(v, &mut candidates); }
+            }
+            used = cover(&candidates, n, rhs);
+        }
+        if used.len() < required { continue; }
+        done.insert((vars.clone(), rhs));
+
+        used.sort_unstable();
+        used.dedup();
+        let defining = used.iter().copied().filter(|&i| clauses[i].lits.len() == n).collect();
+        found.push(ExtractedXor { xor: XorConstraint { vars, rhs }, clauses: used, defining });
+    }
+    found
+}
+
+/// For every sign pattern of the parity class forbidden by the XOR, the first candidate covering it.
+/// Uncovered patterns are skipped, so the result is complete iff it has 2^(n-1) entries.
+fn cover(candidates: &[(usize, u64, u64)], n: usize, rhs: bool) -> Vec<usize> {
+    (0..(1u64 << n))
+        .filter(|pattern| pattern.count_ones().is_multiple_of(2) == rhs)
+        .filter_map(|pattern| candidates.iter().find(|&&(_, mask, signs)| pattern & mask == signs).map(|&(idx, _, _)| idx))
+        .collect()
+}
+
+/// Bit masks of the clause's variables and negated literals, relative to the positions in 'vars'.
+/// Clauses with both polarities of a variable get an inconsistent pattern and never match.
+fn sign_pattern(clause: &Clause, vars: &[usize]) -> (u64, u64) {
+    let mut mask = 0u64;
+    let mut signs = 0u64;
+    for lit in &clause.lits {
+        let bit = 1u64 << vars.binary_search(&lit.var()).unwrap();
+        if mask & bit != 0 && (signs & bit != 0) != lit.is_neg() { return (mask, u64::MAX); }
+        mask |= bit;
+        if lit.is_neg() { signs |= bit; }
+    }
+    (mask, signs)
+}